@@ -0,0 +1,61 @@
+use criterion::{black_box, criterion_group, criterion_main, Criterion};
+use jops::json_path::{JsonPath, JsonPathQuery, JsonPathRef};
+use serde_json::json;
+
+const QUERY: &str = "$.data.items[#-1].attributes.name";
+
+fn lookup(c: &mut Criterion) {
+    let value = json!({
+        "data": {
+            "items": [
+                { "attributes": { "name": "first" } },
+                { "attributes": { "name": "last" } }
+            ]
+        }
+    });
+    let owned = JsonPath::try_from(QUERY).unwrap();
+    let borrowed = JsonPathRef::parse(QUERY).unwrap();
+
+    let mut group = c.benchmark_group("lookup");
+    group.bench_function("JsonPath parse and find", |b| {
+        b.iter(|| {
+            let path = JsonPath::try_from(black_box(QUERY)).unwrap();
+            path.find(&value).is_some()
+        })
+    });
+    group.bench_function("JsonPathRef parse and find", |b| {
+        b.iter(|| {
+            let path = JsonPathRef::parse(black_box(QUERY)).unwrap();
+            path.find(&value).is_some()
+        })
+    });
+    group.bench_function("JsonPathQuery::path", |b| {
+        b.iter(|| value.path(black_box(QUERY)).is_ok())
+    });
+    group.bench_function("JsonPath find", |b| {
+        b.iter(|| black_box(&owned).find(&value).is_some())
+    });
+    group.bench_function("JsonPathRef find", |b| {
+        b.iter(|| black_box(&borrowed).find(&value).is_some())
+    });
+    group.finish();
+}
+
+fn mutation(c: &mut Criterion) {
+    let mut value = json!({ "data": { "items": [{ "name": "first" }, { "name": "last" }] } });
+    let query = "$.data.items[1].name";
+    let owned = JsonPath::try_from(query).unwrap();
+    let borrowed = JsonPathRef::parse(query).unwrap();
+
+    let mut group = c.benchmark_group("set");
+    group.bench_function("JsonPath", |b| {
+        b.iter(|| owned.set(&mut value, json!("x")).is_ok())
+    });
+    group.bench_function("JsonPathRef", |b| {
+        b.iter(|| borrowed.set(&mut value, json!("x")).is_ok())
+    });
+    group.finish();
+}
+
+criterion_group!(benches, lookup, mutation);
+criterion_main!(benches);
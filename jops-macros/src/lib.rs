@@ -0,0 +1,249 @@
+//! Compile time checked paths for [jops](https://docs.rs/jops).
+
+use jops::json_path::{JsonPath, JsonPathElement, JsonPathIndex};
+use proc_macro::TokenStream;
+use proc_macro2::TokenStream as TokenStream2;
+use quote::quote;
+use syn::{
+    ext::IdentExt, parse_macro_input, spanned::Spanned, Data, DeriveInput, Fields, Ident, LitStr,
+};
+
+/// Parses a JsonPath at compile time.
+///
+/// The query uses the grammar of `JsonPath::from_str`, an invalid query is a compile error. The
+/// macro expands to a `jops::json_path::JsonPath` built from its elements.
+///
+/// ```
+/// use jops_macros::json_path;
+///
+/// let path = json_path!("$.a[#-1].b");
+/// assert_eq!(path.to_string(), "$.a[#-1].b");
+/// ```
+#[proc_macro]
+pub fn json_path(input: TokenStream) -> TokenStream {
+    let query = parse_macro_input!(input as LitStr);
+    match parse(&query) {
+        Ok(path) => path_tokens(&path).into(),
+        Err(err) => err.to_compile_error().into(),
+    }
+}
+
+fn parse(query: &LitStr) -> syn::Result<JsonPath> {
+    query
+        .value()
+        .parse()
+        .map_err(|err| syn::Error::new(query.span(), format!("invalid JsonPath: {err}")))
+}
+
+fn path_tokens(path: &JsonPath) -> TokenStream2 {
+    let elements = path.iter().map(element);
+    quote! {
+        ::jops::json_path::JsonPath::from(::std::vec![#(#elements),*])
+    }
+}
+
+fn element(e: &JsonPathElement) -> TokenStream2 {
+    match e {
+        JsonPathElement::Field(key) => quote! {
+            ::jops::json_path::JsonPathElement::Field(::std::string::String::from(#key))
+        },
+        JsonPathElement::Index(JsonPathIndex::NthLefth(i)) => quote! {
+            ::jops::json_path::JsonPathElement::Index(
+                ::jops::json_path::JsonPathIndex::NthLefth(#i)
+            )
+        },
+        JsonPathElement::Index(JsonPathIndex::NthRight(i)) => quote! {
+            ::jops::json_path::JsonPathElement::Index(
+                ::jops::json_path::JsonPathIndex::NthRight(#i)
+            )
+        },
+    }
+}
+
+struct Field {
+    ident: Ident,
+    ty: syn::Type,
+    path: JsonPath,
+    default: bool,
+    nested: bool,
+    optional: bool,
+}
+
+impl Field {
+    fn new(field: &syn::Field) -> syn::Result<Self> {
+        let ident = field
+            .ident
+            .clone()
+            .ok_or_else(|| syn::Error::new(field.span(), "expected a named field"))?;
+        let mut path = None;
+        let mut default = false;
+        let mut nested = false;
+        for attr in field
+            .attrs
+            .iter()
+            .filter(|attr| attr.path().is_ident("jops"))
+        {
+            attr.parse_nested_meta(|meta| {
+                if meta.path.is_ident("path") {
+                    path = Some(parse(&meta.value()?.parse()?)?);
+                } else if meta.path.is_ident("default") {
+                    default = true;
+                } else if meta.path.is_ident("nested") {
+                    nested = true;
+                } else {
+                    return Err(meta.error("expected `path`, `default` or `nested`"));
+                }
+                Ok(())
+            })?;
+        }
+        // Without a path the field is read from the key of the same name.
+        let path = path.unwrap_or_else(|| {
+            JsonPath::from(vec![JsonPathElement::Field(ident.unraw().to_string())])
+        });
+        Ok(Field {
+            ident,
+            optional: is_option(&field.ty),
+            ty: field.ty.clone(),
+            path,
+            default,
+            nested,
+        })
+    }
+}
+
+// Whether ty is written as an Option, aliases of Option can't be detected.
+fn is_option(ty: &syn::Type) -> bool {
+    match ty {
+        syn::Type::Path(ty) if ty.qself.is_none() => ty
+            .path
+            .segments
+            .last()
+            .is_some_and(|segment| segment.ident == "Option"),
+        _ => false,
+    }
+}
+
+/// Derives `jops::extract::JopsExtract` for a struct with named fields.
+///
+/// Each field is read from the path given by `#[jops(path = "$.a.b")]`, the key with the name
+/// of the field by default. Fields use `serde` to read and write their values, so they must
+/// implement `Deserialize` and `Serialize`.
+///
+/// * `#[jops(default)]` uses `Default::default()` if there is no value at the path.
+/// * `#[jops(nested)]` reads the field with its own `JopsExtract` from the value at the path.
+///
+/// A field whose type is written as `Option<T>` is None if there is no value at the path, like
+/// with `#[jops(default)]`.
+///
+/// ```
+/// use jops::extract::JopsExtract;
+/// use jops_macros::JopsExtract;
+///
+/// #[derive(JopsExtract)]
+/// struct User {
+///     #[jops(path = "$.data.attributes[0].name")]
+///     name: String,
+///     age: Option<u8>,
+/// }
+///
+/// let value = serde_json::json!({ "data": { "attributes": [{ "name": "ann" }] } });
+/// let user = User::extract(&value).unwrap();
+/// assert_eq!(user.name, "ann");
+/// ```
+#[proc_macro_derive(JopsExtract, attributes(jops))]
+pub fn derive_jops_extract(input: TokenStream) -> TokenStream {
+    let input = parse_macro_input!(input as DeriveInput);
+    match derive(&input) {
+        Ok(tokens) => tokens.into(),
+        Err(err) => err.to_compile_error().into(),
+    }
+}
+
+fn derive(input: &DeriveInput) -> syn::Result<TokenStream2> {
+    let fields = match &input.data {
+        Data::Struct(data) => match &data.fields {
+            Fields::Named(fields) => &fields.named,
+            _ => {
+                return Err(syn::Error::new(
+                    input.ident.span(),
+                    "JopsExtract requires named fields",
+                ))
+            }
+        },
+        _ => {
+            return Err(syn::Error::new(
+                input.ident.span(),
+                "JopsExtract can only be derived for structs",
+            ))
+        }
+    };
+    let fields = fields
+        .iter()
+        .map(Field::new)
+        .collect::<syn::Result<Vec<_>>>()?;
+
+    let name = &input.ident;
+    let (impl_generics, ty_generics, where_clause) = input.generics.split_for_impl();
+    let idents: Vec<_> = fields.iter().map(|f| &f.ident).collect();
+    let vars: Vec<_> = fields
+        .iter()
+        .map(|f| quote::format_ident!("__jops_{}", f.ident.unraw()))
+        .collect();
+    let reads = fields.iter().zip(&vars).map(|(f, var)| {
+        let ty = &f.ty;
+        let path = path_tokens(&f.path);
+        let read = if f.nested {
+            quote!(::jops::extract::nested::<#ty>)
+        } else {
+            quote!(::jops::extract::field::<#ty>)
+        };
+        let default = if f.default || (f.optional && !f.nested) {
+            quote!(::std::option::Option::Some(<#ty as ::std::default::Default>::default))
+        } else {
+            quote!(::std::option::Option::None)
+        };
+        quote! {
+            let #var = #read(value, &#path, #default, &mut errors);
+        }
+    });
+    let writes = fields.iter().map(|f| {
+        let ident = &f.ident;
+        let path = path_tokens(&f.path);
+        let write = if f.nested {
+            quote!(::jops::extract::inject_nested)
+        } else {
+            quote!(::jops::extract::inject_field)
+        };
+        quote! {
+            #write(value, &#path, &self.#ident, &mut errors);
+        }
+    });
+
+    Ok(quote! {
+        impl #impl_generics ::jops::extract::JopsExtract for #name #ty_generics #where_clause {
+            fn extract(
+                value: &::jops::__private::serde_json::Value,
+            ) -> ::std::result::Result<Self, ::jops::json_path::ExtractError> {
+                let mut errors = ::std::vec::Vec::new();
+                #(#reads)*
+                match (#(#vars,)*) {
+                    (#(::std::option::Option::Some(#vars),)*) => {
+                        ::std::result::Result::Ok(Self { #(#idents: #vars),* })
+                    }
+                    _ => ::std::result::Result::Err(
+                        ::jops::json_path::ExtractError::Fields(errors),
+                    ),
+                }
+            }
+
+            fn inject(
+                &self,
+                value: &mut ::jops::__private::serde_json::Value,
+            ) -> ::std::result::Result<(), ::jops::json_path::ExtractError> {
+                let mut errors = ::std::vec::Vec::new();
+                #(#writes)*
+                ::jops::extract::finish(errors)
+            }
+        }
+    })
+}
@@ -0,0 +1,183 @@
+use jops::{extract::JopsExtract, json_path::ExtractError};
+use jops_macros::JopsExtract;
+use serde_json::json;
+
+#[derive(Debug, PartialEq, JopsExtract)]
+struct Author {
+    #[jops(path = "$.names[0]")]
+    name: String,
+    #[jops(default)]
+    email: Option<String>,
+}
+
+#[derive(Debug, PartialEq, JopsExtract)]
+struct Article {
+    #[jops(path = "$.data.attributes.title")]
+    title: String,
+    #[jops(path = "$.data.attributes.tags[#-1]")]
+    last_tag: String,
+    #[jops(path = "$.data.attributes.views", default)]
+    views: u64,
+    #[jops(path = "$.data.author", nested)]
+    author: Author,
+    r#type: String,
+}
+
+#[derive(Debug, PartialEq, JopsExtract)]
+struct Pair {
+    #[jops(path = "$.l[0]")]
+    first: Option<u64>,
+    #[jops(path = "$.l[1]")]
+    second: Option<u64>,
+}
+
+fn payload() -> serde_json::Value {
+    json!({
+        "type": "article",
+        "data": {
+            "attributes": {"title": "Paths", "tags": ["json", "rust"]},
+            "author": {"names": ["ann", "a."]}
+        }
+    })
+}
+
+#[test]
+fn extract() {
+    let article = Article::extract(&payload()).unwrap();
+    assert_eq!(
+        article,
+        Article {
+            title: "Paths".to_string(),
+            last_tag: "rust".to_string(),
+            views: 0,
+            author: Author {
+                name: "ann".to_string(),
+                email: None,
+            },
+            r#type: "article".to_string(),
+        }
+    );
+}
+
+#[test]
+fn extract_option() {
+    // Option fields are None without a value, like with #[jops(default)].
+    let pair = Pair::extract(&json!({"l": [3]})).unwrap();
+    assert_eq!(
+        pair,
+        Pair {
+            first: Some(3),
+            second: None
+        }
+    );
+    let pair = Pair::extract(&json!({})).unwrap();
+    assert_eq!(
+        pair,
+        Pair {
+            first: None,
+            second: None
+        }
+    );
+}
+
+#[test]
+fn extract_errors() {
+    let value = json!({
+        "data": {
+            "attributes": {"title": 1, "tags": []},
+            "author": {"names": [2]}
+        }
+    });
+    let err = Article::extract(&value).unwrap_err();
+    // With arbitrary_precision serde_json reports numbers without their type and value.
+    let (one, two) = if cfg!(feature = "arbitrary_precision") {
+        ("number", "number")
+    } else {
+        ("integer `1`", "integer `2`")
+    };
+    assert_eq!(
+        err,
+        ExtractError::Fields(vec![
+            ExtractError::Deserialize(
+                "$.data.attributes.title".to_string(),
+                format!("invalid type: {}, expected a string", one)
+            ),
+            ExtractError::Missing("$.data.attributes.tags[#-1]".to_string()),
+            ExtractError::Deserialize(
+                "$.data.author.names[0]".to_string(),
+                format!("invalid type: {}, expected a string", two)
+            ),
+            ExtractError::Missing("$.type".to_string()),
+        ])
+    );
+    assert_eq!(
+        err.to_string(),
+        format!(
+            concat!(
+                "invalid value at `$.data.attributes.title`: invalid type: {}, ",
+                "expected a string; no value at `$.data.attributes.tags[#-1]`; ",
+                "invalid value at `$.data.author.names[0]`: invalid type: {}, ",
+                "expected a string; no value at `$.type`"
+            ),
+            one, two
+        )
+    );
+}
+
+#[test]
+fn inject() {
+    let mut value = payload();
+    let mut article = Article::extract(&value).unwrap();
+    article.title = "Paths, again".to_string();
+    article.last_tag = "serde".to_string();
+    article.author.name = "bob".to_string();
+    article.inject(&mut value).unwrap();
+    assert_eq!(
+        value,
+        json!({
+            "type": "article",
+            "data": {
+                "attributes": {"title": "Paths, again", "tags": ["json", "serde"], "views": 0},
+                "author": {"names": ["bob", "a."]}
+            }
+        })
+    );
+
+    // None removes the value, a value read as None is injected unchanged.
+    let mut value = json!({"names": ["ann"], "email": "ann@example.com"});
+    let mut author = Author::extract(&value).unwrap();
+    author.email = None;
+    author.inject(&mut value).unwrap();
+    assert_eq!(value, json!({"names": ["ann"]}));
+    author.inject(&mut value).unwrap();
+    assert_eq!(value, json!({"names": ["ann"]}));
+
+    // None at an array index doesn't shift the elements after it.
+    let mut value = json!({"l": [null, null, 7]});
+    let mut pair = Pair::extract(&value).unwrap();
+    assert_eq!(
+        pair,
+        Pair {
+            first: None,
+            second: None
+        }
+    );
+    pair.inject(&mut value).unwrap();
+    assert_eq!(value, json!({"l": [null, null, 7]}));
+    pair.first = Some(1);
+    pair.inject(&mut value).unwrap();
+    assert_eq!(value, json!({"l": [1, null, 7]}));
+    pair.first = None;
+    pair.inject(&mut value).unwrap();
+    assert_eq!(value, json!({"l": [null, null, 7]}));
+
+    let mut value = json!({"type": "x", "data": {"attributes": {"tags": []}}});
+    let err = article.inject(&mut value).unwrap_err();
+    assert_eq!(
+        err.to_string(),
+        concat!(
+            "unable to write `$.data.attributes.tags[#-1]`: index out of range; ",
+            "unable to write `$.data.author`: value doesn't exist"
+        )
+    );
+}
@@ -0,0 +1,30 @@
+use jops::json_path::JsonPath;
+use jops_macros::json_path;
+use serde_json::json;
+
+#[test]
+fn expands_like_from_str() {
+    let tests = vec![
+        (json_path!("$"), "$"),
+        (json_path!("$.a"), "$.a"),
+        (json_path!("$.a[#-1].b"), "$.a[#-1].b"),
+        (json_path!("$.\"a b\"[0][#]"), "$.\"a b\"[0][#]"),
+        (json_path!("1"), "1"),
+    ];
+    for (path, query) in tests {
+        let expected: JsonPath = query.parse().unwrap();
+        assert_eq!(path, expected, "expected {} to be {}", query, expected);
+    }
+}
+
+#[test]
+fn finds_values() {
+    let value = json!({"a": [{"b": 1}, {"b": 2}]});
+    assert_eq!(json_path!("$.a[#-1].b").find(&value), Some(&json!(2)));
+}
+
+#[test]
+fn ui() {
+    let t = trybuild::TestCases::new();
+    t.compile_fail("tests/ui/*.rs");
+}
@@ -0,0 +1,18 @@
+use jops_macros::JopsExtract;
+
+#[derive(JopsExtract)]
+struct Invalid {
+    #[jops(path = "$.a[")]
+    a: String,
+}
+
+#[derive(JopsExtract)]
+struct Unknown {
+    #[jops(rename = "b")]
+    b: String,
+}
+
+#[derive(JopsExtract)]
+struct Tuple(String);
+
+fn main() {}
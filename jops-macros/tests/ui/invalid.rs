@@ -0,0 +1,7 @@
+use jops_macros::json_path;
+
+fn main() {
+    let _ = json_path!("$.a[1");
+    let _ = json_path!("a.b");
+    let _ = json_path!("$.\"a");
+}
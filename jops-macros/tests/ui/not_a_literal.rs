@@ -0,0 +1,6 @@
+use jops_macros::json_path;
+
+fn main() {
+    let query = "$.a";
+    let _ = json_path!(query);
+}
@@ -0,0 +1,331 @@
+use std::{
+    collections::HashMap,
+    sync::{
+        atomic::{AtomicU64, Ordering},
+        Arc, Mutex,
+    },
+};
+
+use crate::{json_like::JsonLike, json_path::JsonPath};
+
+const NIL: usize = usize::MAX;
+
+struct Entry {
+    query: String,
+    path: Arc<JsonPath>,
+    prev: usize, // more recently used entry
+    next: usize, // less recently used entry
+}
+
+// Least recently used list of entries, linked by their position in entries.
+struct Lru {
+    map: HashMap<String, usize>,
+    entries: Vec<Entry>,
+    head: usize,
+    tail: usize,
+}
+
+impl Lru {
+    fn unlink(&mut self, i: usize) {
+        let (prev, next) = (self.entries[i].prev, self.entries[i].next);
+        match prev {
+            NIL => self.head = next,
+            prev => self.entries[prev].next = next,
+        }
+        match next {
+            NIL => self.tail = prev,
+            next => self.entries[next].prev = prev,
+        }
+    }
+
+    fn push_front(&mut self, i: usize) {
+        self.entries[i].prev = NIL;
+        self.entries[i].next = self.head;
+        match self.head {
+            NIL => self.tail = i,
+            head => self.entries[head].prev = i,
+        }
+        self.head = i;
+    }
+
+    fn get(&mut self, query: &str) -> Option<Arc<JsonPath>> {
+        let i = *self.map.get(query)?;
+        self.unlink(i);
+        self.push_front(i);
+        Some(self.entries[i].path.clone())
+    }
+
+    // Returns true if an entry was evicted.
+    fn insert(&mut self, query: &str, path: Arc<JsonPath>, capacity: usize) -> bool {
+        if let Some(&i) = self.map.get(query) {
+            self.entries[i].path = path;
+            self.unlink(i);
+            self.push_front(i);
+            return false;
+        }
+        let entry = Entry {
+            query: query.to_string(),
+            path,
+            prev: NIL,
+            next: NIL,
+        };
+        let evicted = self.entries.len() >= capacity;
+        let i = if evicted {
+            let i = self.tail;
+            self.unlink(i);
+            self.map.remove(&self.entries[i].query);
+            self.entries[i] = entry;
+            i
+        } else {
+            self.entries.push(entry);
+            self.entries.len() - 1
+        };
+        self.map.insert(query.to_string(), i);
+        self.push_front(i);
+        evicted
+    }
+}
+
+/// Counters of a PathCache.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct CacheMetrics {
+    pub hits: u64,
+    pub misses: u64,
+    pub evictions: u64,
+}
+
+/// A thread-safe, bounded cache of parsed JsonPaths.
+///
+/// When the cache is full the least recently used path is evicted.
+pub struct PathCache {
+    capacity: usize,
+    lru: Mutex<Lru>,
+    hits: AtomicU64,
+    misses: AtomicU64,
+    evictions: AtomicU64,
+}
+
+impl PathCache {
+    /// Creates a cache holding at most `capacity` paths.
+    ///
+    /// Entries are allocated as paths are cached, so a large capacity costs nothing up front.
+    pub fn new(capacity: usize) -> Self {
+        PathCache {
+            capacity,
+            lru: Mutex::new(Lru {
+                map: HashMap::new(),
+                entries: Vec::new(),
+                head: NIL,
+                tail: NIL,
+            }),
+            hits: AtomicU64::new(0),
+            misses: AtomicU64::new(0),
+            evictions: AtomicU64::new(0),
+        }
+    }
+
+    /// Returns the parsed path of `query`, parsing and caching it on a miss.
+    ///
+    /// Queries which fail to parse are not cached.
+    pub fn get(&self, query: &str) -> Result<Arc<JsonPath>, &'static str> {
+        if let Some(path) = self.lock().get(query) {
+            self.hits.fetch_add(1, Ordering::Relaxed);
+            return Ok(path);
+        }
+        self.misses.fetch_add(1, Ordering::Relaxed);
+        // Parse without holding the lock
+        let path = Arc::new(JsonPath::try_from(query)?);
+        if self.capacity > 0 && self.lock().insert(query, path.clone(), self.capacity) {
+            self.evictions.fetch_add(1, Ordering::Relaxed);
+        }
+        Ok(path)
+    }
+
+    /// Returns the maximum number of cached paths.
+    pub fn capacity(&self) -> usize {
+        self.capacity
+    }
+
+    /// Returns the number of cached paths.
+    pub fn len(&self) -> usize {
+        self.lock().map.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Removes all cached paths, metrics are kept.
+    pub fn clear(&self) {
+        let mut lru = self.lock();
+        lru.map.clear();
+        lru.entries.clear();
+        lru.head = NIL;
+        lru.tail = NIL;
+    }
+
+    pub fn metrics(&self) -> CacheMetrics {
+        CacheMetrics {
+            hits: self.hits.load(Ordering::Relaxed),
+            misses: self.misses.load(Ordering::Relaxed),
+            evictions: self.evictions.load(Ordering::Relaxed),
+        }
+    }
+
+    fn lock(&self) -> std::sync::MutexGuard<'_, Lru> {
+        // The list is consistent between calls, a panic while holding the lock can't corrupt it
+        self.lru.lock().unwrap_or_else(|err| err.into_inner())
+    }
+}
+
+impl std::fmt::Debug for PathCache {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("PathCache")
+            .field("capacity", &self.capacity)
+            .field("len", &self.len())
+            .field("metrics", &self.metrics())
+            .finish()
+    }
+}
+
+/// Like JsonPathQuery but parses queries through a PathCache.
+pub trait CachedJsonPathQuery<'a> {
+    fn path_cached(&'a self, cache: &PathCache, query: &str) -> Result<&'a Self, &'static str>;
+    fn path_mut_cached(
+        &'a mut self,
+        cache: &PathCache,
+        query: &str,
+    ) -> Result<&'a mut Self, &'static str>;
+}
+
+impl<'a, V: JsonLike> CachedJsonPathQuery<'a> for V {
+    #[inline]
+    fn path_cached(&'a self, cache: &PathCache, query: &str) -> Result<&'a Self, &'static str> {
+        let path = cache.get(query)?;
+        path.find(self).ok_or("unable to find path to value")
+    }
+
+    #[inline]
+    fn path_mut_cached(
+        &'a mut self,
+        cache: &PathCache,
+        query: &str,
+    ) -> Result<&'a mut Self, &'static str> {
+        let path = cache.get(query)?;
+        path.find_mut(self).ok_or("unable to find path to value")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::*;
+
+    #[test]
+    fn lru() {
+        let cache = PathCache::new(2);
+        let tests = vec![
+            // query, len, metrics after the get
+            ("$.a", 1, (0, 1, 0)),
+            ("$.a", 1, (1, 1, 0)),
+            ("$.b", 2, (1, 2, 0)),
+            ("$.a", 2, (2, 2, 0)),
+            ("$.c", 2, (2, 3, 1)), // evicts $.b
+            ("$.a", 2, (3, 3, 1)),
+            ("$.b", 2, (3, 4, 2)), // evicts $.c
+            ("$.c", 2, (3, 5, 3)), // evicts $.a
+            ("$.1", 2, (3, 6, 3)), // not cached
+        ];
+        for (query, len, (hits, misses, evictions)) in tests {
+            let _ = cache.get(query);
+            let expected = CacheMetrics {
+                hits,
+                misses,
+                evictions,
+            };
+            assert_eq!(
+                cache.len(),
+                len,
+                "expected {} to leave {} paths",
+                query,
+                len
+            );
+            assert_eq!(
+                cache.metrics(),
+                expected,
+                "expected {} to result in {:?}",
+                query,
+                expected
+            );
+        }
+        cache.clear();
+        assert!(cache.is_empty());
+    }
+
+    #[test]
+    fn zero_capacity() {
+        let cache = PathCache::new(0);
+        assert!(cache.get("$.a").is_ok());
+        assert!(cache.get("$.a").is_ok());
+        assert!(cache.is_empty());
+        assert_eq!(cache.metrics().misses, 2);
+    }
+
+    #[test]
+    fn unbounded_capacity() {
+        let cache = PathCache::new(usize::MAX);
+        assert!(cache.get("$.a").is_ok());
+        assert_eq!(cache.len(), 1);
+    }
+
+    #[test]
+    fn path_cached() {
+        let cache = PathCache::new(4);
+        let mut value = json!({"a": [1, {"b": 2}]});
+        let tests = vec![
+            ("$.a[0]", Ok(json!(1))),
+            ("$.a[1].b", Ok(json!(2))),
+            ("$.c", Err("unable to find path to value")),
+            ("$.a[", Err("expected ]")),
+        ];
+        for (query, expected) in tests {
+            let result = value.path_cached(&cache, query).cloned();
+            assert_eq!(result, expected, "expected {} to be {:?}", query, expected);
+        }
+        *value.path_mut_cached(&cache, "$.a[0]").unwrap() = json!(3);
+        assert_eq!(value, json!({"a": [3, {"b": 2}]}));
+        assert_eq!(cache.metrics().hits, 1);
+    }
+
+    #[cfg(feature = "serde_yaml")]
+    #[test]
+    fn path_cached_yaml() {
+        let cache = PathCache::new(4);
+        let value: serde_yaml::Value = serde_yaml::from_str("a: [1, {b: 2}]").unwrap();
+        let result = value.path_cached(&cache, "$.a[1].b");
+        assert_eq!(result, Ok(&serde_yaml::Value::from(2)));
+    }
+
+    #[test]
+    fn threads() {
+        let cache = Arc::new(PathCache::new(8));
+        let handles: Vec<_> = (0..4)
+            .map(|_| {
+                let cache = cache.clone();
+                std::thread::spawn(move || {
+                    for i in 0..100 {
+                        let query = format!("$[{}]", i % 8);
+                        assert_eq!(cache.get(&query).unwrap().to_string(), query);
+                    }
+                })
+            })
+            .collect();
+        for handle in handles {
+            handle.join().unwrap();
+        }
+        let metrics = cache.metrics();
+        assert_eq!(metrics.hits + metrics.misses, 400);
+        assert_eq!(metrics.evictions, 0);
+    }
+}
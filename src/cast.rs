@@ -0,0 +1,320 @@
+use std::fmt;
+
+use serde_json::{Number, Value};
+
+use crate::json_like::JsonLike;
+
+/// The target type of a cast, like `CAST(x AS INTEGER)` in SQLite.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SqlType {
+    Integer,
+    Real,
+    Text,
+    Numeric, // Integer if the value is an integer, Real otherwise
+    Boolean, // Numeric compared with zero
+}
+
+impl fmt::Display for SqlType {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            SqlType::Integer => "INTEGER",
+            SqlType::Real => "REAL",
+            SqlType::Text => "TEXT",
+            SqlType::Numeric => "NUMERIC",
+            SqlType::Boolean => "BOOLEAN",
+        })
+    }
+}
+
+// The SQL value of a JsonLike, booleans are the integers 1 and 0 like in SQLite.
+enum Sql<'a> {
+    Integer(i64),
+    Real(f64),
+    Text(&'a str),
+}
+
+// Returns None for Null, Arrays, Objects and other values without an SQL equivalent.
+fn sql<V: JsonLike>(value: &V) -> Option<Sql<'_>> {
+    if let Some(b) = value.as_bool() {
+        Some(Sql::Integer(i64::from(b)))
+    } else if let Some(n) = value.as_number() {
+        Some(number(&n))
+    } else {
+        value.as_str().map(Sql::Text)
+    }
+}
+
+// Integers above i64::MAX are Reals in SQLite.
+fn number(n: &Number) -> Sql<'static> {
+    match n.as_i64() {
+        Some(i) => Sql::Integer(i),
+        None => Sql::Real(n.as_f64().unwrap_or_default()),
+    }
+}
+
+/// Casts `value` following SQLite's `CAST(value AS to)`, Null for NULL.
+///
+/// Null, Arrays and Objects cast to Null, a Real which isn't a JSON number casts to Null.
+pub fn cast<V: JsonLike>(value: &V, to: SqlType) -> Value {
+    match to {
+        SqlType::Integer => to_integer(value).map(Value::from),
+        SqlType::Real => to_real(value).and_then(Number::from_f64).map(Value::Number),
+        SqlType::Text => to_text(value).map(Value::String),
+        SqlType::Numeric => to_numeric(value).map(Value::Number),
+        SqlType::Boolean => to_boolean(value).map(Value::Bool),
+    }
+    .unwrap_or(Value::Null)
+}
+
+/// `CAST(value AS INTEGER)`.
+///
+/// Reals are truncated toward zero and Text is read up to the end of its longest integer prefix,
+/// Text without one is 0. Results outside of i64 saturate.
+pub fn to_integer<V: JsonLike>(value: &V) -> Option<i64> {
+    Some(match sql(value)? {
+        Sql::Integer(i) => i,
+        // `as` truncates toward zero and saturates.
+        Sql::Real(f) => f as i64,
+        Sql::Text(s) => integer_prefix(s),
+    })
+}
+
+/// `CAST(value AS REAL)`, Text is read up to the end of its longest real prefix, or is 0.0.
+pub fn to_real<V: JsonLike>(value: &V) -> Option<f64> {
+    Some(match sql(value)? {
+        Sql::Integer(i) => i as f64,
+        Sql::Real(f) => f,
+        Sql::Text(s) => real_prefix(s).map_or(0.0, |(f, _)| f),
+    })
+}
+
+/// `CAST(value AS TEXT)`, Reals use the shortest text which reads back as the same number.
+pub fn to_text<V: JsonLike>(value: &V) -> Option<String> {
+    if let Some(b) = value.as_bool() {
+        return Some(i64::from(b).to_string());
+    }
+    if let Some(n) = value.as_number() {
+        return Some(n.to_string());
+    }
+    value.as_str().map(str::to_string)
+}
+
+/// `CAST(value AS NUMERIC)`.
+///
+/// Integers and Reals are unchanged. Text is read up to the end of its longest real prefix, which
+/// is an Integer if it has no fractional part and fits into an i64. Text whose Real overflows,
+/// like `"1e999"`, is None as infinity isn't a JSON number.
+pub fn to_numeric<V: JsonLike>(value: &V) -> Option<Number> {
+    if let Some(b) = value.as_bool() {
+        return Some(Number::from(i64::from(b)));
+    }
+    if let Some(n) = value.as_number() {
+        return Some(n);
+    }
+    let s = value.as_str()?;
+    match real_prefix(s) {
+        Some((f, len)) => numeric(&s[..len], f),
+        None => Some(Number::from(0)),
+    }
+}
+
+/// `CAST(value AS BOOLEAN)`, true if the value cast to NUMERIC isn't zero.
+pub fn to_boolean<V: JsonLike>(value: &V) -> Option<bool> {
+    // REAL is zero exactly when NUMERIC is, and also reads overflowing Text.
+    to_real(value).map(|f| f != 0.0)
+}
+
+/// Returns the number of `s` if all of it, apart from surrounding whitespace, is an integer or
+/// real literal.
+///
+/// This is SQLite's NUMERIC affinity, which `partial_cmp` applies when comparing Text with a
+/// number. Unlike the casts, `"12abc"` and `""` aren't numbers, neither are literals which
+/// overflow a Real like `"1e999"`.
+pub fn affinity(s: &str) -> Option<Number> {
+    let (f, len) = real_prefix(s)?;
+    if !s[len..].bytes().all(is_space) {
+        return None;
+    }
+    numeric(&s[..len], f)
+}
+
+// An Integer if literal has no fractional part and fits into an i64, otherwise the Real f, None
+// if f is infinite.
+fn numeric(literal: &str, f: f64) -> Option<Number> {
+    let literal = literal.trim_start_matches(|c: char| c.is_ascii() && is_space(c as u8));
+    if let Ok(i) = literal.parse::<i64>() {
+        return Some(Number::from(i));
+    }
+    if f.fract() == 0.0 && f >= i64::MIN as f64 && f < i64::MAX as f64 {
+        return Some(Number::from(f as i64));
+    }
+    Number::from_f64(f)
+}
+
+// Whitespace skipped by SQLite before and after numbers.
+fn is_space(b: u8) -> bool {
+    matches!(b, b' ' | b'\t' | b'\n' | b'\x0b' | b'\x0c' | b'\r')
+}
+
+// Reads leading whitespace, an optional sign and digits, saturating at the bounds of i64.
+fn integer_prefix(s: &str) -> i64 {
+    let s = s.as_bytes();
+    let mut pos = s.iter().take_while(|b| is_space(**b)).count();
+    let negative = match s.get(pos) {
+        Some(b'-') => {
+            pos += 1;
+            true
+        }
+        Some(b'+') => {
+            pos += 1;
+            false
+        }
+        _ => false,
+    };
+    let mut n: i64 = 0;
+    for b in s[pos..].iter().take_while(|b| b.is_ascii_digit()) {
+        let digit = i64::from(b - b'0');
+        n = if negative {
+            n.saturating_mul(10).saturating_sub(digit)
+        } else {
+            n.saturating_mul(10).saturating_add(digit)
+        };
+    }
+    n
+}
+
+// Reads leading whitespace, an optional sign, digits with an optional fraction and an optional
+// exponent. Returns the number and the length of the prefix, None if there are no digits.
+fn real_prefix(s: &str) -> Option<(f64, usize)> {
+    let b = s.as_bytes();
+    let digits = |pos: usize| b[pos..].iter().take_while(|b| b.is_ascii_digit()).count();
+    let start = b.iter().take_while(|b| is_space(**b)).count();
+    let mut pos = start;
+    if matches!(b.get(pos), Some(b'-' | b'+')) {
+        pos += 1;
+    }
+    let mut mantissa = digits(pos);
+    pos += mantissa;
+    if b.get(pos) == Some(&b'.') {
+        let fraction = digits(pos + 1);
+        mantissa += fraction;
+        pos += 1 + fraction;
+    }
+    if mantissa == 0 {
+        return None;
+    }
+    if matches!(b.get(pos), Some(b'e' | b'E')) {
+        let mut exp = pos + 1;
+        if matches!(b.get(exp), Some(b'-' | b'+')) {
+            exp += 1;
+        }
+        let n = digits(exp);
+        if n > 0 {
+            pos = exp + n;
+        }
+    }
+    s[start..pos].parse().ok().map(|f| (f, pos))
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::*;
+
+    #[test]
+    fn test_cast() {
+        let tests = vec![
+            // Integer
+            (json!(null), SqlType::Integer, json!(null)),
+            (json!(true), SqlType::Integer, json!(1)),
+            (json!(false), SqlType::Integer, json!(0)),
+            (json!(12), SqlType::Integer, json!(12)),
+            (json!(-3.9), SqlType::Integer, json!(-3)),
+            (json!(1e300), SqlType::Integer, json!(i64::MAX)),
+            (json!(u64::MAX), SqlType::Integer, json!(i64::MAX)),
+            (json!(" 12abc"), SqlType::Integer, json!(12)),
+            (json!("-7.9"), SqlType::Integer, json!(-7)),
+            (json!("1e3"), SqlType::Integer, json!(1)),
+            (json!("+5"), SqlType::Integer, json!(5)),
+            (json!("abc"), SqlType::Integer, json!(0)),
+            (json!("0x10"), SqlType::Integer, json!(0)),
+            (
+                json!("-99999999999999999999"),
+                SqlType::Integer,
+                json!(i64::MIN),
+            ),
+            (json!([1]), SqlType::Integer, json!(null)),
+            (json!({"a": 1}), SqlType::Integer, json!(null)),
+            // Real
+            (json!(true), SqlType::Real, json!(1.0)),
+            (json!(3), SqlType::Real, json!(3.0)),
+            (json!("2.5kg"), SqlType::Real, json!(2.5)),
+            (json!(" -1.5e2x"), SqlType::Real, json!(-150.0)),
+            (json!("1e"), SqlType::Real, json!(1.0)),
+            (json!("1.e2"), SqlType::Real, json!(100.0)),
+            (json!(".5"), SqlType::Real, json!(0.5)),
+            (json!("."), SqlType::Real, json!(0.0)),
+            (json!("inf"), SqlType::Real, json!(0.0)),
+            // Text
+            (json!(true), SqlType::Text, json!("1")),
+            (json!(-12), SqlType::Text, json!("-12")),
+            (json!(1.5), SqlType::Text, json!("1.5")),
+            (json!("x"), SqlType::Text, json!("x")),
+            (json!([]), SqlType::Text, json!(null)),
+            // Numeric
+            (json!(false), SqlType::Numeric, json!(0)),
+            (json!(2.0), SqlType::Numeric, json!(2.0)),
+            (json!("3.0"), SqlType::Numeric, json!(3)),
+            (json!("3.5 apples"), SqlType::Numeric, json!(3.5)),
+            (json!("1e3"), SqlType::Numeric, json!(1000)),
+            (
+                json!("9223372036854775808"),
+                SqlType::Numeric,
+                json!(9223372036854775808.0),
+            ),
+            (json!("apples"), SqlType::Numeric, json!(0)),
+            (json!("1e999"), SqlType::Numeric, json!(null)),
+            // Boolean
+            (json!(0.5), SqlType::Boolean, json!(true)),
+            (json!(0), SqlType::Boolean, json!(false)),
+            (json!("1abc"), SqlType::Boolean, json!(true)),
+            (json!("true"), SqlType::Boolean, json!(false)),
+            (json!("-1e999"), SqlType::Boolean, json!(true)),
+            (json!(null), SqlType::Boolean, json!(null)),
+        ];
+        for (value, to, expected) in tests {
+            let result = cast(&value, to);
+            assert_eq!(
+                result, expected,
+                "expected CAST({} AS {}) to be {}",
+                value, to, expected
+            );
+        }
+    }
+
+    #[test]
+    fn test_affinity() {
+        let tests = vec![
+            ("12", Some(json!(12))),
+            (" 12 ", Some(json!(12))),
+            ("-1.5e2", Some(json!(-150))),
+            ("2.5", Some(json!(2.5))),
+            ("3.0", Some(json!(3))),
+            ("+7", Some(json!(7))),
+            ("18446744073709551615", Some(json!(18446744073709551615.0))),
+            ("12abc", None),
+            ("1e", None),
+            ("", None),
+            ("inf", None),
+            ("NaN", None),
+            ("0x10", None),
+            ("1e999", None),
+            ("-1e999", None),
+        ];
+        for (s, expected) in tests {
+            let result = affinity(s).map(Value::Number);
+            assert_eq!(result, expected, "expected {:?} to be {:?}", s, expected);
+        }
+    }
+}
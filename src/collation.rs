@@ -0,0 +1,227 @@
+use std::{cmp::Ordering, iter::Peekable, str::CharIndices};
+
+/// Compares strings inside values, like an SQL collating sequence.
+///
+/// Comparators take a collation with `Comparator::partial_cmp_collated`, or wrapped together
+/// with it into `compare::Collated`. Object keys are always compared by bytes.
+pub trait Collation {
+    fn compare(&self, a: &str, b: &str) -> Ordering;
+}
+
+/// SQLite's `BINARY`, compares bytes.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct Binary;
+
+/// SQLite's `NOCASE`, compares bytes with ASCII letters folded to lower case.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct NoCase;
+
+/// SQLite's `RTRIM`, compares bytes ignoring trailing spaces.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct RTrim;
+
+/// Compares runs of ASCII digits by their numeric value and everything between them with the
+/// collation `C`, so `"file2"` is less than `"file10"`.
+///
+/// Numbers which only differ in leading zeros are equal.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct Natural<C = Binary>(pub C);
+
+/// Compares strings by their base letters first and by their diacritics only if the base letters
+/// are equal, ignoring case for all scripts.
+///
+/// Strings are compared as the compatibility decomposition (NFKD) after full Unicode case
+/// folding, the key of Unicode's compatibility caseless match. Combining marks are skipped at
+/// first, so `"éclair"` is less than `"ezra"` and `"cote"` is less than `"côte"`, which is less
+/// than `"coter"`. Composed and decomposed characters like `"é"` and `"e\u{301}"` are equal,
+/// `"Straße"` equals `"STRASSE"` and `"ς"` equals `"Σ"`.
+///
+/// This is not the Unicode Collation Algorithm: base letters compare by code point and there is
+/// no tailoring for a locale, so Swedish `"å"` sorts with `"a"` and not after `"z"`.
+#[cfg(feature = "unicode-normalization")]
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct Unicode;
+
+impl Collation for Binary {
+    #[inline]
+    fn compare(&self, a: &str, b: &str) -> Ordering {
+        a.cmp(b)
+    }
+}
+
+impl Collation for NoCase {
+    fn compare(&self, a: &str, b: &str) -> Ordering {
+        let a = a.bytes().map(|b| b.to_ascii_lowercase());
+        a.cmp(b.bytes().map(|b| b.to_ascii_lowercase()))
+    }
+}
+
+impl Collation for RTrim {
+    fn compare(&self, a: &str, b: &str) -> Ordering {
+        a.trim_end_matches(' ').cmp(b.trim_end_matches(' '))
+    }
+}
+
+impl<C: Collation> Collation for Natural<C> {
+    fn compare(&self, a: &str, b: &str) -> Ordering {
+        let (mut ia, mut ib) = (a.char_indices().peekable(), b.char_indices().peekable());
+        loop {
+            let (ca, cb) = (chunk(a, &mut ia), chunk(b, &mut ib));
+            let ordering = match (ca, cb) {
+                (None, None) => return Ordering::Equal,
+                (None, Some(_)) => return Ordering::Less,
+                (Some(_), None) => return Ordering::Greater,
+                (Some(Chunk::Digits(x)), Some(Chunk::Digits(y))) => {
+                    let (x, y) = (x.trim_start_matches('0'), y.trim_start_matches('0'));
+                    x.len().cmp(&y.len()).then_with(|| x.cmp(y))
+                }
+                (Some(x), Some(y)) => self.0.compare(x.as_str(), y.as_str()),
+            };
+            if ordering.is_ne() {
+                return ordering;
+            }
+        }
+    }
+}
+
+#[cfg(feature = "unicode-normalization")]
+impl Collation for Unicode {
+    fn compare(&self, a: &str, b: &str) -> Ordering {
+        use caseless::Caseless;
+        use unicode_normalization::{char::is_combining_mark, UnicodeNormalization};
+
+        // NFKD(fold(NFKD(fold(NFD(s))))), folding twice as normalizing can produce foldable
+        // characters again.
+        fn key(s: &str) -> impl Iterator<Item = char> + '_ {
+            s.nfd()
+                .default_case_fold()
+                .nfkd()
+                .default_case_fold()
+                .nfkd()
+        }
+        let base = |s| key(s).filter(|c| !is_combining_mark(*c));
+        base(a).cmp(base(b)).then_with(|| key(a).cmp(key(b)))
+    }
+}
+
+impl<C: Collation + ?Sized> Collation for &C {
+    #[inline]
+    fn compare(&self, a: &str, b: &str) -> Ordering {
+        (**self).compare(a, b)
+    }
+}
+
+// A run of ASCII digits or of other characters.
+enum Chunk<'a> {
+    Digits(&'a str),
+    Text(&'a str),
+}
+
+impl<'a> Chunk<'a> {
+    fn as_str(&self) -> &'a str {
+        match self {
+            Chunk::Digits(s) | Chunk::Text(s) => s,
+        }
+    }
+}
+
+// Returns the next run of s.
+fn chunk<'a>(s: &'a str, iter: &mut Peekable<CharIndices>) -> Option<Chunk<'a>> {
+    let (start, c) = *iter.peek()?;
+    let digit = c.is_ascii_digit();
+    let mut end = s.len();
+    while let Some((i, c)) = iter.peek() {
+        if c.is_ascii_digit() != digit {
+            end = *i;
+            break;
+        }
+        iter.next();
+    }
+    let run = &s[start..end];
+    Some(if digit {
+        Chunk::Digits(run)
+    } else {
+        Chunk::Text(run)
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use Ordering::{Equal as E, Greater as G, Less as L};
+
+    #[test]
+    fn test_collations() {
+        // Expected orderings for Binary, NoCase, RTrim and Natural.
+        let tests = vec![
+            ("a", "a", [E, E, E, E]),
+            ("a", "B", [G, L, G, G]),
+            ("ABC", "abc", [L, E, L, L]),
+            ("Straße", "STRASSE", [G, G, G, G]),
+            ("É", "é", [L, L, L, L]),
+            ("a  ", "a", [G, G, E, G]),
+            ("a \t", "a", [G, G, G, G]),
+            (" a", "a", [L, L, L, L]),
+            ("file2", "file10", [G, G, G, L]),
+            ("file10", "File9", [G, L, G, G]),
+            ("v1.10.0", "v1.9.2", [L, L, L, G]),
+            ("x007", "x7", [L, L, L, E]),
+            ("2a", "10", [G, G, G, L]),
+            ("a1", "a", [G, G, G, G]),
+            ("a", "1", [G, G, G, G]),
+            ("", "0", [L, L, L, L]),
+            (
+                "99999999999999999999",
+                "100000000000000000000",
+                [G, G, G, L],
+            ),
+        ];
+        for (a, b, expected) in tests {
+            let result = [
+                Binary.compare(a, b),
+                NoCase.compare(a, b),
+                RTrim.compare(a, b),
+                Natural(Binary).compare(a, b),
+            ];
+            assert_eq!(
+                result, expected,
+                "expected {:?} compared to {:?} to be {:?}",
+                a, b, expected
+            );
+        }
+
+        assert_eq!(Natural(NoCase).compare("File10", "file9"), G);
+        assert_eq!(Natural(NoCase).compare("IMG2", "img10"), L);
+    }
+
+    #[cfg(feature = "unicode-normalization")]
+    #[test]
+    fn test_unicode() {
+        let tests = vec![
+            ("É", "é", E),
+            ("e\u{301}", "é", E),
+            ("ﬁle", "FILE", E),
+            ("Ärger", "b", L),
+            ("éclair", "ezra", L),
+            ("cote", "côte", L),
+            ("côte", "coter", L),
+            ("e", "é", L),
+            ("Ω", "ω", E),
+            ("Straße", "STRASSE", E),
+            ("ς", "Σ", E),
+            ("ὀδυσσεύς", "ὈΔΥΣΣΕΎΣ", E),
+            ("ǅ", "dž", E),
+            ("b", "Á", G),
+        ];
+        for (a, b, expected) in tests {
+            let result = Unicode.compare(a, b);
+            assert_eq!(
+                result, expected,
+                "expected {:?} compared to {:?} to be {:?}",
+                a, b, expected
+            );
+        }
+        assert_eq!(Natural(Unicode).compare("Übung 10", "übung 9"), G);
+    }
+}
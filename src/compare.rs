@@ -0,0 +1,774 @@
+use std::cmp::Ordering;
+
+use crate::{
+    collation::{Binary, Collation},
+    json_like::JsonLike,
+    number::cmp_numbers,
+    value,
+};
+
+/// The rules used to compare two JSON-like values.
+///
+/// `Sqlite` is the default everywhere a comparator can be chosen, like `JsonValue` and
+/// `search::search_with`.
+pub trait Comparator {
+    /// Compares a with b, None if they can't be compared.
+    fn partial_cmp<V: JsonLike>(&self, a: &V, b: &V) -> Option<Ordering> {
+        self.partial_cmp_collated(a, b, &Binary)
+    }
+
+    /// Compares like `partial_cmp`, Strings are compared with `collation`.
+    fn partial_cmp_collated<V: JsonLike, L: Collation + ?Sized>(
+        &self,
+        a: &V,
+        b: &V,
+        collation: &L,
+    ) -> Option<Ordering>;
+}
+
+/// A comparator which compares Strings with the collation `L`.
+///
+/// `Collated(Sqlite, NoCase)` compares like `partial_cmp` but ignores the case of ASCII letters.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct Collated<C, L>(pub C, pub L);
+
+/// SQLite JSON operators, see `value::partial_cmp`.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct Sqlite;
+
+/// PostgreSQL `jsonb` ordering.
+///
+/// Object > Array > Boolean > Number > String > Null, values of the same type:
+/// * Objects with more pairs are greater, otherwise the pairs are compared key, value, key, ... in
+///   jsonb storage order, which sorts keys by length and then bytes.
+/// * Arrays with more elements are greater, otherwise they are compared element by element.
+/// * Strings are compared by bytes, like the C collation.
+///
+/// For historical reasons an empty top level Array is less than any top level scalar.
+/// All values can be compared, Null equals Null.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct Postgres;
+
+/// MySQL `JSON` comparison.
+///
+/// Boolean > Array > Object > String > Number > Null, values of the same type:
+/// * Arrays are compared element by element, an Array is greater than its prefixes.
+/// * Objects are compared like in `Sqlite`, MySQL only defines their equality.
+/// * Strings are compared by bytes.
+///
+/// All values can be compared, Null equals Null.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct MySql;
+
+/// JavaScript's abstract relational comparison, as used by `<` and `>`.
+///
+/// Both values are converted to primitives: Arrays join their elements with `,` and Objects
+/// become `"[object Object]"`. Two Strings are compared by UTF-16 code units, anything else is
+/// converted to numbers, where Null is 0, Booleans are 0 or 1 and Strings which aren't numeric
+/// literals are NaN. Values are equal if neither is less than the other, so `null` equals `0`
+/// and `[1]` equals `"1"`. Comparisons with NaN return None.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct JavaScript;
+
+impl<C: Comparator, L: Collation> Comparator for Collated<C, L> {
+    #[inline]
+    fn partial_cmp<V: JsonLike>(&self, a: &V, b: &V) -> Option<Ordering> {
+        self.0.partial_cmp_collated(a, b, &self.1)
+    }
+
+    #[inline]
+    fn partial_cmp_collated<V: JsonLike, M: Collation + ?Sized>(
+        &self,
+        a: &V,
+        b: &V,
+        collation: &M,
+    ) -> Option<Ordering> {
+        self.0.partial_cmp_collated(a, b, collation)
+    }
+}
+
+impl Comparator for Sqlite {
+    #[inline]
+    fn partial_cmp_collated<V: JsonLike, L: Collation + ?Sized>(
+        &self,
+        a: &V,
+        b: &V,
+        collation: &L,
+    ) -> Option<Ordering> {
+        value::partial_cmp_collated(a, b, collation)
+    }
+}
+
+impl Comparator for Postgres {
+    fn partial_cmp_collated<V: JsonLike, L: Collation + ?Sized>(
+        &self,
+        a: &V,
+        b: &V,
+        collation: &L,
+    ) -> Option<Ordering> {
+        // Top level scalars are stored as arrays with one element, so they are greater than the
+        // empty array.
+        let empty = |v: &V| v.as_array().is_some_and(Vec::is_empty);
+        let scalar = |v: &V| v.as_array().is_none() && !v.is_object();
+        if empty(a) && scalar(b) {
+            return Some(Ordering::Less);
+        }
+        if scalar(a) && empty(b) {
+            return Some(Ordering::Greater);
+        }
+        postgres(a, b, collation)
+    }
+}
+
+impl Comparator for MySql {
+    fn partial_cmp_collated<V: JsonLike, L: Collation + ?Sized>(
+        &self,
+        a: &V,
+        b: &V,
+        collation: &L,
+    ) -> Option<Ordering> {
+        mysql(a, b, collation)
+    }
+}
+
+impl Comparator for JavaScript {
+    // Without a collation Strings are compared by UTF-16 code units.
+    fn partial_cmp<V: JsonLike>(&self, a: &V, b: &V) -> Option<Ordering> {
+        self.partial_cmp_collated(a, b, &Utf16)
+    }
+
+    fn partial_cmp_collated<V: JsonLike, L: Collation + ?Sized>(
+        &self,
+        a: &V,
+        b: &V,
+        collation: &L,
+    ) -> Option<Ordering> {
+        match (primitive(a), primitive(b)) {
+            (Primitive::String(a), Primitive::String(b)) => Some(collation.compare(&a, &b)),
+            (a, b) => a.to_number().partial_cmp(&b.to_number()),
+        }
+    }
+}
+
+// JavaScript's string order.
+struct Utf16;
+
+impl Collation for Utf16 {
+    fn compare(&self, a: &str, b: &str) -> Ordering {
+        a.encode_utf16().cmp(b.encode_utf16())
+    }
+}
+
+/// Sorts `values` with `comparator`, values which compare equal keep their order.
+///
+/// Values which can't be compared are treated as equal. Unlike `slice::sort_by` this doesn't
+/// panic if that makes the order inconsistent, the resulting order is unspecified then.
+pub fn sort<V: JsonLike, C: Comparator>(values: &mut [V], comparator: &C) {
+    let mut order: Vec<usize> = (0..values.len()).collect();
+    let mut buffer = Vec::with_capacity(values.len());
+    merge_sort(&mut order, &mut buffer, &|i, j| {
+        comparator.partial_cmp(&values[i], &values[j]) == Some(Ordering::Greater)
+    });
+    // order[k] is the index of the value which belongs at k, earlier positions are done.
+    for k in 0..order.len() {
+        let mut i = order[k];
+        while i < k {
+            i = order[i];
+        }
+        values.swap(k, i);
+    }
+}
+
+// A stable merge sort of indices, greater(i, j) returns whether i must come after j.
+fn merge_sort(
+    items: &mut [usize],
+    buffer: &mut Vec<usize>,
+    greater: &impl Fn(usize, usize) -> bool,
+) {
+    if items.len() < 2 {
+        return;
+    }
+    let mid = items.len() / 2;
+    merge_sort(&mut items[..mid], buffer, greater);
+    merge_sort(&mut items[mid..], buffer, greater);
+    buffer.clear();
+    let (mut i, mut j) = (0, mid);
+    while i < mid && j < items.len() {
+        if greater(items[i], items[j]) {
+            buffer.push(items[j]);
+            j += 1;
+        } else {
+            buffer.push(items[i]);
+            i += 1;
+        }
+    }
+    buffer.extend_from_slice(&items[i..mid]);
+    buffer.extend_from_slice(&items[j..]);
+    items.copy_from_slice(buffer);
+}
+
+/// Removes every value which compares equal to an earlier value with `comparator`.
+pub fn distinct<V: JsonLike, C: Comparator>(values: &mut Vec<V>, comparator: &C) {
+    let mut kept: Vec<V> = Vec::with_capacity(values.len());
+    for v in values.drain(..) {
+        if !kept
+            .iter()
+            .any(|k| comparator.partial_cmp(k, &v) == Some(Ordering::Equal))
+        {
+            kept.push(v);
+        }
+    }
+    *values = kept;
+}
+
+// The type of a JsonLike value, ranked by each comparator.
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum Type {
+    Null,
+    Bool,
+    Number,
+    String,
+    Array,
+    Object,
+    Other, // a value without a JSON equivalent, like a TOML datetime
+}
+
+fn type_of<V: JsonLike>(value: &V) -> Type {
+    if value.is_null() {
+        Type::Null
+    } else if value.as_bool().is_some() {
+        Type::Bool
+    } else if value.as_number().is_some() {
+        Type::Number
+    } else if value.as_str().is_some() {
+        Type::String
+    } else if value.as_array().is_some() {
+        Type::Array
+    } else if value.is_object() {
+        Type::Object
+    } else {
+        Type::Other
+    }
+}
+
+// Compares the values of two JsonLikes of the same scalar type.
+fn cmp_scalar<V: JsonLike, L: Collation + ?Sized>(a: &V, b: &V, c: &L) -> Option<Ordering> {
+    if let (Some(a), Some(b)) = (a.as_bool(), b.as_bool()) {
+        Some(a.cmp(&b))
+    } else if let (Some(a), Some(b)) = (a.as_number(), b.as_number()) {
+        Some(cmp_numbers(&a, &b))
+    } else if let (Some(a), Some(b)) = (a.as_str(), b.as_str()) {
+        Some(c.compare(a, b))
+    } else if a.is_null() && b.is_null() {
+        Some(Ordering::Equal)
+    } else {
+        None
+    }
+}
+
+// Compares the first pair of elements which isn't equal.
+fn first_difference<'v, V: 'v>(
+    pairs: impl Iterator<Item = (&'v V, &'v V)>,
+    cmp: impl Fn(&V, &V) -> Option<Ordering>,
+) -> Option<Option<Ordering>> {
+    for (a, b) in pairs {
+        match cmp(a, b) {
+            Some(Ordering::Equal) => {}
+            ordering => return Some(ordering),
+        }
+    }
+    None
+}
+
+// Keys sorted like in jsonb, shorter keys come first.
+fn storage_order<V: JsonLike>(value: &V) -> Vec<&str> {
+    let mut keys = value.keys();
+    keys.sort_unstable_by(|x, y| x.len().cmp(&y.len()).then_with(|| x.cmp(y)));
+    keys
+}
+
+fn postgres<V: JsonLike, L: Collation + ?Sized>(a: &V, b: &V, c: &L) -> Option<Ordering> {
+    let rank = |t| match t {
+        Type::Null => 0,
+        Type::String => 1,
+        Type::Number => 2,
+        Type::Bool => 3,
+        Type::Array => 4,
+        Type::Object => 5,
+        Type::Other => 6,
+    };
+    let (ta, tb) = (type_of(a), type_of(b));
+    if ta != tb {
+        return Some(rank(ta).cmp(&rank(tb)));
+    }
+    match ta {
+        Type::Array => {
+            let (a, b) = (a.as_array()?, b.as_array()?);
+            if a.len() != b.len() {
+                return Some(a.len().cmp(&b.len()));
+            }
+            let elements = a.iter().zip(b);
+            first_difference(elements, |a, b| postgres(a, b, c)).unwrap_or(Some(Ordering::Equal))
+        }
+        Type::Object => {
+            let (ka, kb) = (storage_order(a), storage_order(b));
+            if ka.len() != kb.len() {
+                return Some(ka.len().cmp(&kb.len()));
+            }
+            for (ka, kb) in ka.into_iter().zip(kb) {
+                match ka.cmp(kb) {
+                    Ordering::Equal => {}
+                    ordering => return Some(ordering),
+                }
+                match postgres(a.get_key(ka)?, b.get_key(kb)?, c)? {
+                    Ordering::Equal => {}
+                    ordering => return Some(ordering),
+                }
+            }
+            Some(Ordering::Equal)
+        }
+        Type::Other => (a == b).then_some(Ordering::Equal),
+        _ => cmp_scalar(a, b, c),
+    }
+}
+
+fn mysql<V: JsonLike, L: Collation + ?Sized>(a: &V, b: &V, c: &L) -> Option<Ordering> {
+    let rank = |t| match t {
+        Type::Null => 0,
+        Type::Number => 1,
+        Type::String => 2,
+        Type::Object => 3,
+        Type::Array => 4,
+        Type::Bool => 5,
+        Type::Other => 6,
+    };
+    let (ta, tb) = (type_of(a), type_of(b));
+    if ta != tb {
+        return Some(rank(ta).cmp(&rank(tb)));
+    }
+    match ta {
+        Type::Array => {
+            let (a, b) = (a.as_array()?, b.as_array()?);
+            let elements = a.iter().zip(b);
+            first_difference(elements, |a, b| mysql(a, b, c)).unwrap_or(Some(a.len().cmp(&b.len())))
+        }
+        Type::Object => {
+            let (mut ka, mut kb) = (a.keys(), b.keys());
+            ka.sort_unstable();
+            kb.sort_unstable();
+            for (x, y) in ka.iter().zip(&kb) {
+                match x.cmp(y) {
+                    Ordering::Equal => {}
+                    ordering => return Some(ordering),
+                }
+                match mysql(a.get_key(x)?, b.get_key(y)?, c)? {
+                    Ordering::Equal => {}
+                    ordering => return Some(ordering),
+                }
+            }
+            Some(ka.len().cmp(&kb.len()))
+        }
+        Type::Other => (a == b).then_some(Ordering::Equal),
+        _ => cmp_scalar(a, b, c),
+    }
+}
+
+// A value converted by JavaScript's ToPrimitive.
+enum Primitive {
+    Null,
+    Bool(bool),
+    Number(f64),
+    String(String),
+}
+
+impl Primitive {
+    // JavaScript's ToNumber.
+    fn to_number(&self) -> f64 {
+        match self {
+            Primitive::Null => 0.0,
+            Primitive::Bool(b) => f64::from(u8::from(*b)),
+            Primitive::Number(n) => *n,
+            Primitive::String(s) => string_to_number(s),
+        }
+    }
+}
+
+fn primitive<V: JsonLike>(value: &V) -> Primitive {
+    if value.is_null() {
+        Primitive::Null
+    } else if let Some(b) = value.as_bool() {
+        Primitive::Bool(b)
+    } else if let Some(n) = value.as_number() {
+        Primitive::Number(n.as_f64().unwrap_or(f64::NAN))
+    } else if let Some(s) = value.as_str() {
+        Primitive::String(s.to_string())
+    } else {
+        let mut s = String::new();
+        to_string(value, &mut s);
+        Primitive::String(s)
+    }
+}
+
+// JavaScript's ToString of a value, Arrays are joined with "," and Null inside Arrays is empty.
+fn to_string<V: JsonLike>(value: &V, out: &mut String) {
+    if let Some(array) = value.as_array() {
+        for (i, v) in array.iter().enumerate() {
+            if i > 0 {
+                out.push(',');
+            }
+            if !v.is_null() {
+                to_string(v, out);
+            }
+        }
+    } else if value.is_null() {
+        out.push_str("null");
+    } else if let Some(b) = value.as_bool() {
+        out.push_str(if b { "true" } else { "false" });
+    } else if let Some(n) = value.as_number() {
+        match (n.as_i64(), n.as_u64(), n.as_f64()) {
+            (Some(i), _, _) => out.push_str(&i.to_string()),
+            (_, Some(u), _) => out.push_str(&number_to_string(u as f64)),
+            (_, _, Some(f)) => out.push_str(&number_to_string(f)),
+            _ => out.push_str("NaN"),
+        }
+    } else if let Some(s) = value.as_str() {
+        out.push_str(s);
+    } else {
+        out.push_str("[object Object]");
+    }
+}
+
+// JavaScript's Number::toString, the shortest digits which read back as f.
+fn number_to_string(f: f64) -> String {
+    if f == 0.0 {
+        return "0".to_string();
+    }
+    // "{:e}" gives the shortest digits as d.ddde±x.
+    let formatted = format!("{:e}", f.abs());
+    let (mantissa, exp) = formatted.split_once('e').unwrap_or((&formatted, "0"));
+    let digits = mantissa.replace('.', "");
+    let k = digits.len() as i32;
+    let n = exp.parse::<i32>().unwrap_or_default() + 1;
+    let sign = if f < 0.0 { "-" } else { "" };
+    let body = if k <= n && n <= 21 {
+        format!("{digits}{}", "0".repeat((n - k) as usize))
+    } else if 0 < n && n <= 21 {
+        format!("{}.{}", &digits[..n as usize], &digits[n as usize..])
+    } else if -6 < n && n <= 0 {
+        format!("0.{}{digits}", "0".repeat(-n as usize))
+    } else {
+        let exp_sign = if n > 0 { "+" } else { "-" };
+        let fraction = if k > 1 {
+            format!(".{}", &digits[1..])
+        } else {
+            String::new()
+        };
+        format!("{}{fraction}e{exp_sign}{}", &digits[..1], (n - 1).abs())
+    };
+    format!("{sign}{body}")
+}
+
+// JavaScript's StringToNumber, NaN if s isn't a numeric literal.
+fn string_to_number(s: &str) -> f64 {
+    let s = s.trim();
+    if s.is_empty() {
+        return 0.0;
+    }
+    for (prefix, radix) in [("0x", 16), ("0o", 8), ("0b", 2)] {
+        let rest = s
+            .strip_prefix(prefix)
+            .or_else(|| s.strip_prefix(&prefix.to_uppercase()));
+        if let Some(rest) = rest {
+            return u128::from_str_radix(rest, radix).map_or(f64::NAN, |n| n as f64);
+        }
+    }
+    let unsigned = s.strip_prefix(['+', '-']).unwrap_or(s);
+    if unsigned == "Infinity" {
+        return if s.starts_with('-') {
+            f64::NEG_INFINITY
+        } else {
+            f64::INFINITY
+        };
+    }
+    // Rust also reads "inf" and "NaN", JavaScript only digits, a point and an exponent.
+    if !unsigned
+        .bytes()
+        .all(|b| b.is_ascii_digit() || matches!(b, b'.' | b'e' | b'E' | b'+' | b'-'))
+    {
+        return f64::NAN;
+    }
+    s.parse().unwrap_or(f64::NAN)
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::{json, Value};
+
+    use super::*;
+    use crate::collation::{Natural, NoCase, RTrim};
+
+    use Ordering::{Equal as E, Greater as G, Less as L};
+
+    #[test]
+    fn test_conformance() {
+        // Expected orderings of a and b for SQLite, PostgreSQL, MySQL and JavaScript.
+        let tests: Vec<(Value, Value, [Option<Ordering>; 4])> = vec![
+            (
+                json!(null),
+                json!(null),
+                [Some(E), Some(E), Some(E), Some(E)],
+            ),
+            (json!(null), json!(0), [None, Some(L), Some(L), Some(E)]),
+            (json!(null), json!(false), [None, Some(L), Some(L), Some(E)]),
+            (json!(null), json!(""), [None, Some(L), Some(L), Some(E)]),
+            (json!(null), json!("a"), [None, Some(L), Some(L), None]),
+            (json!(null), json!([]), [None, Some(G), Some(L), Some(E)]),
+            (json!(null), json!({}), [None, Some(L), Some(L), None]),
+            (json!(true), json!(1), [Some(E), Some(G), Some(G), Some(E)]),
+            (json!(false), json!(2), [Some(L), Some(G), Some(G), Some(L)]),
+            (json!(true), json!("a"), [Some(L), Some(G), Some(G), None]),
+            (json!(true), json!([]), [Some(L), Some(G), Some(G), Some(G)]),
+            (json!(true), json!({}), [Some(L), Some(L), Some(G), None]),
+            (json!(1), json!(1.0), [Some(E), Some(E), Some(E), Some(E)]),
+            (json!(-1), json!(2.5), [Some(L), Some(L), Some(L), Some(L)]),
+            (json!(10), json!("9"), [Some(G), Some(G), Some(L), Some(G)]),
+            (json!(2), json!("10"), [Some(L), Some(G), Some(L), Some(L)]),
+            (json!(1), json!("a"), [Some(L), Some(G), Some(L), None]),
+            (json!(1), json!([1]), [Some(L), Some(L), Some(L), Some(E)]),
+            (json!(1), json!({}), [Some(L), Some(L), Some(L), None]),
+            (
+                json!("10"),
+                json!("9"),
+                [Some(L), Some(L), Some(L), Some(L)],
+            ),
+            (
+                json!("b"),
+                json!("ab"),
+                [Some(G), Some(G), Some(G), Some(G)],
+            ),
+            (json!("Z"), json!("a"), [Some(L), Some(L), Some(L), Some(L)]),
+            (
+                json!("\u{ff61}"),
+                json!("\u{1f600}"),
+                [Some(L), Some(L), Some(L), Some(G)],
+            ),
+            (json!("a"), json!([]), [Some(L), Some(G), Some(L), Some(G)]),
+            (
+                json!("1,2"),
+                json!([1, 2]),
+                [Some(L), Some(L), Some(L), Some(E)],
+            ),
+            (json!("a"), json!({}), [Some(L), Some(L), Some(L), Some(G)]),
+            (json!([]), json!([]), [Some(E), Some(E), Some(E), Some(E)]),
+            (
+                json!([2]),
+                json!([1, 3]),
+                [Some(G), Some(L), Some(G), Some(G)],
+            ),
+            (
+                json!([1, 2]),
+                json!([1]),
+                [Some(G), Some(G), Some(G), Some(G)],
+            ),
+            (
+                json!([1, 2]),
+                json!([1, 3]),
+                [Some(L), Some(L), Some(L), Some(L)],
+            ),
+            (
+                json!([10]),
+                json!([9]),
+                [Some(G), Some(G), Some(G), Some(L)],
+            ),
+            (json!([null]), json!([0]), [None, Some(L), Some(L), Some(L)]),
+            (json!([]), json!({}), [Some(L), Some(L), Some(G), Some(L)]),
+            (json!({}), json!({}), [Some(E), Some(E), Some(E), Some(E)]),
+            (
+                json!({"a": 1}),
+                json!({"a": 2}),
+                [Some(L), Some(L), Some(L), Some(E)],
+            ),
+            (
+                json!({"b": 1}),
+                json!({"a": 1, "c": 1}),
+                [Some(G), Some(L), Some(G), Some(E)],
+            ),
+            (
+                json!({"aa": 1, "c": 1}),
+                json!({"b": 1, "d": 1}),
+                [Some(L), Some(G), Some(L), Some(E)],
+            ),
+        ];
+        for (a, b, expected) in tests {
+            let result = [
+                Sqlite.partial_cmp(&a, &b),
+                Postgres.partial_cmp(&a, &b),
+                MySql.partial_cmp(&a, &b),
+                JavaScript.partial_cmp(&a, &b),
+            ];
+            assert_eq!(
+                result, expected,
+                "expected {} compared to {} to be {:?}",
+                a, b, expected
+            );
+        }
+    }
+
+    #[test]
+    fn test_collated() {
+        let tests = vec![
+            (json!("a"), json!("B"), Some(L)),
+            (json!("ABC"), json!("abc"), Some(E)),
+            (json!(["x", "Y"]), json!(["X", "y"]), Some(E)),
+            (json!({"a": "x"}), json!({"a": "X"}), Some(E)),
+            (json!({"a": 1}), json!({"A": 1}), Some(G)),
+        ];
+        for (a, b, expected) in tests {
+            let result = [
+                Collated(Sqlite, NoCase).partial_cmp(&a, &b),
+                Collated(Postgres, NoCase).partial_cmp(&a, &b),
+                Collated(MySql, NoCase).partial_cmp(&a, &b),
+                Sqlite.partial_cmp_collated(&a, &b, &NoCase),
+            ];
+            assert_eq!(
+                result, [expected; 4],
+                "expected {} compared to {} to be {:?}",
+                a, b, expected
+            );
+        }
+        let (a, b) = (json!(["file10"]), json!(["file9"]));
+        assert_eq!(JavaScript.partial_cmp(&a, &b), Some(L));
+        assert_eq!(
+            Collated(JavaScript, Natural(Binary)).partial_cmp(&a, &b),
+            Some(G)
+        );
+    }
+
+    #[test]
+    fn test_sort() {
+        let values = vec![
+            json!("file10"),
+            json!("File2"),
+            json!(null),
+            json!("file9"),
+            json!(3),
+            json!("file2"),
+        ];
+        let mut result = values.clone();
+        sort(&mut result, &Postgres);
+        let expected = vec![
+            json!(null),
+            json!("File2"),
+            json!("file10"),
+            json!("file2"),
+            json!("file9"),
+            json!(3),
+        ];
+        assert_eq!(
+            result, expected,
+            "expected Postgres to sort as {:?}",
+            expected
+        );
+
+        let mut result = values.clone();
+        sort(&mut result, &Collated(MySql, Natural(NoCase)));
+        let expected = vec![
+            json!(null),
+            json!(3),
+            json!("File2"),
+            json!("file2"),
+            json!("file9"),
+            json!("file10"),
+        ];
+        assert_eq!(result, expected, "expected MySql to sort as {:?}", expected);
+
+        // Null can't be compared in SQLite, the order is unspecified but nothing is lost.
+        let mut result = values.clone();
+        sort(&mut result, &Sqlite);
+        assert!(values.iter().all(|v| result.contains(v)));
+    }
+
+    #[test]
+    fn test_distinct() {
+        let mut values = vec![json!("a"), json!("A "), json!(1), json!("a"), json!(1.0)];
+        distinct(&mut values, &Sqlite);
+        assert_eq!(values, vec![json!("a"), json!("A "), json!(1)]);
+        distinct(&mut values, &Collated(Sqlite, NoCase));
+        assert_eq!(values, vec![json!("a"), json!("A "), json!(1)]);
+        distinct(&mut values, &Collated(Sqlite, Natural(NoCase)));
+        assert_eq!(values, vec![json!("a"), json!("A "), json!(1)]);
+        let mut values = vec![json!("a"), json!("A "), json!(1), json!("A")];
+        distinct(&mut values, &Collated(Sqlite, RTrim));
+        assert_eq!(values, vec![json!("a"), json!("A "), json!(1)]);
+        distinct(&mut values, &Collated(Postgres, NoCase));
+        assert_eq!(values, vec![json!("a"), json!("A "), json!(1)]);
+    }
+
+    #[test]
+    fn test_nested_empty_array() {
+        // Only a top level empty Array is less than scalars in PostgreSQL.
+        let tests = vec![
+            (json!([]), json!(null), Some(L)),
+            (json!([]), json!(-1), Some(L)),
+            (json!([[]]), json!([null]), Some(G)),
+            (json!({"a": []}), json!({"a": null}), Some(G)),
+        ];
+        for (a, b, expected) in tests {
+            let result = Postgres.partial_cmp(&a, &b);
+            assert_eq!(
+                result, expected,
+                "expected {} compared to {} to be {:?}",
+                a, b, expected
+            );
+            let result = Postgres.partial_cmp(&b, &a);
+            let expected = expected.map(Ordering::reverse);
+            assert_eq!(
+                result, expected,
+                "expected {} compared to {} to be {:?}",
+                b, a, expected
+            );
+        }
+    }
+
+    #[test]
+    fn test_number_to_string() {
+        let tests = vec![
+            (0.0, "0"),
+            (-0.0, "0"),
+            (1.0, "1"),
+            (-1.5, "-1.5"),
+            (0.1, "0.1"),
+            (123456.789, "123456.789"),
+            (1e21, "1e+21"),
+            (1.5e300, "1.5e+300"),
+            (1e20, "100000000000000000000"),
+            (0.000001, "0.000001"),
+            (1e-7, "1e-7"),
+            (-2.5e-8, "-2.5e-8"),
+        ];
+        for (f, expected) in tests {
+            let result = number_to_string(f);
+            assert_eq!(result, expected, "expected {} to be {}", f, expected);
+        }
+    }
+
+    #[test]
+    fn test_string_to_number() {
+        let tests = vec![
+            ("", 0.0),
+            ("  12 ", 12.0),
+            ("-1.5e2", -150.0),
+            (".5", 0.5),
+            ("0x1F", 31.0),
+            ("0b11", 3.0),
+            ("-Infinity", f64::NEG_INFINITY),
+        ];
+        for (s, expected) in tests {
+            let result = string_to_number(s);
+            assert_eq!(result, expected, "expected {:?} to be {}", s, expected);
+        }
+        for s in ["abc", "1e", "inf", "NaN", "-0x10", "1,2", "1 2"] {
+            assert!(string_to_number(s).is_nan(), "expected {:?} to be NaN", s);
+        }
+    }
+}
@@ -0,0 +1,230 @@
+use crate::{
+    json_like::JsonLike,
+    json_path::{JsonPathElementRef, JsonPathIndex},
+};
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum Position {
+    Key(String),  // key inside an object
+    Index(usize), // absolute index inside an array
+}
+
+/// A view into a single place of a value, either occupied or vacant.
+///
+/// Created by `JsonPath::entry`.
+#[derive(Debug)]
+pub enum Entry<'a, V> {
+    Occupied(OccupiedEntry<'a, V>),
+    Vacant(VacantEntry<'a, V>),
+}
+
+/// A place holding a value, an existing key of an object or an element of an array.
+#[derive(Debug)]
+pub struct OccupiedEntry<'a, V> {
+    parent: &'a mut V,
+    position: Position,
+}
+
+/// A place without a value, a missing key of an object or the end of an array.
+#[derive(Debug)]
+pub struct VacantEntry<'a, V> {
+    parent: &'a mut V,
+    position: Position,
+}
+
+// Returns the entry at last inside parent, None if parent can't hold a value there.
+pub(crate) fn entry<'a, V: JsonLike>(
+    parent: &'a mut V,
+    last: &JsonPathElementRef,
+) -> Option<Entry<'a, V>> {
+    match last {
+        JsonPathElementRef::Field(key) => {
+            if !parent.is_object() {
+                return None;
+            }
+            let occupied = parent.get_key(key).is_some();
+            let position = Position::Key(key.to_string());
+            Some(if occupied {
+                Entry::Occupied(OccupiedEntry { parent, position })
+            } else {
+                Entry::Vacant(VacantEntry { parent, position })
+            })
+        }
+        JsonPathElementRef::Index(index) => {
+            let len = parent.as_array()?.len();
+            let i = match *index {
+                JsonPathIndex::NthLefth(i) => i,
+                JsonPathIndex::NthRight(i) => len.checked_sub(i)?,
+            };
+            let position = Position::Index(i);
+            match i.cmp(&len) {
+                std::cmp::Ordering::Less => {
+                    Some(Entry::Occupied(OccupiedEntry { parent, position }))
+                }
+                std::cmp::Ordering::Equal => Some(Entry::Vacant(VacantEntry { parent, position })),
+                std::cmp::Ordering::Greater => None,
+            }
+        }
+    }
+}
+
+impl<'a, V: JsonLike> Entry<'a, V> {
+    /// Inserts `default` if vacant and returns the value.
+    pub fn or_insert(self, default: V) -> &'a mut V {
+        match self {
+            Entry::Occupied(entry) => entry.into_mut(),
+            Entry::Vacant(entry) => entry.insert(default),
+        }
+    }
+
+    /// Inserts the result of `default` if vacant and returns the value.
+    pub fn or_insert_with<F: FnOnce() -> V>(self, default: F) -> &'a mut V {
+        match self {
+            Entry::Occupied(entry) => entry.into_mut(),
+            Entry::Vacant(entry) => entry.insert(default()),
+        }
+    }
+
+    /// Calls `f` with the value if occupied.
+    pub fn and_modify<F: FnOnce(&mut V)>(self, f: F) -> Self {
+        match self {
+            Entry::Occupied(mut entry) => {
+                f(entry.get_mut());
+                Entry::Occupied(entry)
+            }
+            entry => entry,
+        }
+    }
+}
+
+impl<'a, V: JsonLike> OccupiedEntry<'a, V> {
+    pub fn get(&self) -> &V {
+        match &self.position {
+            Position::Key(key) => self.parent.get_key(key),
+            Position::Index(i) => self.parent.as_array().and_then(|a| a.get(*i)),
+        }
+        .expect("occupied entry holds a value")
+    }
+
+    pub fn get_mut(&mut self) -> &mut V {
+        match &self.position {
+            Position::Key(key) => self.parent.get_key_mut(key),
+            Position::Index(i) => self.parent.as_array_mut().and_then(|a| a.get_mut(*i)),
+        }
+        .expect("occupied entry holds a value")
+    }
+
+    /// Converts the entry into a reference to the value with the lifetime of the entry.
+    pub fn into_mut(self) -> &'a mut V {
+        match self.position {
+            Position::Key(key) => self.parent.get_key_mut(&key),
+            Position::Index(i) => self.parent.as_array_mut().and_then(|a| a.get_mut(i)),
+        }
+        .expect("occupied entry holds a value")
+    }
+
+    /// Replaces the value and returns the previous value.
+    pub fn insert(&mut self, value: V) -> V {
+        std::mem::replace(self.get_mut(), value)
+    }
+
+    /// Removes the value, following array elements move one position to the left.
+    pub fn remove(self) -> V {
+        match self.position {
+            Position::Key(key) => self.parent.remove_key(&key),
+            Position::Index(i) => self.parent.as_array_mut().map(|a| a.remove(i)),
+        }
+        .expect("occupied entry holds a value")
+    }
+}
+
+impl<'a, V: JsonLike> VacantEntry<'a, V> {
+    /// Inserts the value and returns a reference to it.
+    pub fn insert(self, value: V) -> &'a mut V {
+        let occupied = OccupiedEntry {
+            parent: self.parent,
+            position: self.position,
+        };
+        match &occupied.position {
+            Position::Key(key) => {
+                occupied.parent.insert_key(key, value);
+            }
+            Position::Index(_) => {
+                if let Some(array) = occupied.parent.as_array_mut() {
+                    array.push(value);
+                }
+            }
+        }
+        occupied.into_mut()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use crate::json_path::JsonPath;
+
+    use super::*;
+
+    #[test]
+    fn test_entry() {
+        let value = json!({"a": {"b": 1}, "l": [1, 2]});
+        let tests = vec![
+            ("$.a.b", Some(Some(json!(1)))),
+            ("$.a.c", Some(None)),
+            ("$.l[1]", Some(Some(json!(2)))),
+            ("$.l[#-2]", Some(Some(json!(1)))),
+            ("$.l[2]", Some(None)),
+            ("$.l[#]", Some(None)),
+            ("$.l[3]", None),
+            ("$.l[#-3]", None),
+            ("$.a[0]", None),
+            ("$.l.a", None),
+            ("$.x.y", None),
+            ("$", None),
+        ];
+        for (path, expected) in tests {
+            let path: JsonPath = path.try_into().unwrap();
+            let mut value = value.clone();
+            let result = path.entry(&mut value).map(|entry| match entry {
+                Entry::Occupied(entry) => Some(entry.get().clone()),
+                Entry::Vacant(_) => None,
+            });
+            assert_eq!(result, expected, "expected {} to be {:?}", path, expected);
+        }
+    }
+
+    #[test]
+    fn test_modify() {
+        let mut value = json!({"a": {"b": 1}, "l": [1, 2]});
+        let path = |p: &str| -> JsonPath { p.try_into().unwrap() };
+
+        *path("$.a.c").entry(&mut value).unwrap().or_insert(json!(0)) = json!(3);
+        path("$.a.b")
+            .entry(&mut value)
+            .unwrap()
+            .and_modify(|v| *v = json!(v.as_i64().unwrap() + 10))
+            .or_insert_with(|| unreachable!());
+        path("$.l[#]")
+            .entry(&mut value)
+            .unwrap()
+            .or_insert_with(|| json!(3));
+        let Some(Entry::Occupied(mut entry)) = path("$.l[#-3]").entry(&mut value) else {
+            panic!("expected $.l[#-3] to be occupied");
+        };
+        assert_eq!(entry.insert(json!(0)), json!(1));
+        let Some(Entry::Occupied(entry)) = path("$.l[1]").entry(&mut value) else {
+            panic!("expected $.l[1] to be occupied");
+        };
+        assert_eq!(entry.remove(), json!(2));
+        let Some(Entry::Vacant(entry)) = path("$.d").entry(&mut value) else {
+            panic!("expected $.d to be vacant");
+        };
+        *entry.insert(json!([])) = json!([5]);
+        assert_eq!(
+            value,
+            json!({"a": {"b": 11, "c": 3}, "l": [0, 3], "d": [5]})
+        );
+    }
+}
@@ -0,0 +1,236 @@
+use std::{error, fmt};
+
+use crate::{
+    json_like::JsonLike,
+    json_path::{self, JsonPath, JsonPathElement, JsonPathElementRef},
+};
+
+const MAX_SUGGESTIONS: usize = 3;
+
+/// The type of the value found where a path couldn't be followed.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FoundType {
+    Null,
+    Bool,
+    Number,
+    String,
+    Array(usize),  // length of the array
+    Object(usize), // number of keys
+    Other,         // a value without a JSON equivalent, like a TOML datetime
+}
+
+impl FoundType {
+    pub fn of<V: JsonLike>(value: &V) -> Self {
+        if value.is_null() {
+            FoundType::Null
+        } else if value.as_bool().is_some() {
+            FoundType::Bool
+        } else if value.as_number().is_some() {
+            FoundType::Number
+        } else if value.as_str().is_some() {
+            FoundType::String
+        } else if let Some(array) = value.as_array() {
+            FoundType::Array(array.len())
+        } else if value.is_object() {
+            FoundType::Object(value.keys().len())
+        } else {
+            FoundType::Other
+        }
+    }
+}
+
+impl fmt::Display for FoundType {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            FoundType::Null => f.write_str("null"),
+            FoundType::Bool => f.write_str("boolean"),
+            FoundType::Number => f.write_str("number"),
+            FoundType::String => f.write_str("string"),
+            FoundType::Array(len) => write!(f, "array of length {len}"),
+            FoundType::Object(1) => f.write_str("object with 1 key"),
+            FoundType::Object(len) => write!(f, "object with {len} keys"),
+            FoundType::Other => f.write_str("other value"),
+        }
+    }
+}
+
+/// Why a path couldn't be followed, returned by `JsonPath::find_explain`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FindError {
+    pub matched: JsonPath,        // longest prefix of the path which was found
+    pub segment: JsonPathElement, // element of the path which couldn't be followed
+    pub found: FoundType,         // type of the value at matched
+    pub suggestions: Vec<String>, // closest existing keys if segment is a missing key
+}
+
+impl fmt::Display for FindError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let matched = &self.matched;
+        match (&self.segment, self.found) {
+            (JsonPathElement::Field(key), FoundType::Object(_)) => {
+                write!(f, "missing key `{key}` in object at `{matched}`")?;
+                for (i, suggestion) in self.suggestions.iter().enumerate() {
+                    let sep = if i == 0 { ", did you mean " } else { " or " };
+                    write!(f, "{sep}`{suggestion}`")?;
+                }
+                if !self.suggestions.is_empty() {
+                    f.write_str("?")?;
+                }
+                Ok(())
+            }
+            (JsonPathElement::Field(_), found) => {
+                write!(f, "expected object at `{matched}`, found {found}")
+            }
+            (JsonPathElement::Index(i), found @ FoundType::Array(_)) => {
+                write!(f, "index [{i}] out of range at `{matched}`, found {found}")
+            }
+            (JsonPathElement::Index(_), found) => {
+                write!(f, "expected array at `{matched}`, found {found}")
+            }
+        }
+    }
+}
+
+impl error::Error for FindError {}
+
+// Follows elements like find, on failure explains where and why.
+pub(crate) fn find_explain<'v, 'e, V: JsonLike>(
+    value: &'v V,
+    elements: impl Iterator<Item = JsonPathElementRef<'e>> + Clone,
+) -> Result<&'v V, FindError> {
+    let mut value = value;
+    for (n, e) in elements.clone().enumerate() {
+        match json_path::step(value, &e) {
+            Some(sub) => value = sub,
+            None => {
+                let suggestions = match &e {
+                    JsonPathElementRef::Field(key) => suggest(key, value.keys()),
+                    JsonPathElementRef::Index(_) => Vec::new(),
+                };
+                return Err(FindError {
+                    matched: elements
+                        .take(n)
+                        .map(JsonPathElementRef::into_owned)
+                        .collect::<Vec<_>>()
+                        .into(),
+                    segment: e.into_owned(),
+                    found: FoundType::of(value),
+                    suggestions,
+                });
+            }
+        }
+    }
+    Ok(value)
+}
+
+// Returns the keys closest to key, at most half of the key may differ.
+fn suggest(key: &str, keys: Vec<&str>) -> Vec<String> {
+    let max = key.chars().count() / 2;
+    let mut close: Vec<(usize, &str)> = keys
+        .into_iter()
+        .map(|k| (edit_distance(key, k), k))
+        .filter(|(d, _)| *d <= max)
+        .collect();
+    close.sort();
+    close
+        .into_iter()
+        .take(MAX_SUGGESTIONS)
+        .map(|(_, k)| k.to_string())
+        .collect()
+}
+
+// Levenshtein distance between a and b in characters.
+fn edit_distance(a: &str, b: &str) -> usize {
+    let b: Vec<char> = b.chars().collect();
+    let mut row: Vec<usize> = (0..=b.len()).collect();
+    for (i, ca) in a.chars().enumerate() {
+        let mut diagonal = row[0];
+        row[0] = i + 1;
+        for (j, cb) in b.iter().enumerate() {
+            let substitute = diagonal + usize::from(ca != *cb);
+            diagonal = row[j + 1];
+            row[j + 1] = substitute.min(row[j] + 1).min(diagonal + 1);
+        }
+    }
+    row[b.len()]
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::*;
+
+    #[test]
+    fn test_find_explain() {
+        let value = json!({
+            "user": {"name": "ann", "email": "a@b", "emails": [], "tags": ["x", "y", "z"]},
+            "n": null
+        });
+        let tests = vec![
+            ("$.user.name", Ok(json!("ann"))),
+            ("$.user.tags[#-1]", Ok(json!("z"))),
+            (
+                "$.user.tags.first",
+                Err("expected object at `$.user.tags`, found array of length 3"),
+            ),
+            (
+                "$.user.tags[3]",
+                Err("index [3] out of range at `$.user.tags`, found array of length 3"),
+            ),
+            (
+                "$.user.name[0]",
+                Err("expected array at `$.user.name`, found string"),
+            ),
+            (
+                "$.user.emial",
+                Err("missing key `emial` in object at `$.user`, did you mean `email`?"),
+            ),
+            (
+                "$.usr.name",
+                Err("missing key `usr` in object at `$`, did you mean `user`?"),
+            ),
+            ("$.account", Err("missing key `account` in object at `$`")),
+            ("$.n.a", Err("expected object at `$.n`, found null")),
+            (
+                "$[0]",
+                Err("expected array at `$`, found object with 2 keys"),
+            ),
+        ];
+        for (path, expected) in tests {
+            let parsed: JsonPath = path.try_into().unwrap();
+            let result = parsed
+                .find_explain(&value)
+                .cloned()
+                .map_err(|err| err.to_string());
+            let expected = expected.map_err(str::to_string);
+            assert_eq!(result, expected, "expected {} to be {:?}", path, expected);
+        }
+
+        let path: JsonPath = "$.user.tags.first".try_into().unwrap();
+        let err = path.find_explain(&value).unwrap_err();
+        assert_eq!(err.matched, "$.user.tags".try_into().unwrap());
+        assert_eq!(err.segment, JsonPathElement::Field("first".to_string()));
+        assert_eq!(err.found, FoundType::Array(3));
+    }
+
+    #[test]
+    fn test_edit_distance() {
+        let tests = vec![
+            ("", "", 0),
+            ("a", "", 1),
+            ("kitten", "sitting", 3),
+            ("email", "emial", 2),
+            ("flaw", "lawn", 2),
+            ("größe", "grösse", 2),
+        ];
+        for (a, b, expected) in tests {
+            let result = edit_distance(a, b);
+            assert_eq!(
+                result, expected,
+                "expected distance of {} and {} to be {}",
+                a, b, expected
+            );
+        }
+    }
+}
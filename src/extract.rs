@@ -0,0 +1,139 @@
+use serde::{de::DeserializeOwned, Serialize};
+use serde_json::Value;
+
+use crate::json_path::{ExtractError, JsonPath, JsonPathElement};
+
+/// A struct whose fields are read from and written to paths of a value.
+///
+/// Derived with `#[derive(JopsExtract)]` from the `jops-macros` crate, see its documentation for
+/// the field attributes.
+pub trait JopsExtract: Sized {
+    /// Reads every field, errors of all fields are collected into `ExtractError::Fields`.
+    fn extract(value: &Value) -> Result<Self, ExtractError>;
+
+    /// Writes every field back with `JsonPath::set`, errors are collected like in `extract`.
+    ///
+    /// Fields which serialize to null, like `Option::None`, aren't written and the value at their
+    /// path is removed if it is the key of an object, so extracting and injecting a missing
+    /// optional field leaves the value unchanged. An existing element at an array index is set to
+    /// null instead, removing it would shift the elements after it. Fields with `#[jops(default)]`
+    /// are written like any other field, a missing value read as its default is added to the value.
+    fn inject(&self, value: &mut Value) -> Result<(), ExtractError>;
+}
+
+// Helpers called by the derived implementations.
+
+// Prefixes the paths of err, which are relative to the value at query.
+fn nest(query: &str, err: ExtractError, errors: &mut Vec<ExtractError>) {
+    let join = |inner: String| format!("{query}{}", inner.strip_prefix('$').unwrap_or(&inner));
+    errors.push(match err {
+        ExtractError::Query(inner, err) => ExtractError::Query(join(inner), err),
+        ExtractError::Missing(inner) => ExtractError::Missing(join(inner)),
+        ExtractError::Deserialize(inner, err) => ExtractError::Deserialize(join(inner), err),
+        ExtractError::Inject(inner, err) => ExtractError::Inject(join(inner), err),
+        ExtractError::Fields(inner) => {
+            for err in inner {
+                nest(query, err, errors);
+            }
+            return;
+        }
+    });
+}
+
+#[doc(hidden)]
+pub fn field<T: DeserializeOwned>(
+    value: &Value,
+    path: &JsonPath,
+    default: Option<fn() -> T>,
+    errors: &mut Vec<ExtractError>,
+) -> Option<T> {
+    match (path.find(value), default) {
+        (Some(v), _) => T::deserialize(v)
+            .map_err(|err| {
+                errors.push(ExtractError::Deserialize(path.to_string(), err.to_string()))
+            })
+            .ok(),
+        (None, Some(default)) => Some(default()),
+        (None, None) => {
+            errors.push(ExtractError::Missing(path.to_string()));
+            None
+        }
+    }
+}
+
+#[doc(hidden)]
+pub fn nested<T: JopsExtract>(
+    value: &Value,
+    path: &JsonPath,
+    default: Option<fn() -> T>,
+    errors: &mut Vec<ExtractError>,
+) -> Option<T> {
+    match (path.find(value), default) {
+        (Some(v), _) => T::extract(v)
+            .map_err(|err| nest(&path.to_string(), err, errors))
+            .ok(),
+        (None, Some(default)) => Some(default()),
+        (None, None) => {
+            errors.push(ExtractError::Missing(path.to_string()));
+            None
+        }
+    }
+}
+
+#[doc(hidden)]
+pub fn inject_field<T: Serialize>(
+    value: &mut Value,
+    path: &JsonPath,
+    v: &T,
+    errors: &mut Vec<ExtractError>,
+) {
+    let result = serde_json::to_value(v)
+        .map_err(|err| err.to_string())
+        .and_then(|v| match v {
+            // A missing value can't be removed or replaced, there is nothing to report.
+            Value::Null => {
+                let _ = match path.last() {
+                    Some(JsonPathElement::Index(_)) => path.replace(value, Value::Null),
+                    _ => path.remove(value),
+                };
+                Ok(())
+            }
+            v => path
+                .set(value, v)
+                .map(|_| ())
+                .map_err(|err| err.to_string()),
+        });
+    if let Err(err) = result {
+        errors.push(ExtractError::Inject(path.to_string(), err));
+    }
+}
+
+#[doc(hidden)]
+pub fn inject_nested<T: JopsExtract>(
+    value: &mut Value,
+    path: &JsonPath,
+    v: &T,
+    errors: &mut Vec<ExtractError>,
+) {
+    let query = path.to_string();
+    match path.find_mut(value) {
+        Some(target) => {
+            if let Err(err) = v.inject(target) {
+                nest(&query, err, errors);
+            }
+        }
+        None => errors.push(ExtractError::Inject(
+            query,
+            "value doesn't exist".to_string(),
+        )),
+    }
+}
+
+#[doc(hidden)]
+pub fn finish(errors: Vec<ExtractError>) -> Result<(), ExtractError> {
+    if errors.is_empty() {
+        Ok(())
+    } else {
+        Err(ExtractError::Fields(errors))
+    }
+}
@@ -0,0 +1,1209 @@
+use std::{cmp::Ordering, iter::Peekable, str::Chars, str::FromStr};
+
+use serde_json::{Map, Number, Value};
+
+use crate::value::partial_cmp;
+
+/// A compiled [JMESPath](https://jmespath.org/specification.html) expression.
+///
+/// Ordering comparisons and sorting use `value::partial_cmp`, which agrees with JMESPath for
+/// numbers and strings.
+#[derive(Debug, Clone, PartialEq)]
+pub struct JmesPath(Expr);
+
+impl JmesPath {
+    /// Evaluates the expression against `data`.
+    pub fn search(&self, data: &Value) -> Result<Value, &'static str> {
+        eval(&self.0, data)
+    }
+}
+
+impl FromStr for JmesPath {
+    type Err = &'static str;
+
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        let tokens = lex(&mut value.chars().peekable())?;
+        let mut parser = Parser { tokens, pos: 0 };
+        let expr = parser.expression(0)?;
+        if parser.peek() != &Token::Eof {
+            return Err("unexpected token");
+        }
+        Ok(JmesPath(expr))
+    }
+}
+
+impl TryFrom<&str> for JmesPath {
+    type Error = &'static str;
+
+    #[inline]
+    fn try_from(value: &str) -> Result<Self, Self::Error> {
+        JmesPath::from_str(value)
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum Expr {
+    Current,
+    Field(String),
+    Subexpr(Box<Expr>, Box<Expr>),
+    Index(Box<Expr>, i64),
+    Slice(Box<Expr>, Option<i64>, Option<i64>, Option<i64>),
+    Projection(Box<Expr>, Box<Expr>),
+    ValueProjection(Box<Expr>, Box<Expr>),
+    FilterProjection(Box<Expr>, Box<Expr>, Box<Expr>),
+    Flatten(Box<Expr>),
+    Literal(Value),
+    MultiList(Vec<Expr>),
+    MultiHash(Vec<(String, Expr)>),
+    Or(Box<Expr>, Box<Expr>),
+    And(Box<Expr>, Box<Expr>),
+    Not(Box<Expr>),
+    Compare(Comparator, Box<Expr>, Box<Expr>),
+    Pipe(Box<Expr>, Box<Expr>),
+    Function(String, Vec<Expr>),
+    ExpRef(Box<Expr>),
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Comparator {
+    Eq,
+    Ne,
+    Lt,
+    Le,
+    Gt,
+    Ge,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum Token {
+    Dot,
+    Star,
+    At,
+    LBracket,
+    RBracket,
+    Filter,  // [?
+    Flatten, // []
+    LBrace,
+    RBrace,
+    LParen,
+    RParen,
+    Comma,
+    Colon,
+    Pipe,
+    Or,
+    And,
+    Not,
+    ExpRef, // &
+    Compare(Comparator),
+    Number(i64),
+    Identifier(String),
+    QuotedIdentifier(String),
+    Literal(Value),
+    Eof,
+}
+
+impl Token {
+    // Binding power of a token when it appears after an expression.
+    fn binding_power(&self) -> u8 {
+        match self {
+            Token::Pipe => 1,
+            Token::Or => 2,
+            Token::And => 3,
+            Token::Compare(_) => 5,
+            Token::Flatten => 9,
+            Token::Star => 20,
+            Token::Filter => 21,
+            Token::Dot => 40,
+            Token::Not => 45,
+            Token::LBrace => 50,
+            Token::LBracket => 55,
+            Token::LParen => 60,
+            _ => 0,
+        }
+    }
+}
+
+// Projections stop at tokens binding less than this.
+const PROJECTION_STOP: u8 = 10;
+
+// Lexes a delimited string after the opening delimiter, keeping escapes except for the
+// escaped delimiter.
+fn lex_delimited(iter: &mut Peekable<Chars>, delimiter: char) -> Result<String, &'static str> {
+    let mut raw = String::new();
+    loop {
+        match iter.next() {
+            Some('\\') if iter.peek() == Some(&delimiter) => {
+                raw.push(delimiter);
+                iter.next();
+            }
+            Some('\\') => {
+                raw.push('\\');
+                raw.extend(iter.next());
+            }
+            Some(c) if c == delimiter => return Ok(raw),
+            Some(c) => raw.push(c),
+            None => return Err("unterminated string"),
+        }
+    }
+}
+
+fn lex(iter: &mut Peekable<Chars>) -> Result<Vec<Token>, &'static str> {
+    let mut tokens = Vec::new();
+    loop {
+        while iter.next_if(|c| c.is_whitespace()).is_some() {}
+        let Some(c) = iter.next() else {
+            tokens.push(Token::Eof);
+            return Ok(tokens);
+        };
+        let token = match c {
+            '.' => Token::Dot,
+            '*' => Token::Star,
+            '@' => Token::At,
+            '[' if iter.next_if_eq(&'?').is_some() => Token::Filter,
+            '[' if iter.next_if_eq(&']').is_some() => Token::Flatten,
+            '[' => Token::LBracket,
+            ']' => Token::RBracket,
+            '{' => Token::LBrace,
+            '}' => Token::RBrace,
+            '(' => Token::LParen,
+            ')' => Token::RParen,
+            ',' => Token::Comma,
+            ':' => Token::Colon,
+            '|' if iter.next_if_eq(&'|').is_some() => Token::Or,
+            '|' => Token::Pipe,
+            '&' if iter.next_if_eq(&'&').is_some() => Token::And,
+            '&' => Token::ExpRef,
+            '!' if iter.next_if_eq(&'=').is_some() => Token::Compare(Comparator::Ne),
+            '!' => Token::Not,
+            '=' if iter.next_if_eq(&'=').is_some() => Token::Compare(Comparator::Eq),
+            '<' if iter.next_if_eq(&'=').is_some() => Token::Compare(Comparator::Le),
+            '<' => Token::Compare(Comparator::Lt),
+            '>' if iter.next_if_eq(&'=').is_some() => Token::Compare(Comparator::Ge),
+            '>' => Token::Compare(Comparator::Gt),
+            '-' | '0'..='9' => {
+                let mut number = String::from(c);
+                while let Some(c) = iter.next_if(|c| c.is_ascii_digit()) {
+                    number.push(c);
+                }
+                Token::Number(number.parse().map_err(|_| "invalid number")?)
+            }
+            c if c.is_ascii_alphabetic() || c == '_' => {
+                let mut identifier = String::from(c);
+                while let Some(c) = iter.next_if(|c| c.is_ascii_alphanumeric() || *c == '_') {
+                    identifier.push(c);
+                }
+                Token::Identifier(identifier)
+            }
+            '"' => {
+                let raw = lex_delimited(iter, '"')?;
+                // Put the escaped quotes back for the JSON parser
+                let json = format!("\"{}\"", raw.replace('"', "\\\""));
+                Token::QuotedIdentifier(
+                    serde_json::from_str(&json).map_err(|_| "invalid quoted identifier")?,
+                )
+            }
+            // Only \' is an escape in a raw string, like in the compliance suite.
+            '\'' => Token::Literal(Value::String(lex_delimited(iter, '\'')?)),
+            '`' => {
+                let raw = lex_delimited(iter, '`')?;
+                // Invalid JSON is read as a string, the deprecated fallback of JEP 12.
+                let literal = serde_json::from_str(&raw)
+                    .or_else(|_| serde_json::from_str(&format!("\"{}\"", raw.trim_start())))
+                    .map_err(|_| "invalid literal")?;
+                Token::Literal(literal)
+            }
+            _ => return Err("unexpected character"),
+        };
+        tokens.push(token);
+    }
+}
+
+struct Parser {
+    tokens: Vec<Token>,
+    pos: usize,
+}
+
+impl Parser {
+    fn peek(&self) -> &Token {
+        self.peek_at(0)
+    }
+
+    fn peek_at(&self, offset: usize) -> &Token {
+        self.tokens.get(self.pos + offset).unwrap_or(&Token::Eof)
+    }
+
+    fn next(&mut self) -> Token {
+        let token = self.peek().clone();
+        self.pos += 1;
+        token
+    }
+
+    fn expect(&mut self, token: &Token, err: &'static str) -> Result<(), &'static str> {
+        if self.peek() == token {
+            self.pos += 1;
+            Ok(())
+        } else {
+            Err(err)
+        }
+    }
+
+    fn expression(&mut self, binding_power: u8) -> Result<Expr, &'static str> {
+        let token = self.next();
+        let mut left = self.nud(token)?;
+        while binding_power < self.peek().binding_power() {
+            let token = self.next();
+            left = self.led(token, left)?;
+        }
+        Ok(left)
+    }
+
+    fn nud(&mut self, token: Token) -> Result<Expr, &'static str> {
+        match token {
+            Token::Literal(value) => Ok(Expr::Literal(value)),
+            Token::Identifier(name) => Ok(Expr::Field(name)),
+            Token::QuotedIdentifier(name) => {
+                if self.peek() == &Token::LParen {
+                    return Err("quoted identifiers are not allowed as function names");
+                }
+                Ok(Expr::Field(name))
+            }
+            Token::Star => {
+                let rhs = self.projection_rhs(Token::Star.binding_power())?;
+                Ok(Expr::ValueProjection(
+                    Box::new(Expr::Current),
+                    Box::new(rhs),
+                ))
+            }
+            Token::Filter => self.filter(Expr::Current),
+            Token::LBrace => self.multi_hash(),
+            Token::LParen => {
+                let expr = self.expression(0)?;
+                self.expect(&Token::RParen, "expected )")?;
+                Ok(expr)
+            }
+            Token::Flatten => {
+                let lhs = Expr::Flatten(Box::new(Expr::Current));
+                let rhs = self.projection_rhs(Token::Flatten.binding_power())?;
+                Ok(Expr::Projection(Box::new(lhs), Box::new(rhs)))
+            }
+            Token::Not => Ok(Expr::Not(Box::new(
+                self.expression(Token::Not.binding_power())?,
+            ))),
+            Token::LBracket => match (self.peek(), self.peek_at(1)) {
+                (Token::Number(_) | Token::Colon, _) => self.index(Expr::Current),
+                (Token::Star, Token::RBracket) => {
+                    self.pos += 2;
+                    let rhs = self.projection_rhs(Token::Star.binding_power())?;
+                    Ok(Expr::Projection(Box::new(Expr::Current), Box::new(rhs)))
+                }
+                _ => self.multi_list(),
+            },
+            Token::At => Ok(Expr::Current),
+            Token::ExpRef => Ok(Expr::ExpRef(Box::new(self.expression(0)?))),
+            _ => Err("unexpected token"),
+        }
+    }
+
+    fn led(&mut self, token: Token, left: Expr) -> Result<Expr, &'static str> {
+        let binding_power = token.binding_power();
+        match token {
+            Token::Dot => {
+                if self.peek() == &Token::Star {
+                    self.pos += 1;
+                    let rhs = self.projection_rhs(binding_power)?;
+                    Ok(Expr::ValueProjection(Box::new(left), Box::new(rhs)))
+                } else {
+                    let rhs = self.dot_rhs(binding_power)?;
+                    Ok(Expr::Subexpr(Box::new(left), Box::new(rhs)))
+                }
+            }
+            Token::Pipe => Ok(Expr::Pipe(
+                Box::new(left),
+                Box::new(self.expression(binding_power)?),
+            )),
+            Token::Or => Ok(Expr::Or(
+                Box::new(left),
+                Box::new(self.expression(binding_power)?),
+            )),
+            Token::And => Ok(Expr::And(
+                Box::new(left),
+                Box::new(self.expression(binding_power)?),
+            )),
+            Token::Compare(comparator) => Ok(Expr::Compare(
+                comparator,
+                Box::new(left),
+                Box::new(self.expression(binding_power)?),
+            )),
+            Token::LParen => {
+                let Expr::Field(name) = left else {
+                    return Err("expected function name");
+                };
+                let mut args = Vec::new();
+                while self.peek() != &Token::RParen {
+                    args.push(self.expression(0)?);
+                    if self.peek() == &Token::Comma {
+                        self.pos += 1;
+                    } else if self.peek() != &Token::RParen {
+                        return Err("expected , or )");
+                    }
+                }
+                self.pos += 1;
+                Ok(Expr::Function(name, args))
+            }
+            Token::Filter => self.filter(left),
+            Token::Flatten => {
+                let lhs = Expr::Flatten(Box::new(left));
+                let rhs = self.projection_rhs(binding_power)?;
+                Ok(Expr::Projection(Box::new(lhs), Box::new(rhs)))
+            }
+            Token::LBracket => match self.peek() {
+                Token::Number(_) | Token::Colon => self.index(left),
+                _ => {
+                    self.expect(&Token::Star, "expected *")?;
+                    self.expect(&Token::RBracket, "expected ]")?;
+                    let rhs = self.projection_rhs(Token::Star.binding_power())?;
+                    Ok(Expr::Projection(Box::new(left), Box::new(rhs)))
+                }
+            },
+            _ => Err("unexpected token"),
+        }
+    }
+
+    // Parses an index or a slice after the [, slices project onto the following expression.
+    fn index(&mut self, left: Expr) -> Result<Expr, &'static str> {
+        if let (Token::Number(i), Token::RBracket) = (self.peek(), self.peek_at(1)) {
+            let i = *i;
+            self.pos += 2;
+            return Ok(Expr::Index(Box::new(left), i));
+        }
+        let mut parts = [None; 3];
+        let mut part = 0;
+        loop {
+            match self.next() {
+                Token::RBracket => break,
+                Token::Colon if part < 2 => part += 1,
+                Token::Number(n) if parts[part].is_none() => parts[part] = Some(n),
+                _ => return Err("invalid slice"),
+            }
+        }
+        let slice = Expr::Slice(Box::new(left), parts[0], parts[1], parts[2]);
+        let rhs = self.projection_rhs(Token::Star.binding_power())?;
+        Ok(Expr::Projection(Box::new(slice), Box::new(rhs)))
+    }
+
+    fn filter(&mut self, left: Expr) -> Result<Expr, &'static str> {
+        let condition = self.expression(0)?;
+        self.expect(&Token::RBracket, "expected ]")?;
+        let rhs = self.projection_rhs(Token::Filter.binding_power())?;
+        Ok(Expr::FilterProjection(
+            Box::new(left),
+            Box::new(rhs),
+            Box::new(condition),
+        ))
+    }
+
+    fn projection_rhs(&mut self, binding_power: u8) -> Result<Expr, &'static str> {
+        match self.peek() {
+            token if token.binding_power() < PROJECTION_STOP => Ok(Expr::Current),
+            Token::LBracket | Token::Filter => self.expression(binding_power),
+            Token::Dot => {
+                self.pos += 1;
+                self.dot_rhs(binding_power)
+            }
+            _ => Err("unexpected token after projection"),
+        }
+    }
+
+    fn dot_rhs(&mut self, binding_power: u8) -> Result<Expr, &'static str> {
+        match self.peek() {
+            Token::Identifier(_) | Token::QuotedIdentifier(_) | Token::Star => {
+                self.expression(binding_power)
+            }
+            Token::LBracket => {
+                self.pos += 1;
+                self.multi_list()
+            }
+            Token::LBrace => {
+                self.pos += 1;
+                self.multi_hash()
+            }
+            _ => Err("expected identifier, [ or { after ."),
+        }
+    }
+
+    fn multi_list(&mut self) -> Result<Expr, &'static str> {
+        let mut exprs = Vec::new();
+        loop {
+            exprs.push(self.expression(0)?);
+            match self.next() {
+                Token::Comma => {}
+                Token::RBracket => return Ok(Expr::MultiList(exprs)),
+                _ => return Err("expected , or ]"),
+            }
+        }
+    }
+
+    fn multi_hash(&mut self) -> Result<Expr, &'static str> {
+        let mut entries = Vec::new();
+        loop {
+            let key = match self.next() {
+                Token::Identifier(key) | Token::QuotedIdentifier(key) => key,
+                _ => return Err("expected identifier"),
+            };
+            self.expect(&Token::Colon, "expected :")?;
+            entries.push((key, self.expression(0)?));
+            match self.next() {
+                Token::Comma => {}
+                Token::RBrace => return Ok(Expr::MultiHash(entries)),
+                _ => return Err("expected , or }"),
+            }
+        }
+    }
+}
+
+fn truthy(value: &Value) -> bool {
+    match value {
+        Value::Null => false,
+        Value::Bool(b) => *b,
+        Value::String(s) => !s.is_empty(),
+        Value::Array(array) => !array.is_empty(),
+        Value::Object(map) => !map.is_empty(),
+        Value::Number(_) => true,
+    }
+}
+
+fn number(f: f64) -> Value {
+    if f.fract() == 0.0 && f.abs() < 9_007_199_254_740_992.0 {
+        Value::from(f as i64)
+    } else {
+        Number::from_f64(f).map_or(Value::Null, Value::Number)
+    }
+}
+
+// Equality of JMESPath, numbers compare by value.
+fn equal(a: &Value, b: &Value) -> bool {
+    match (a, b) {
+        (Value::Number(_), Value::Number(_)) => partial_cmp(a, b) == Some(Ordering::Equal),
+        (Value::Array(a), Value::Array(b)) => {
+            a.len() == b.len() && a.iter().zip(b).all(|(a, b)| equal(a, b))
+        }
+        (Value::Object(a), Value::Object(b)) => {
+            a.len() == b.len()
+                && a.iter()
+                    .all(|(key, a)| b.get(key).is_some_and(|b| equal(a, b)))
+        }
+        (a, b) => a == b,
+    }
+}
+
+fn slice(
+    array: &[Value],
+    start: Option<i64>,
+    stop: Option<i64>,
+    step: Option<i64>,
+) -> Result<Value, &'static str> {
+    let step = step.unwrap_or(1);
+    if step == 0 {
+        return Err("invalid value: slice step cannot be 0");
+    }
+    let len = array.len() as i64;
+    let bound = |i: Option<i64>, default: i64| match i {
+        None => default,
+        Some(i) if i < 0 => (len + i).max(if step < 0 { -1 } else { 0 }),
+        Some(i) => i.min(if step < 0 { len - 1 } else { len }),
+    };
+    let (start, stop) = if step > 0 {
+        (bound(start, 0), bound(stop, len))
+    } else {
+        (bound(start, len - 1), bound(stop, -1))
+    };
+    let mut out = Vec::new();
+    let mut i = start;
+    while (step > 0 && i < stop) || (step < 0 && i > stop) {
+        out.push(array[i as usize].clone());
+        // A step past the end of i64 is past the end of the array.
+        match i.checked_add(step) {
+            Some(next) => i = next,
+            None => break,
+        }
+    }
+    Ok(Value::Array(out))
+}
+
+// Applies rhs to every element and drops null results.
+fn project<'a>(values: impl Iterator<Item = &'a Value>, rhs: &Expr) -> Result<Value, &'static str> {
+    let mut out = Vec::new();
+    for v in values {
+        let v = eval(rhs, v)?;
+        if !v.is_null() {
+            out.push(v);
+        }
+    }
+    Ok(Value::Array(out))
+}
+
+fn eval(expr: &Expr, data: &Value) -> Result<Value, &'static str> {
+    match expr {
+        Expr::Current => Ok(data.clone()),
+        Expr::Field(key) => Ok(data.get(key).cloned().unwrap_or(Value::Null)),
+        Expr::Subexpr(lhs, rhs) => eval(rhs, &eval(lhs, data)?),
+        Expr::Index(lhs, i) => {
+            let Value::Array(array) = eval(lhs, data)? else {
+                return Ok(Value::Null);
+            };
+            let i = if *i < 0 { array.len() as i64 + i } else { *i };
+            Ok(usize::try_from(i)
+                .ok()
+                .and_then(|i| array.get(i).cloned())
+                .unwrap_or(Value::Null))
+        }
+        Expr::Slice(lhs, start, stop, step) => match eval(lhs, data)? {
+            Value::Array(array) => slice(&array, *start, *stop, *step),
+            _ => Ok(Value::Null),
+        },
+        Expr::Projection(lhs, rhs) => match eval(lhs, data)? {
+            Value::Array(array) => project(array.iter(), rhs),
+            _ => Ok(Value::Null),
+        },
+        Expr::ValueProjection(lhs, rhs) => match eval(lhs, data)? {
+            Value::Object(map) => project(map.values(), rhs),
+            _ => Ok(Value::Null),
+        },
+        Expr::FilterProjection(lhs, rhs, condition) => {
+            let Value::Array(array) = eval(lhs, data)? else {
+                return Ok(Value::Null);
+            };
+            let mut matching = Vec::new();
+            for v in &array {
+                if truthy(&eval(condition, v)?) {
+                    matching.push(v);
+                }
+            }
+            project(matching.into_iter(), rhs)
+        }
+        Expr::Flatten(lhs) => match eval(lhs, data)? {
+            Value::Array(array) => {
+                let mut out = Vec::new();
+                for v in array {
+                    match v {
+                        Value::Array(inner) => out.extend(inner),
+                        v => out.push(v),
+                    }
+                }
+                Ok(Value::Array(out))
+            }
+            _ => Ok(Value::Null),
+        },
+        Expr::Literal(value) => Ok(value.clone()),
+        Expr::MultiList(exprs) => {
+            if data.is_null() {
+                return Ok(Value::Null);
+            }
+            exprs
+                .iter()
+                .map(|expr| eval(expr, data))
+                .collect::<Result<_, _>>()
+                .map(Value::Array)
+        }
+        Expr::MultiHash(entries) => {
+            if data.is_null() {
+                return Ok(Value::Null);
+            }
+            let mut map = Map::new();
+            for (key, expr) in entries {
+                map.insert(key.clone(), eval(expr, data)?);
+            }
+            Ok(Value::Object(map))
+        }
+        Expr::Or(lhs, rhs) => {
+            let lhs = eval(lhs, data)?;
+            if truthy(&lhs) {
+                Ok(lhs)
+            } else {
+                eval(rhs, data)
+            }
+        }
+        Expr::And(lhs, rhs) => {
+            let lhs = eval(lhs, data)?;
+            if truthy(&lhs) {
+                eval(rhs, data)
+            } else {
+                Ok(lhs)
+            }
+        }
+        Expr::Not(expr) => Ok(Value::Bool(!truthy(&eval(expr, data)?))),
+        Expr::Compare(comparator, lhs, rhs) => {
+            let (lhs, rhs) = (eval(lhs, data)?, eval(rhs, data)?);
+            Ok(match comparator {
+                Comparator::Eq => Value::Bool(equal(&lhs, &rhs)),
+                Comparator::Ne => Value::Bool(!equal(&lhs, &rhs)),
+                // Ordering is only defined for numbers
+                _ if !(lhs.is_number() && rhs.is_number()) => Value::Null,
+                _ => match partial_cmp(&lhs, &rhs) {
+                    Some(ordering) => Value::Bool(match comparator {
+                        Comparator::Lt => ordering.is_lt(),
+                        Comparator::Le => ordering.is_le(),
+                        Comparator::Gt => ordering.is_gt(),
+                        _ => ordering.is_ge(),
+                    }),
+                    None => Value::Null,
+                },
+            })
+        }
+        Expr::Pipe(lhs, rhs) => eval(rhs, &eval(lhs, data)?),
+        Expr::Function(name, args) => call(name, args, data),
+        Expr::ExpRef(_) => Err("invalid type: expression references are only function arguments"),
+    }
+}
+
+// An evaluated function argument.
+enum Arg<'a> {
+    Value(Value),
+    ExpRef(&'a Expr),
+}
+
+const INVALID_TYPE: &str = "invalid type";
+const INVALID_ARITY: &str = "invalid arity";
+
+impl<'a> Arg<'a> {
+    fn value(&self) -> Result<&Value, &'static str> {
+        match self {
+            Arg::Value(value) => Ok(value),
+            Arg::ExpRef(_) => Err(INVALID_TYPE),
+        }
+    }
+
+    fn expref(&self) -> Result<&'a Expr, &'static str> {
+        match self {
+            Arg::ExpRef(expr) => Ok(expr),
+            Arg::Value(_) => Err(INVALID_TYPE),
+        }
+    }
+
+    fn number(&self) -> Result<f64, &'static str> {
+        self.value()?.as_f64().ok_or(INVALID_TYPE)
+    }
+
+    fn string(&self) -> Result<&str, &'static str> {
+        self.value()?.as_str().ok_or(INVALID_TYPE)
+    }
+
+    fn array(&self) -> Result<&Vec<Value>, &'static str> {
+        self.value()?.as_array().ok_or(INVALID_TYPE)
+    }
+
+    fn object(&self) -> Result<&Map<String, Value>, &'static str> {
+        self.value()?.as_object().ok_or(INVALID_TYPE)
+    }
+}
+
+fn numbers(array: &[Value]) -> Result<Vec<f64>, &'static str> {
+    array
+        .iter()
+        .map(|v| v.as_f64().ok_or(INVALID_TYPE))
+        .collect()
+}
+
+// Checks that all values are numbers or all values are strings.
+fn sortable(values: &[Value]) -> Result<(), &'static str> {
+    let all_numbers = values.iter().all(Value::is_number);
+    let all_strings = values.iter().all(Value::is_string);
+    if all_numbers || all_strings {
+        Ok(())
+    } else {
+        Err(INVALID_TYPE)
+    }
+}
+
+fn compare(a: &Value, b: &Value) -> Ordering {
+    partial_cmp(a, b).unwrap_or(Ordering::Equal)
+}
+
+// Evaluates the expression reference for every element, which must yield sortable keys.
+fn keys_by(array: &[Value], expr: &Expr) -> Result<Vec<Value>, &'static str> {
+    let keys = array
+        .iter()
+        .map(|v| eval(expr, v))
+        .collect::<Result<Vec<_>, _>>()?;
+    sortable(&keys)?;
+    Ok(keys)
+}
+
+fn type_name(value: &Value) -> &'static str {
+    match value {
+        Value::Null => "null",
+        Value::Bool(_) => "boolean",
+        Value::Number(_) => "number",
+        Value::String(_) => "string",
+        Value::Array(_) => "array",
+        Value::Object(_) => "object",
+    }
+}
+
+fn call(name: &str, args: &[Expr], data: &Value) -> Result<Value, &'static str> {
+    let args = args
+        .iter()
+        .map(|arg| match arg {
+            Expr::ExpRef(expr) => Ok(Arg::ExpRef(expr)),
+            arg => eval(arg, data).map(Arg::Value),
+        })
+        .collect::<Result<Vec<_>, _>>()?;
+    let arity = |n: usize| {
+        if args.len() == n {
+            Ok(())
+        } else {
+            Err(INVALID_ARITY)
+        }
+    };
+    match name {
+        "abs" | "ceil" | "floor" => {
+            arity(1)?;
+            let n = args[0].number()?;
+            Ok(number(match name {
+                "abs" => n.abs(),
+                "ceil" => n.ceil(),
+                _ => n.floor(),
+            }))
+        }
+        "avg" => {
+            arity(1)?;
+            let values = numbers(args[0].array()?)?;
+            if values.is_empty() {
+                return Ok(Value::Null);
+            }
+            Ok(number(values.iter().sum::<f64>() / values.len() as f64))
+        }
+        "sum" => {
+            arity(1)?;
+            Ok(number(numbers(args[0].array()?)?.iter().sum()))
+        }
+        "contains" => {
+            arity(2)?;
+            let needle = args[1].value()?;
+            match args[0].value()? {
+                Value::String(s) => Ok(Value::Bool(
+                    needle.as_str().is_some_and(|needle| s.contains(needle)),
+                )),
+                Value::Array(array) => Ok(Value::Bool(array.iter().any(|v| equal(v, needle)))),
+                _ => Err(INVALID_TYPE),
+            }
+        }
+        "starts_with" | "ends_with" => {
+            arity(2)?;
+            let (s, affix) = (args[0].string()?, args[1].string()?);
+            Ok(Value::Bool(if name == "starts_with" {
+                s.starts_with(affix)
+            } else {
+                s.ends_with(affix)
+            }))
+        }
+        "join" => {
+            arity(2)?;
+            let separator = args[0].string()?;
+            let parts = args[1]
+                .array()?
+                .iter()
+                .map(|v| v.as_str().ok_or(INVALID_TYPE))
+                .collect::<Result<Vec<_>, _>>()?;
+            Ok(Value::String(parts.join(separator)))
+        }
+        "keys" | "values" => {
+            arity(1)?;
+            let map = args[0].object()?;
+            Ok(Value::Array(if name == "keys" {
+                map.keys().cloned().map(Value::String).collect()
+            } else {
+                map.values().cloned().collect()
+            }))
+        }
+        "length" => {
+            arity(1)?;
+            match args[0].value()? {
+                Value::String(s) => Ok(Value::from(s.chars().count())),
+                Value::Array(array) => Ok(Value::from(array.len())),
+                Value::Object(map) => Ok(Value::from(map.len())),
+                _ => Err(INVALID_TYPE),
+            }
+        }
+        "map" => {
+            arity(2)?;
+            let expr = args[0].expref()?;
+            args[1]
+                .array()?
+                .iter()
+                .map(|v| eval(expr, v))
+                .collect::<Result<_, _>>()
+                .map(Value::Array)
+        }
+        "max" | "min" => {
+            arity(1)?;
+            let array = args[0].array()?;
+            sortable(array)?;
+            let found = if name == "max" {
+                array.iter().max_by(|a, b| compare(a, b))
+            } else {
+                array.iter().min_by(|a, b| compare(a, b))
+            };
+            Ok(found.cloned().unwrap_or(Value::Null))
+        }
+        "max_by" | "min_by" => {
+            arity(2)?;
+            let array = args[0].array()?;
+            let keys = keys_by(array, args[1].expref()?)?;
+            let pairs = keys.iter().zip(array);
+            let found = if name == "max_by" {
+                pairs.max_by(|a, b| compare(a.0, b.0))
+            } else {
+                pairs.min_by(|a, b| compare(a.0, b.0))
+            };
+            Ok(found.map_or(Value::Null, |(_, v)| v.clone()))
+        }
+        "merge" => {
+            let mut merged = Map::new();
+            for arg in &args {
+                merged.extend(arg.object()?.clone());
+            }
+            Ok(Value::Object(merged))
+        }
+        "not_null" => {
+            if args.is_empty() {
+                return Err(INVALID_ARITY);
+            }
+            for arg in &args {
+                let value = arg.value()?;
+                if !value.is_null() {
+                    return Ok(value.clone());
+                }
+            }
+            Ok(Value::Null)
+        }
+        "reverse" => {
+            arity(1)?;
+            match args[0].value()? {
+                Value::String(s) => Ok(Value::String(s.chars().rev().collect())),
+                Value::Array(array) => Ok(Value::Array(array.iter().rev().cloned().collect())),
+                _ => Err(INVALID_TYPE),
+            }
+        }
+        "sort" => {
+            arity(1)?;
+            let mut array = args[0].array()?.clone();
+            sortable(&array)?;
+            array.sort_by(compare);
+            Ok(Value::Array(array))
+        }
+        "sort_by" => {
+            arity(2)?;
+            let array = args[0].array()?;
+            let keys = keys_by(array, args[1].expref()?)?;
+            let mut pairs: Vec<(&Value, &Value)> = keys.iter().zip(array).collect();
+            pairs.sort_by(|a, b| compare(a.0, b.0));
+            Ok(Value::Array(
+                pairs.into_iter().map(|(_, v)| v.clone()).collect(),
+            ))
+        }
+        "to_array" => {
+            arity(1)?;
+            Ok(match args[0].value()? {
+                Value::Array(array) => Value::Array(array.clone()),
+                value => Value::Array(vec![value.clone()]),
+            })
+        }
+        "to_string" => {
+            arity(1)?;
+            Ok(match args[0].value()? {
+                Value::String(s) => Value::String(s.clone()),
+                value => Value::String(value.to_string()),
+            })
+        }
+        "to_number" => {
+            arity(1)?;
+            Ok(match args[0].value()? {
+                Value::Number(n) => Value::Number(n.clone()),
+                Value::String(s) => s.parse::<f64>().map_or(Value::Null, number),
+                _ => Value::Null,
+            })
+        }
+        "type" => {
+            arity(1)?;
+            Ok(Value::String(type_name(args[0].value()?).to_string()))
+        }
+        _ => Err("unknown function"),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::*;
+
+    // Hand-picked cases following the JMESPath specification, tests/jmespath.rs runs files of the
+    // compliance suite.
+    fn check(data: &Value, tests: Vec<(&str, Result<Value, &'static str>)>) {
+        for (expression, expected) in tests {
+            let result = JmesPath::from_str(expression).and_then(|e| e.search(data));
+            assert_eq!(
+                result, expected,
+                "expected {} to be {:?}",
+                expression, expected
+            );
+        }
+    }
+
+    #[test]
+    fn basic() {
+        let data = json!({"foo": {"bar": {"baz": "correct"}}, "a-b": 1, "list": [0, 1, 2]});
+        check(
+            &data,
+            vec![
+                ("foo", Ok(json!({"bar": {"baz": "correct"}}))),
+                ("foo.bar.baz", Ok(json!("correct"))),
+                ("foo.bar.baz.bad", Ok(json!(null))),
+                ("foo.bad", Ok(json!(null))),
+                ("\"a-b\"", Ok(json!(1))),
+                ("\"foo\".\"bar\"", Ok(json!({"baz": "correct"}))),
+                ("list[0]", Ok(json!(0))),
+                ("list[-1]", Ok(json!(2))),
+                ("list[3]", Ok(json!(null))),
+                ("foo[0]", Ok(json!(null))),
+                ("@", Ok(data.clone())),
+                ("foo.\"bar\".baz", Ok(json!("correct"))),
+                ("foo.", Err("expected identifier, [ or { after .")),
+                ("foo[", Err("expected *")),
+                ("\"foo", Err("unterminated string")),
+            ],
+        );
+    }
+
+    #[test]
+    fn slices() {
+        let data = json!({"foo": [0, 1, 2, 3, 4, 5, 6, 7, 8, 9], "bar": {"baz": 1}});
+        check(
+            &data,
+            vec![
+                ("foo[0:5]", Ok(json!([0, 1, 2, 3, 4]))),
+                ("foo[5:]", Ok(json!([5, 6, 7, 8, 9]))),
+                ("foo[::2]", Ok(json!([0, 2, 4, 6, 8]))),
+                ("foo[::-1]", Ok(json!([9, 8, 7, 6, 5, 4, 3, 2, 1, 0]))),
+                ("foo[-3:]", Ok(json!([7, 8, 9]))),
+                ("foo[8:2:-2]", Ok(json!([8, 6, 4]))),
+                ("foo[100:]", Ok(json!([]))),
+                ("foo[:-20]", Ok(json!([]))),
+                ("foo[-20:2]", Ok(json!([0, 1]))),
+                ("bar[:1]", Ok(json!(null))),
+                ("foo[1:5:9223372036854775807]", Ok(json!([1]))),
+                ("foo[::-9223372036854775808]", Ok(json!([9]))),
+                ("foo[::0]", Err("invalid value: slice step cannot be 0")),
+                ("foo[1:2:3:4]", Err("invalid slice")),
+            ],
+        );
+    }
+
+    #[test]
+    fn projections() {
+        let data = json!({
+            "people": [
+                {"first": "James", "last": "d"},
+                {"first": "Jacob", "last": "e"},
+                {"first": "Jayden", "last": "f"},
+                {"missing": "different"}
+            ],
+            "ops": {
+                "functionA": {"numArgs": 2},
+                "functionB": {"numArgs": 3},
+                "functionC": {"variadic": true}
+            },
+            "reservations": [
+                {"instances": [{"state": "running"}, {"state": "stopped"}]},
+                {"instances": [{"state": "terminated"}, {"state": "running"}]}
+            ],
+            "nested": [[0, 1], 2, [3], 4, [5, [6, 7]]]
+        });
+        check(
+            &data,
+            vec![
+                ("people[*].first", Ok(json!(["James", "Jacob", "Jayden"]))),
+                ("people[:2].first", Ok(json!(["James", "Jacob"]))),
+                ("people[*].first | [0]", Ok(json!("James"))),
+                ("people[*].first[0]", Ok(json!([]))),
+                ("ops.*.numArgs", Ok(json!([2, 3]))),
+                (
+                    "reservations[*].instances[*].state",
+                    Ok(json!([["running", "stopped"], ["terminated", "running"]])),
+                ),
+                (
+                    "reservations[].instances[].state",
+                    Ok(json!(["running", "stopped", "terminated", "running"])),
+                ),
+                ("nested[]", Ok(json!([0, 1, 2, 3, 4, 5, [6, 7]]))),
+                ("nested[][]", Ok(json!([0, 1, 2, 3, 4, 5, 6, 7]))),
+                (
+                    "people[*].[first, last]",
+                    Ok(json!([
+                        ["James", "d"],
+                        ["Jacob", "e"],
+                        ["Jayden", "f"],
+                        [null, null]
+                    ])),
+                ),
+                (
+                    "people[:1].{name: first, \"surname\": last}",
+                    Ok(json!([{"name": "James", "surname": "d"}])),
+                ),
+                ("ops.functionA.[numArgs, variadic]", Ok(json!([2, null]))),
+                ("missing.[a, b]", Ok(json!(null))),
+                (
+                    "*",
+                    Ok(json!([
+                        data["nested"],
+                        data["ops"],
+                        data["people"],
+                        data["reservations"]
+                    ])),
+                ),
+                ("people.*", Ok(json!(null))),
+            ],
+        );
+    }
+
+    #[test]
+    fn filters() {
+        let data = json!({
+            "machines": [
+                {"name": "a", "state": "running", "cpu": 4},
+                {"name": "b", "state": "stopped", "cpu": 2},
+                {"name": "c", "state": "running", "cpu": 8}
+            ],
+            "locations": [
+                {"name": "Seattle", "state": "WA"},
+                {"name": "New York", "state": "NY"},
+                {"name": "Bellevue", "state": "WA"},
+                {"name": "Olympia", "state": "WA"}
+            ]
+        });
+        check(
+            &data,
+            vec![
+                ("machines[?state=='running'].name", Ok(json!(["a", "c"]))),
+                ("machines[?cpu > `3`].name", Ok(json!(["a", "c"]))),
+                (
+                    "machines[?cpu >= `4` && state == 'running'].name",
+                    Ok(json!(["a", "c"])),
+                ),
+                (
+                    "machines[?cpu < `3` || name == 'c'].name",
+                    Ok(json!(["b", "c"])),
+                ),
+                ("machines[?!(state == 'running')].name", Ok(json!(["b"]))),
+                ("machines[?name > 'a'].name", Ok(json!([]))),
+                ("machines[?cpu == `4.0`].name", Ok(json!(["a"]))),
+                (
+                    "locations[?state == 'WA'].name | sort(@) | {WashingtonCities: join(', ', @)}",
+                    Ok(json!({"WashingtonCities": "Bellevue, Olympia, Seattle"})),
+                ),
+                ("machines[?state == 'running'] | [0].name", Ok(json!("a"))),
+                ("machines[0].name || 'none'", Ok(json!("a"))),
+                ("machines[0].missing || 'none'", Ok(json!("none"))),
+                ("machines[0].name && machines[1].name", Ok(json!("b"))),
+                ("!machines", Ok(json!(false))),
+                ("`[]` || `{}` || '' || `0`", Ok(json!(0))),
+            ],
+        );
+    }
+
+    #[test]
+    fn functions() {
+        let data = json!({
+            "numbers": [-1, 3, 4, 5],
+            "decimals": [1.01, 1.2, -1.5],
+            "strings": ["a", "b", "c"],
+            "empty": [],
+            "people": [
+                {"name": "b", "age": 30},
+                {"name": "a", "age": 50},
+                {"name": "c", "age": 40}
+            ],
+            "foo": {"a": 1, "b": 2},
+            "bar": {"b": 3, "c": 4},
+            "str": "Str"
+        });
+        check(
+            &data,
+            vec![
+                ("abs(numbers[0])", Ok(json!(1))),
+                ("avg(numbers)", Ok(json!(2.75))),
+                ("avg(empty)", Ok(json!(null))),
+                ("ceil(decimals[0])", Ok(json!(2))),
+                ("floor(decimals[2])", Ok(json!(-2))),
+                ("contains(strings, 'a')", Ok(json!(true))),
+                ("contains(str, 'tr')", Ok(json!(true))),
+                ("contains(`false`, 'a')", Err(INVALID_TYPE)),
+                ("starts_with(str, 'St')", Ok(json!(true))),
+                ("ends_with(str, 'r')", Ok(json!(true))),
+                ("join(',', strings)", Ok(json!("a,b,c"))),
+                ("join(',', numbers)", Err(INVALID_TYPE)),
+                ("keys(foo)", Ok(json!(["a", "b"]))),
+                ("values(foo)", Ok(json!([1, 2]))),
+                ("length(str)", Ok(json!(3))),
+                ("length(people)", Ok(json!(3))),
+                ("length(`1`)", Err(INVALID_TYPE)),
+                ("map(&name, people)", Ok(json!(["b", "a", "c"]))),
+                (
+                    "map(&[], `[[1, 2], 3, [4]]`)",
+                    Ok(json!([[1, 2], null, [4]])),
+                ),
+                ("max(numbers)", Ok(json!(5))),
+                ("min(strings)", Ok(json!("a"))),
+                ("max(`[1, \"a\"]`)", Err(INVALID_TYPE)),
+                ("max_by(people, &age).name", Ok(json!("a"))),
+                ("min_by(people, &age).name", Ok(json!("b"))),
+                ("max_by(people, &foo)", Err(INVALID_TYPE)),
+                ("merge(foo, bar)", Ok(json!({"a": 1, "b": 3, "c": 4}))),
+                ("not_null(missing, str)", Ok(json!("Str"))),
+                ("reverse(strings)", Ok(json!(["c", "b", "a"]))),
+                ("reverse(str)", Ok(json!("rtS"))),
+                ("sort(numbers)", Ok(json!([-1, 3, 4, 5]))),
+                ("sort_by(people, &age)[*].name", Ok(json!(["b", "c", "a"]))),
+                ("sort_by(people, &name)[*].age", Ok(json!([50, 30, 40]))),
+                ("sum(numbers)", Ok(json!(11))),
+                ("sum(empty)", Ok(json!(0))),
+                ("to_array(str)", Ok(json!(["Str"]))),
+                ("to_string(foo)", Ok(json!("{\"a\":1,\"b\":2}"))),
+                ("to_number('1.5')", Ok(json!(1.5))),
+                ("to_number('abc')", Ok(json!(null))),
+                ("type(people)", Ok(json!("array"))),
+                ("type(`null`)", Ok(json!("null"))),
+                ("people[?age > `35`].name | sort(@)", Ok(json!(["a", "c"]))),
+                ("abs(`1`, `2`)", Err(INVALID_ARITY)),
+                ("unknown(`1`)", Err("unknown function")),
+                (
+                    "\"abs\"(`1`)",
+                    Err("quoted identifiers are not allowed as function names"),
+                ),
+                ("sort_by(people, name)", Err(INVALID_TYPE)),
+            ],
+        );
+    }
+
+    #[test]
+    fn literals() {
+        let data = json!({"foo": "bar"});
+        check(
+            &data,
+            vec![
+                ("`\"foo\"`", Ok(json!("foo"))),
+                ("`{\"a\": [1, 2]}`", Ok(json!({"a": [1, 2]}))),
+                ("'raw \\' string'", Ok(json!("raw ' string"))),
+                ("'\\\\'", Ok(json!("\\\\"))),
+                ("'\\n'", Ok(json!("\\n"))),
+                ("`foo`", Ok(json!("foo"))),
+                ("`{foo}`", Ok(json!("{foo}"))),
+                ("`\"foo`", Err("invalid literal")),
+                ("\"\\u00e9\"", Ok(json!(null))),
+                ("{a: foo, b: `1`}", Ok(json!({"a": "bar", "b": 1}))),
+                ("[foo, 'x']", Ok(json!(["bar", "x"]))),
+            ],
+        );
+    }
+}
@@ -0,0 +1,1852 @@
+use std::{cmp::Ordering, iter::Peekable, ops::Deref, rc::Rc, str::Chars, str::FromStr};
+
+use serde_json::{Map, Number, Value};
+
+use crate::{
+    json_path::{JsonPath, JsonPathElement, JsonPathIndex},
+    number::cmp_numbers,
+};
+
+/// A compiled filter of the [jq](https://jqlang.github.io/jq/manual/) language.
+///
+/// Supports a practical subset of jq: paths, `..`, iteration, slices, `?`, pipes, `,`, `//`,
+/// arithmetic, comparisons, `and`/`or`, `if`, `reduce`, `as` bindings, object and array
+/// construction, string interpolation and a selection of builtin functions.
+/// Chains of field and literal index accesses are evaluated with a `JsonPath`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct JqFilter(Filter);
+
+impl JqFilter {
+    /// Runs the filter on `input` and returns all outputs, or the first error.
+    pub fn run(&self, input: &Value) -> Result<Vec<Value>, &'static str> {
+        self.stream(input).collect()
+    }
+
+    /// Runs the filter on `input` and returns its outputs as they are produced.
+    ///
+    /// Outputs are only evaluated when they are pulled, so `first(range(1e9))` produces a single
+    /// number. The stream ends with the first error, after the outputs before it.
+    pub fn stream<'a>(
+        &'a self,
+        input: &'a Value,
+    ) -> impl Iterator<Item = Result<Value, &'static str>> + 'a {
+        let mut failed = false;
+        eval(&self.0, &Input::Borrowed(input), &Rc::default()).map_while(move |output| {
+            if failed {
+                return None;
+            }
+            failed = output.is_err();
+            Some(output)
+        })
+    }
+}
+
+impl FromStr for JqFilter {
+    type Err = &'static str;
+
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        let tokens = lex(&mut value.chars().peekable(), false)?;
+        let mut parser = Parser { tokens, pos: 0 };
+        let filter = parser.parse_pipe()?;
+        if parser.pos < parser.tokens.len() {
+            return Err("unexpected token");
+        }
+        Ok(JqFilter(filter))
+    }
+}
+
+impl TryFrom<&str> for JqFilter {
+    type Error = &'static str;
+
+    #[inline]
+    fn try_from(value: &str) -> Result<Self, Self::Error> {
+        JqFilter::from_str(value)
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum Filter {
+    Identity,
+    Recurse,
+    Path(JsonPath),
+    Index(Box<Filter>, Box<Filter>),
+    Slice(Box<Filter>, Option<Box<Filter>>, Option<Box<Filter>>),
+    Iterate(Box<Filter>),
+    Try(Box<Filter>),
+    Literal(Value),
+    Str(Vec<StrPart>),
+    Array(Option<Box<Filter>>),
+    Object(Vec<(Filter, Filter)>),
+    Neg(Box<Filter>),
+    Pipe(Box<Filter>, Box<Filter>),
+    Comma(Box<Filter>, Box<Filter>),
+    Binary(BinaryOp, Box<Filter>, Box<Filter>),
+    And(Box<Filter>, Box<Filter>),
+    Or(Box<Filter>, Box<Filter>),
+    Alternative(Box<Filter>, Box<Filter>),
+    If(Box<Filter>, Box<Filter>, Box<Filter>),
+    Reduce(Box<Filter>, String, Box<Filter>, Box<Filter>),
+    Bind(Box<Filter>, String, Box<Filter>),
+    Var(String),
+    Call(String, Vec<Filter>),
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum StrPart {
+    Literal(String),
+    Interpolation(Filter),
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum BinaryOp {
+    Add,
+    Sub,
+    Mul,
+    Div,
+    Rem,
+    Eq,
+    Ne,
+    Lt,
+    Le,
+    Gt,
+    Ge,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum Token {
+    Dot,
+    DotDot,
+    Field(String), // .name
+    Ident(String),
+    Var(String), // $name
+    Num(f64),
+    Str(Vec<StrToken>),
+    LParen,
+    RParen,
+    LBracket,
+    RBracket,
+    LBrace,
+    RBrace,
+    Pipe,
+    Comma,
+    Colon,
+    Semicolon,
+    Question,
+    Op(BinaryOp),
+    Alternative, // //
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum StrToken {
+    Literal(String),
+    Interpolation(Vec<Token>),
+}
+
+fn is_ident_start(c: &char) -> bool {
+    c.is_ascii_alphabetic() || *c == '_'
+}
+
+fn is_ident(c: &char) -> bool {
+    c.is_ascii_alphanumeric() || *c == '_'
+}
+
+fn lex_ident(iter: &mut Peekable<Chars>) -> String {
+    let mut ident = String::new();
+    while let Some(c) = iter.next_if(is_ident) {
+        ident.push(c);
+    }
+    ident
+}
+
+// Lexes until the end of input, or until the closing ) of a string interpolation if nested.
+fn lex(iter: &mut Peekable<Chars>, nested: bool) -> Result<Vec<Token>, &'static str> {
+    let mut tokens = Vec::new();
+    let mut depth = 0usize;
+    loop {
+        while iter.next_if(|c| c.is_whitespace()).is_some() {}
+        let Some(c) = iter.next() else {
+            return if nested {
+                Err("expected )")
+            } else {
+                Ok(tokens)
+            };
+        };
+        let token = match c {
+            '#' => {
+                while iter.next_if(|c| *c != '\n').is_some() {}
+                continue;
+            }
+            '.' if iter.next_if_eq(&'.').is_some() => Token::DotDot,
+            '.' if iter.peek().is_some_and(is_ident_start) => Token::Field(lex_ident(iter)),
+            '.' => Token::Dot,
+            '$' if iter.peek().is_some_and(is_ident_start) => Token::Var(lex_ident(iter)),
+            '0'..='9' => {
+                let mut number = String::from(c);
+                while let Some(c) = iter.next_if(|c| c.is_ascii_digit() || *c == '.') {
+                    number.push(c);
+                }
+                if let Some(e) = iter.next_if(|c| *c == 'e' || *c == 'E') {
+                    number.push(e);
+                    number.extend(iter.next_if(|c| *c == '+' || *c == '-'));
+                    while let Some(c) = iter.next_if(|c| c.is_ascii_digit()) {
+                        number.push(c);
+                    }
+                }
+                Token::Num(number.parse().map_err(|_| "invalid number")?)
+            }
+            '"' => Token::Str(lex_string(iter)?),
+            '(' => {
+                depth += 1;
+                Token::LParen
+            }
+            ')' if nested && depth == 0 => return Ok(tokens),
+            ')' => {
+                depth = depth.saturating_sub(1);
+                Token::RParen
+            }
+            '[' => Token::LBracket,
+            ']' => Token::RBracket,
+            '{' => Token::LBrace,
+            '}' => Token::RBrace,
+            '|' => Token::Pipe,
+            ',' => Token::Comma,
+            ':' => Token::Colon,
+            ';' => Token::Semicolon,
+            '?' => Token::Question,
+            '+' => Token::Op(BinaryOp::Add),
+            '-' => Token::Op(BinaryOp::Sub),
+            '*' => Token::Op(BinaryOp::Mul),
+            '%' => Token::Op(BinaryOp::Rem),
+            '/' if iter.next_if_eq(&'/').is_some() => Token::Alternative,
+            '/' => Token::Op(BinaryOp::Div),
+            '=' if iter.next_if_eq(&'=').is_some() => Token::Op(BinaryOp::Eq),
+            '!' if iter.next_if_eq(&'=').is_some() => Token::Op(BinaryOp::Ne),
+            '<' if iter.next_if_eq(&'=').is_some() => Token::Op(BinaryOp::Le),
+            '<' => Token::Op(BinaryOp::Lt),
+            '>' if iter.next_if_eq(&'=').is_some() => Token::Op(BinaryOp::Ge),
+            '>' => Token::Op(BinaryOp::Gt),
+            c if is_ident_start(&c) => {
+                let mut ident = String::from(c);
+                ident.push_str(&lex_ident(iter));
+                Token::Ident(ident)
+            }
+            _ => return Err("unexpected character"),
+        };
+        tokens.push(token);
+    }
+}
+
+fn lex_hex(iter: &mut Peekable<Chars>) -> Result<u32, &'static str> {
+    let hex: String = iter.take(4).collect();
+    u32::from_str_radix(&hex, 16).map_err(|_| "invalid unicode escape")
+}
+
+// Lexes a string after the opening quote.
+fn lex_string(iter: &mut Peekable<Chars>) -> Result<Vec<StrToken>, &'static str> {
+    let mut parts = Vec::new();
+    let mut literal = String::new();
+    loop {
+        match iter.next() {
+            Some('"') => break,
+            Some('\\') => match iter.next() {
+                Some('n') => literal.push('\n'),
+                Some('t') => literal.push('\t'),
+                Some('r') => literal.push('\r'),
+                Some('b') => literal.push('\u{8}'),
+                Some('f') => literal.push('\u{c}'),
+                Some('u') => {
+                    let mut code = lex_hex(iter)?;
+                    if (0xD800..0xDC00).contains(&code) {
+                        if iter.next() != Some('\\') || iter.next() != Some('u') {
+                            return Err("invalid unicode escape");
+                        }
+                        let low = lex_hex(iter)?;
+                        if !(0xDC00..0xE000).contains(&low) {
+                            return Err("invalid unicode escape");
+                        }
+                        code = 0x10000 + ((code - 0xD800) << 10) + (low - 0xDC00);
+                    }
+                    literal.push(char::from_u32(code).ok_or("invalid unicode escape")?);
+                }
+                Some('(') => {
+                    if !literal.is_empty() {
+                        parts.push(StrToken::Literal(std::mem::take(&mut literal)));
+                    }
+                    parts.push(StrToken::Interpolation(lex(iter, true)?));
+                }
+                Some(c @ ('"' | '\\' | '/')) => literal.push(c),
+                _ => return Err("invalid escape"),
+            },
+            Some(c) => literal.push(c),
+            None => return Err("unterminated string"),
+        }
+    }
+    if !literal.is_empty() || parts.is_empty() {
+        parts.push(StrToken::Literal(literal));
+    }
+    Ok(parts)
+}
+
+struct Parser {
+    tokens: Vec<Token>,
+    pos: usize,
+}
+
+// The longest string, in bytes, that multiplying a string by a number creates.
+const MAX_REPEAT_LEN: usize = 1 << 30;
+
+const KEYWORDS: [&str; 10] = [
+    "if", "then", "elif", "else", "end", "as", "reduce", "and", "or", "def",
+];
+
+fn number(f: f64) -> Value {
+    if f.fract() == 0.0 && f.abs() < 9_007_199_254_740_992.0 {
+        Value::from(f as i64)
+    } else {
+        Number::from_f64(f).map_or(Value::Null, Value::Number)
+    }
+}
+
+// Appends a field or index access to a filter, extending a JsonPath where possible.
+fn extend_path(filter: Filter, element: JsonPathElement) -> Filter {
+    match filter {
+        Filter::Identity => Filter::Path(JsonPath::from(vec![element])),
+        Filter::Path(mut path) => {
+            path.push(element);
+            Filter::Path(path)
+        }
+        Filter::Pipe(lhs, rhs) if matches!(*rhs, Filter::Path(_)) => {
+            Filter::Pipe(lhs, Box::new(extend_path(*rhs, element)))
+        }
+        filter => Filter::Pipe(
+            Box::new(filter),
+            Box::new(Filter::Path(JsonPath::from(vec![element]))),
+        ),
+    }
+}
+
+// Converts a literal index expression into a path element.
+fn literal_element(filter: &Filter) -> Option<JsonPathElement> {
+    match filter {
+        Filter::Literal(Value::String(key)) => Some(JsonPathElement::Field(key.clone())),
+        Filter::Literal(Value::Number(n)) => n
+            .as_u64()
+            .map(|i| JsonPathIndex::NthLefth(i as usize))
+            .or_else(|| {
+                n.as_i64()
+                    .map(|i| JsonPathIndex::NthRight(i.unsigned_abs() as usize))
+            })
+            .map(JsonPathElement::Index),
+        Filter::Neg(inner) => match inner.as_ref() {
+            Filter::Literal(Value::Number(n)) => n
+                .as_u64()
+                .filter(|i| *i > 0)
+                .map(|i| JsonPathElement::Index(JsonPathIndex::NthRight(i as usize))),
+            _ => None,
+        },
+        _ => None,
+    }
+}
+
+impl Parser {
+    fn peek(&self) -> Option<&Token> {
+        self.tokens.get(self.pos)
+    }
+
+    fn next(&mut self) -> Option<Token> {
+        let token = self.tokens.get(self.pos).cloned();
+        self.pos += 1;
+        token
+    }
+
+    fn next_if(&mut self, token: &Token) -> bool {
+        if self.peek() == Some(token) {
+            self.pos += 1;
+            true
+        } else {
+            false
+        }
+    }
+
+    fn next_if_keyword(&mut self, keyword: &str) -> bool {
+        if matches!(self.peek(), Some(Token::Ident(ident)) if ident == keyword) {
+            self.pos += 1;
+            true
+        } else {
+            false
+        }
+    }
+
+    fn expect(&mut self, token: &Token, err: &'static str) -> Result<(), &'static str> {
+        if self.next_if(token) {
+            Ok(())
+        } else {
+            Err(err)
+        }
+    }
+
+    fn expect_keyword(&mut self, keyword: &str, err: &'static str) -> Result<(), &'static str> {
+        if self.next_if_keyword(keyword) {
+            Ok(())
+        } else {
+            Err(err)
+        }
+    }
+
+    fn expect_var(&mut self) -> Result<String, &'static str> {
+        match self.next() {
+            Some(Token::Var(name)) => Ok(name),
+            _ => Err("expected $name"),
+        }
+    }
+
+    fn parse_pipe(&mut self) -> Result<Filter, &'static str> {
+        let lhs = self.parse_comma()?;
+        if self.next_if(&Token::Pipe) {
+            Ok(Filter::Pipe(Box::new(lhs), Box::new(self.parse_pipe()?)))
+        } else {
+            Ok(lhs)
+        }
+    }
+
+    fn parse_comma(&mut self) -> Result<Filter, &'static str> {
+        let mut lhs = self.parse_alternative()?;
+        while self.next_if(&Token::Comma) {
+            lhs = Filter::Comma(Box::new(lhs), Box::new(self.parse_alternative()?));
+        }
+        Ok(lhs)
+    }
+
+    fn parse_alternative(&mut self) -> Result<Filter, &'static str> {
+        let lhs = self.parse_or()?;
+        if self.next_if(&Token::Alternative) {
+            Ok(Filter::Alternative(
+                Box::new(lhs),
+                Box::new(self.parse_alternative()?),
+            ))
+        } else {
+            Ok(lhs)
+        }
+    }
+
+    fn parse_or(&mut self) -> Result<Filter, &'static str> {
+        let mut lhs = self.parse_and()?;
+        while self.next_if_keyword("or") {
+            lhs = Filter::Or(Box::new(lhs), Box::new(self.parse_and()?));
+        }
+        Ok(lhs)
+    }
+
+    fn parse_and(&mut self) -> Result<Filter, &'static str> {
+        let mut lhs = self.parse_comparison()?;
+        while self.next_if_keyword("and") {
+            lhs = Filter::And(Box::new(lhs), Box::new(self.parse_comparison()?));
+        }
+        Ok(lhs)
+    }
+
+    fn parse_comparison(&mut self) -> Result<Filter, &'static str> {
+        let lhs = self.parse_additive()?;
+        match self.peek() {
+            Some(Token::Op(
+                op @ (BinaryOp::Eq
+                | BinaryOp::Ne
+                | BinaryOp::Lt
+                | BinaryOp::Le
+                | BinaryOp::Gt
+                | BinaryOp::Ge),
+            )) => {
+                let op = *op;
+                self.pos += 1;
+                let rhs = self.parse_additive()?;
+                Ok(Filter::Binary(op, Box::new(lhs), Box::new(rhs)))
+            }
+            _ => Ok(lhs),
+        }
+    }
+
+    fn parse_additive(&mut self) -> Result<Filter, &'static str> {
+        let mut lhs = self.parse_multiplicative()?;
+        while let Some(Token::Op(op @ (BinaryOp::Add | BinaryOp::Sub))) = self.peek() {
+            let op = *op;
+            self.pos += 1;
+            let rhs = self.parse_multiplicative()?;
+            lhs = Filter::Binary(op, Box::new(lhs), Box::new(rhs));
+        }
+        Ok(lhs)
+    }
+
+    fn parse_multiplicative(&mut self) -> Result<Filter, &'static str> {
+        let mut lhs = self.parse_unary()?;
+        while let Some(Token::Op(op @ (BinaryOp::Mul | BinaryOp::Div | BinaryOp::Rem))) =
+            self.peek()
+        {
+            let op = *op;
+            self.pos += 1;
+            let rhs = self.parse_unary()?;
+            lhs = Filter::Binary(op, Box::new(lhs), Box::new(rhs));
+        }
+        Ok(lhs)
+    }
+
+    fn parse_unary(&mut self) -> Result<Filter, &'static str> {
+        if self.next_if(&Token::Op(BinaryOp::Sub)) {
+            Ok(Filter::Neg(Box::new(self.parse_bind()?)))
+        } else {
+            self.parse_bind()
+        }
+    }
+
+    // Parses Term as $name | body, where the body extends to the end of the pipe.
+    fn parse_bind(&mut self) -> Result<Filter, &'static str> {
+        let term = self.parse_postfix()?;
+        if !self.next_if_keyword("as") {
+            return Ok(term);
+        }
+        let name = self.expect_var()?;
+        self.expect(&Token::Pipe, "expected |")?;
+        let body = self.parse_pipe()?;
+        Ok(Filter::Bind(Box::new(term), name, Box::new(body)))
+    }
+
+    fn parse_postfix(&mut self) -> Result<Filter, &'static str> {
+        let mut term = self.parse_term()?;
+        loop {
+            match self.peek() {
+                Some(Token::Field(name)) => {
+                    let element = JsonPathElement::Field(name.clone());
+                    self.pos += 1;
+                    term = extend_path(term, element);
+                }
+                Some(Token::Dot)
+                    if matches!(self.tokens.get(self.pos + 1), Some(Token::Str(_))) =>
+                {
+                    self.pos += 1;
+                    let key = self.parse_term()?;
+                    term = match literal_element(&key) {
+                        Some(element) => extend_path(term, element),
+                        None => Filter::Index(Box::new(term), Box::new(key)),
+                    };
+                }
+                Some(Token::Dot)
+                    if matches!(self.tokens.get(self.pos + 1), Some(Token::LBracket)) =>
+                {
+                    self.pos += 1;
+                }
+                Some(Token::LBracket) => {
+                    self.pos += 1;
+                    term = self.parse_brackets(term)?;
+                }
+                Some(Token::Question) => {
+                    self.pos += 1;
+                    term = Filter::Try(Box::new(term));
+                }
+                _ => return Ok(term),
+            }
+        }
+    }
+
+    // Parses the inside of term[...] after the [.
+    fn parse_brackets(&mut self, term: Filter) -> Result<Filter, &'static str> {
+        if self.next_if(&Token::RBracket) {
+            return Ok(Filter::Iterate(Box::new(term)));
+        }
+        let from = if self.peek() == Some(&Token::Colon) {
+            None
+        } else {
+            Some(Box::new(self.parse_pipe()?))
+        };
+        if self.next_if(&Token::Colon) {
+            let to = if self.peek() == Some(&Token::RBracket) {
+                None
+            } else {
+                Some(Box::new(self.parse_pipe()?))
+            };
+            self.expect(&Token::RBracket, "expected ]")?;
+            if from.is_none() && to.is_none() {
+                return Err("expected slice index");
+            }
+            return Ok(Filter::Slice(Box::new(term), from, to));
+        }
+        self.expect(&Token::RBracket, "expected ]")?;
+        let index = from.ok_or("expected index")?;
+        Ok(match literal_element(&index) {
+            Some(element) => extend_path(term, element),
+            None => Filter::Index(Box::new(term), index),
+        })
+    }
+
+    fn parse_term(&mut self) -> Result<Filter, &'static str> {
+        match self.next() {
+            Some(Token::Dot) if matches!(self.peek(), Some(Token::Str(_))) => {
+                let key = self.parse_term()?;
+                Ok(match literal_element(&key) {
+                    Some(element) => extend_path(Filter::Identity, element),
+                    None => Filter::Index(Box::new(Filter::Identity), Box::new(key)),
+                })
+            }
+            Some(Token::Dot) => Ok(Filter::Identity),
+            Some(Token::DotDot) => Ok(Filter::Recurse),
+            Some(Token::Field(name)) => {
+                Ok(extend_path(Filter::Identity, JsonPathElement::Field(name)))
+            }
+            Some(Token::Num(n)) => Ok(Filter::Literal(number(n))),
+            Some(Token::Str(parts)) => self.parse_string(parts),
+            Some(Token::Var(name)) => Ok(Filter::Var(name)),
+            Some(Token::LParen) => {
+                let filter = self.parse_pipe()?;
+                self.expect(&Token::RParen, "expected )")?;
+                Ok(filter)
+            }
+            Some(Token::LBracket) => {
+                if self.next_if(&Token::RBracket) {
+                    return Ok(Filter::Array(None));
+                }
+                let filter = self.parse_pipe()?;
+                self.expect(&Token::RBracket, "expected ]")?;
+                Ok(Filter::Array(Some(Box::new(filter))))
+            }
+            Some(Token::LBrace) => self.parse_object(),
+            Some(Token::Ident(ident)) => match ident.as_str() {
+                "true" => Ok(Filter::Literal(Value::Bool(true))),
+                "false" => Ok(Filter::Literal(Value::Bool(false))),
+                "null" => Ok(Filter::Literal(Value::Null)),
+                "if" => self.parse_if(),
+                "reduce" => {
+                    let source = self.parse_postfix()?;
+                    self.expect_keyword("as", "expected as")?;
+                    let name = self.expect_var()?;
+                    self.expect(&Token::LParen, "expected (")?;
+                    let init = self.parse_pipe()?;
+                    self.expect(&Token::Semicolon, "expected ;")?;
+                    let update = self.parse_pipe()?;
+                    self.expect(&Token::RParen, "expected )")?;
+                    Ok(Filter::Reduce(
+                        Box::new(source),
+                        name,
+                        Box::new(init),
+                        Box::new(update),
+                    ))
+                }
+                ident if KEYWORDS.contains(&ident) => Err("unexpected keyword"),
+                _ => {
+                    let mut args = Vec::new();
+                    if self.next_if(&Token::LParen) {
+                        loop {
+                            args.push(self.parse_pipe()?);
+                            if !self.next_if(&Token::Semicolon) {
+                                break;
+                            }
+                        }
+                        self.expect(&Token::RParen, "expected )")?;
+                    }
+                    Ok(Filter::Call(ident, args))
+                }
+            },
+            _ => Err("expected term"),
+        }
+    }
+
+    fn parse_string(&mut self, parts: Vec<StrToken>) -> Result<Filter, &'static str> {
+        if let [StrToken::Literal(literal)] = parts.as_slice() {
+            return Ok(Filter::Literal(Value::String(literal.clone())));
+        }
+        let parts = parts
+            .into_iter()
+            .map(|part| match part {
+                StrToken::Literal(literal) => Ok(StrPart::Literal(literal)),
+                StrToken::Interpolation(tokens) => {
+                    let mut parser = Parser { tokens, pos: 0 };
+                    let filter = parser.parse_pipe()?;
+                    if parser.pos < parser.tokens.len() {
+                        return Err("unexpected token");
+                    }
+                    Ok(StrPart::Interpolation(filter))
+                }
+            })
+            .collect::<Result<Vec<_>, _>>()?;
+        Ok(Filter::Str(parts))
+    }
+
+    fn parse_if(&mut self) -> Result<Filter, &'static str> {
+        let condition = self.parse_pipe()?;
+        self.expect_keyword("then", "expected then")?;
+        let then = self.parse_pipe()?;
+        let otherwise = if self.next_if_keyword("elif") {
+            return Ok(Filter::If(
+                Box::new(condition),
+                Box::new(then),
+                Box::new(self.parse_if()?),
+            ));
+        } else if self.next_if_keyword("else") {
+            self.parse_pipe()?
+        } else {
+            Filter::Identity
+        };
+        self.expect_keyword("end", "expected end")?;
+        Ok(Filter::If(
+            Box::new(condition),
+            Box::new(then),
+            Box::new(otherwise),
+        ))
+    }
+
+    // Parses an object value, which may contain pipes but no commas.
+    fn parse_object_value(&mut self) -> Result<Filter, &'static str> {
+        let mut lhs = self.parse_alternative()?;
+        while self.next_if(&Token::Pipe) {
+            lhs = Filter::Pipe(Box::new(lhs), Box::new(self.parse_alternative()?));
+        }
+        Ok(lhs)
+    }
+
+    fn parse_object(&mut self) -> Result<Filter, &'static str> {
+        let mut entries = Vec::new();
+        if self.next_if(&Token::RBrace) {
+            return Ok(Filter::Object(entries));
+        }
+        loop {
+            let (key, shorthand) = match self.next() {
+                Some(Token::Ident(key)) => {
+                    let value = extend_path(Filter::Identity, JsonPathElement::Field(key.clone()));
+                    (Filter::Literal(Value::String(key)), Some(value))
+                }
+                Some(Token::Var(name)) => (
+                    Filter::Literal(Value::String(name.clone())),
+                    Some(Filter::Var(name)),
+                ),
+                Some(Token::Str(parts)) => {
+                    let key = self.parse_string(parts)?;
+                    let value = match literal_element(&key) {
+                        Some(element) => extend_path(Filter::Identity, element),
+                        None => Filter::Index(Box::new(Filter::Identity), Box::new(key.clone())),
+                    };
+                    (key, Some(value))
+                }
+                Some(Token::LParen) => {
+                    let key = self.parse_pipe()?;
+                    self.expect(&Token::RParen, "expected )")?;
+                    (key, None)
+                }
+                _ => return Err("expected object key"),
+            };
+            let value = if self.next_if(&Token::Colon) {
+                self.parse_object_value()?
+            } else {
+                shorthand.ok_or("expected :")?
+            };
+            entries.push((key, value));
+            if self.next_if(&Token::RBrace) {
+                return Ok(Filter::Object(entries));
+            }
+            self.expect(&Token::Comma, "expected , or }")?;
+        }
+    }
+}
+
+type Vars = Vec<(String, Value)>;
+
+fn truthy(value: &Value) -> bool {
+    !matches!(value, Value::Null | Value::Bool(false))
+}
+
+fn type_name(value: &Value) -> &'static str {
+    match value {
+        Value::Null => "null",
+        Value::Bool(_) => "boolean",
+        Value::Number(_) => "number",
+        Value::String(_) => "string",
+        Value::Array(_) => "array",
+        Value::Object(_) => "object",
+    }
+}
+
+fn type_rank(value: &Value) -> u8 {
+    match value {
+        Value::Null => 0,
+        Value::Bool(false) => 1,
+        Value::Bool(true) => 2,
+        Value::Number(_) => 3,
+        Value::String(_) => 4,
+        Value::Array(_) => 5,
+        Value::Object(_) => 6,
+    }
+}
+
+// Total order of jq: null < false < true < numbers < strings < arrays < objects.
+fn jq_cmp(a: &Value, b: &Value) -> Ordering {
+    match (a, b) {
+        (Value::Number(a), Value::Number(b)) => cmp_numbers(a, b),
+        (Value::String(a), Value::String(b)) => a.cmp(b),
+        (Value::Array(a), Value::Array(b)) => a
+            .iter()
+            .zip(b)
+            .map(|(a, b)| jq_cmp(a, b))
+            .find(|o| o.is_ne())
+            .unwrap_or_else(|| a.len().cmp(&b.len())),
+        (Value::Object(a), Value::Object(b)) => {
+            let mut keys_a: Vec<&String> = a.keys().collect();
+            let mut keys_b: Vec<&String> = b.keys().collect();
+            keys_a.sort();
+            keys_b.sort();
+            keys_a.cmp(&keys_b).then_with(|| {
+                keys_a
+                    .iter()
+                    .map(|key| jq_cmp(&a[*key], &b[*key]))
+                    .find(|o| o.is_ne())
+                    .unwrap_or(Ordering::Equal)
+            })
+        }
+        (a, b) => type_rank(a).cmp(&type_rank(b)),
+    }
+}
+
+fn to_string(value: &Value) -> String {
+    match value {
+        Value::String(s) => s.clone(),
+        value => value.to_string(),
+    }
+}
+
+fn index_value(value: &Value, index: &Value) -> Result<Value, &'static str> {
+    match (value, index) {
+        (Value::Object(map), Value::String(key)) => {
+            Ok(map.get(key).cloned().unwrap_or(Value::Null))
+        }
+        (Value::Array(array), Value::Number(n)) => {
+            let i = n.as_f64().unwrap_or(0.0).floor();
+            let i = if i < 0.0 { array.len() as f64 + i } else { i };
+            if i < 0.0 {
+                Ok(Value::Null)
+            } else {
+                Ok(array.get(i as usize).cloned().unwrap_or(Value::Null))
+            }
+        }
+        (Value::Null, Value::String(_) | Value::Number(_)) => Ok(Value::Null),
+        _ => Err("cannot index value"),
+    }
+}
+
+fn element_value(element: &JsonPathElement) -> Value {
+    match element {
+        JsonPathElement::Field(key) => Value::String(key.clone()),
+        JsonPathElement::Index(JsonPathIndex::NthLefth(i)) => Value::from(*i),
+        JsonPathElement::Index(JsonPathIndex::NthRight(i)) => number(-(*i as f64)),
+    }
+}
+
+fn slice_bounds(len: usize, from: &Value, to: &Value) -> Result<(usize, usize), &'static str> {
+    let bound = |v: &Value, default: usize| -> Result<usize, &'static str> {
+        match v {
+            Value::Null => Ok(default),
+            Value::Number(n) => {
+                let i = n.as_f64().unwrap_or(0.0).floor();
+                let i = if i < 0.0 { len as f64 + i } else { i };
+                Ok(i.clamp(0.0, len as f64) as usize)
+            }
+            _ => Err("slice indices must be numbers"),
+        }
+    };
+    let from = bound(from, 0)?;
+    let to = bound(to, len)?;
+    Ok((from, to.max(from)))
+}
+
+fn slice(value: &Value, from: &Value, to: &Value) -> Result<Value, &'static str> {
+    match value {
+        Value::Null => Ok(Value::Null),
+        Value::Array(array) => {
+            let (from, to) = slice_bounds(array.len(), from, to)?;
+            Ok(Value::Array(array[from..to].to_vec()))
+        }
+        Value::String(s) => {
+            let chars: Vec<char> = s.chars().collect();
+            let (from, to) = slice_bounds(chars.len(), from, to)?;
+            Ok(Value::String(chars[from..to].iter().collect()))
+        }
+        _ => Err("cannot slice value"),
+    }
+}
+
+fn iterate(value: &Value) -> Result<Vec<Value>, &'static str> {
+    match value {
+        Value::Array(array) => Ok(array.clone()),
+        Value::Object(map) => Ok(map.values().cloned().collect()),
+        _ => Err("cannot iterate over value"),
+    }
+}
+
+fn recurse(value: &Value, out: &mut Vec<Value>) {
+    out.push(value.clone());
+    match value {
+        Value::Array(array) => array.iter().for_each(|v| recurse(v, out)),
+        Value::Object(map) => map.values().for_each(|v| recurse(v, out)),
+        _ => {}
+    }
+}
+
+fn deep_merge(mut a: Map<String, Value>, b: &Map<String, Value>) -> Map<String, Value> {
+    for (key, v) in b {
+        let merged = match (a.remove(key), v) {
+            (Some(Value::Object(old)), Value::Object(v)) => Value::Object(deep_merge(old, v)),
+            (_, v) => v.clone(),
+        };
+        a.insert(key.clone(), merged);
+    }
+    a
+}
+
+fn binary(op: BinaryOp, a: &Value, b: &Value) -> Result<Value, &'static str> {
+    let numbers = match (a, b) {
+        (Value::Number(a), Value::Number(b)) => a.as_f64().zip(b.as_f64()),
+        _ => None,
+    };
+    match (op, a, b) {
+        (BinaryOp::Eq, a, b) => Ok(Value::Bool(jq_cmp(a, b).is_eq())),
+        (BinaryOp::Ne, a, b) => Ok(Value::Bool(jq_cmp(a, b).is_ne())),
+        (BinaryOp::Lt, a, b) => Ok(Value::Bool(jq_cmp(a, b).is_lt())),
+        (BinaryOp::Le, a, b) => Ok(Value::Bool(jq_cmp(a, b).is_le())),
+        (BinaryOp::Gt, a, b) => Ok(Value::Bool(jq_cmp(a, b).is_gt())),
+        (BinaryOp::Ge, a, b) => Ok(Value::Bool(jq_cmp(a, b).is_ge())),
+
+        (BinaryOp::Add, Value::Null, v) | (BinaryOp::Add, v, Value::Null) => Ok(v.clone()),
+        (BinaryOp::Add, Value::String(a), Value::String(b)) => Ok(Value::String(a.clone() + b)),
+        (BinaryOp::Add, Value::Array(a), Value::Array(b)) => {
+            Ok(Value::Array(a.iter().chain(b).cloned().collect()))
+        }
+        (BinaryOp::Add, Value::Object(a), Value::Object(b)) => {
+            let mut a = a.clone();
+            a.extend(b.clone());
+            Ok(Value::Object(a))
+        }
+        (BinaryOp::Sub, Value::Array(a), Value::Array(b)) => Ok(Value::Array(
+            a.iter()
+                .filter(|a| !b.iter().any(|b| jq_cmp(a, b).is_eq()))
+                .cloned()
+                .collect(),
+        )),
+        (BinaryOp::Mul, Value::String(s), Value::Number(n))
+        | (BinaryOp::Mul, Value::Number(n), Value::String(s)) => {
+            let n = n.as_f64().unwrap_or(0.0) as i64;
+            if n <= 0 {
+                return Ok(Value::Null);
+            }
+            match s.len().checked_mul(n as usize) {
+                Some(len) if len <= MAX_REPEAT_LEN => Ok(Value::String(s.repeat(n as usize))),
+                _ => Err("repeat string result too long"),
+            }
+        }
+        (BinaryOp::Mul, Value::Object(a), Value::Object(b)) => {
+            Ok(Value::Object(deep_merge(a.clone(), b)))
+        }
+        (BinaryOp::Div, Value::String(a), Value::String(b)) => Ok(split(a, b)),
+
+        (op, _, _) => {
+            let (a, b) = numbers.ok_or(match op {
+                BinaryOp::Add => "cannot add values",
+                BinaryOp::Sub => "cannot subtract values",
+                BinaryOp::Mul => "cannot multiply values",
+                _ => "cannot divide values",
+            })?;
+            match op {
+                BinaryOp::Add => Ok(number(a + b)),
+                BinaryOp::Sub => Ok(number(a - b)),
+                BinaryOp::Mul => Ok(number(a * b)),
+                BinaryOp::Div if b == 0.0 => Err("cannot divide by zero"),
+                BinaryOp::Div => Ok(number(a / b)),
+                _ if b as i64 == 0 => Err("cannot divide by zero"),
+                // i64::MIN % -1 overflows, its remainder is 0 like in jq.
+                _ => Ok(number((a as i64).checked_rem(b as i64).unwrap_or(0) as f64)),
+            }
+        }
+    }
+}
+
+fn split(s: &str, separator: &str) -> Value {
+    if s.is_empty() {
+        return Value::Array(Vec::new());
+    }
+    Value::Array(
+        s.split(separator)
+            .map(|part| Value::String(part.to_string()))
+            .collect(),
+    )
+}
+
+// The outputs of a filter, produced when they are pulled. Consumers stop at the first error.
+type Outputs<'a> = Box<dyn Iterator<Item = Result<Value, &'static str>> + 'a>;
+
+// The input of a filter, the value passed to `JqFilter::stream` or an output of another filter.
+#[derive(Clone)]
+enum Input<'a> {
+    Borrowed(&'a Value),
+    Shared(Rc<Value>),
+}
+
+impl<'a> Deref for Input<'a> {
+    type Target = Value;
+
+    #[inline]
+    fn deref(&self) -> &Value {
+        match self {
+            Input::Borrowed(value) => value,
+            Input::Shared(value) => value,
+        }
+    }
+}
+
+impl From<Value> for Input<'_> {
+    #[inline]
+    fn from(value: Value) -> Self {
+        Input::Shared(Rc::new(value))
+    }
+}
+
+fn once<'a>(output: Result<Value, &'static str>) -> Outputs<'a> {
+    Box::new(std::iter::once(output))
+}
+
+fn eager<'a>(outputs: Result<Vec<Value>, &'static str>) -> Outputs<'a> {
+    match outputs {
+        Ok(values) => Box::new(values.into_iter().map(Ok)),
+        Err(err) => once(Err(err)),
+    }
+}
+
+// Calls f with each output of outputs and chains the outputs it returns, errors are passed on.
+fn then<'a>(outputs: Outputs<'a>, mut f: impl FnMut(Value) -> Outputs<'a> + 'a) -> Outputs<'a> {
+    Box::new(outputs.flat_map(move |output| match output {
+        Ok(value) => f(value),
+        Err(err) => once(Err(err)),
+    }))
+}
+
+// Evaluates the filter only once its first output is pulled.
+fn defer<'a>(f: impl FnOnce() -> Outputs<'a> + 'a) -> Outputs<'a> {
+    Box::new(std::iter::once_with(f).flatten())
+}
+
+fn eval_all(filter: &Filter, input: &Input, vars: &Rc<Vars>) -> Result<Vec<Value>, &'static str> {
+    eval(filter, input, vars).collect()
+}
+
+fn eval<'a>(filter: &'a Filter, input: &Input<'a>, vars: &Rc<Vars>) -> Outputs<'a> {
+    let (input, vars) = (input.clone(), vars.clone());
+    match filter {
+        Filter::Identity => once(Ok(Value::clone(&input))),
+        Filter::Recurse => {
+            let mut out = Vec::new();
+            recurse(&input, &mut out);
+            eager(Ok(out))
+        }
+        Filter::Path(path) => {
+            if let Some(value) = path.find(&*input) {
+                return once(Ok(value.clone()));
+            }
+            // Missing keys and indices evaluate to null, other mismatches are errors
+            let mut value = Value::clone(&input);
+            for element in path.iter() {
+                match index_value(&value, &element_value(element)) {
+                    Ok(v) => value = v,
+                    Err(err) => return once(Err(err)),
+                }
+            }
+            once(Ok(value))
+        }
+        Filter::Index(term, index) => then(eval(term, &input, &vars), move |t| {
+            Box::new(eval(index, &input, &vars).map(move |i| index_value(&t, &i?)))
+        }),
+        Filter::Slice(term, from, to) => then(eval(term, &input, &vars), move |t| {
+            let (input, vars) = (input.clone(), vars.clone());
+            then(bound(to, &input, &vars), move |upper| {
+                let t = t.clone();
+                Box::new(bound(from, &input, &vars).map(move |lower| slice(&t, &lower?, &upper)))
+            })
+        }),
+        Filter::Iterate(term) => then(eval(term, &input, &vars), |t| eager(iterate(&t))),
+        Filter::Try(filter) => Box::new(eval(filter, &input, &vars).map_while(Result::ok).map(Ok)),
+        Filter::Literal(value) => once(Ok(value.clone())),
+        Filter::Str(parts) => eager(interpolate(parts, &input, &vars)),
+        Filter::Array(None) => once(Ok(Value::Array(Vec::new()))),
+        Filter::Array(Some(filter)) => once(eval_all(filter, &input, &vars).map(Value::Array)),
+        Filter::Object(entries) => eager(object(entries, &input, &vars)),
+        Filter::Neg(filter) => Box::new(eval(filter, &input, &vars).map(|v| match v?.as_f64() {
+            Some(n) => Ok(number(-n)),
+            None => Err("cannot negate value"),
+        })),
+        Filter::Pipe(lhs, rhs) => then(eval(lhs, &input, &vars), move |v| {
+            eval(rhs, &Input::from(v), &vars)
+        }),
+        Filter::Comma(lhs, rhs) => {
+            Box::new(eval(lhs, &input, &vars).chain(defer(move || eval(rhs, &input, &vars))))
+        }
+        Filter::Binary(op, lhs, rhs) => then(eval(rhs, &input, &vars), move |r| {
+            Box::new(eval(lhs, &input, &vars).map(move |l| binary(*op, &l?, &r)))
+        }),
+        Filter::And(lhs, rhs) | Filter::Or(lhs, rhs) => {
+            let is_and = matches!(filter, Filter::And(_, _));
+            then(eval(lhs, &input, &vars), move |l| {
+                if truthy(&l) != is_and {
+                    return once(Ok(Value::Bool(!is_and)));
+                }
+                Box::new(eval(rhs, &input, &vars).map(|r| Ok(Value::Bool(truthy(&r?)))))
+            })
+        }
+        Filter::Alternative(lhs, rhs) => {
+            let mut out = eval(lhs, &input, &vars)
+                .map_while(Result::ok)
+                .filter(truthy)
+                .peekable();
+            if out.peek().is_none() {
+                eval(rhs, &input, &vars)
+            } else {
+                Box::new(out.map(Ok))
+            }
+        }
+        Filter::If(condition, then_branch, otherwise) => {
+            then(eval(condition, &input, &vars), move |c| {
+                let branch = if truthy(&c) { then_branch } else { otherwise };
+                eval(branch, &input, &vars)
+            })
+        }
+        Filter::Reduce(source, name, init, update) => then(eval(init, &input, &vars), move |acc| {
+            once(reduce(source, name, update, acc, &input, &vars))
+        }),
+        Filter::Bind(source, name, body) => then(eval(source, &input, &vars), move |v| {
+            eval(body, &input, &bind(&vars, name, v))
+        }),
+        Filter::Var(name) => once(
+            vars.iter()
+                .rev()
+                .find(|(n, _)| n == name)
+                .map(|(_, v)| v.clone())
+                .ok_or("undefined variable"),
+        ),
+        Filter::Call(name, args) => call(name, args, &input, &vars),
+    }
+}
+
+// Updates acc with each output of source bound to name.
+fn reduce(
+    source: &Filter,
+    name: &str,
+    update: &Filter,
+    mut acc: Value,
+    input: &Input,
+    vars: &Rc<Vars>,
+) -> Result<Value, &'static str> {
+    for item in eval(source, input, vars) {
+        let vars = bind(vars, name, item?);
+        acc = eval_all(update, &Input::from(acc), &vars)?
+            .pop()
+            .unwrap_or(Value::Null);
+    }
+    Ok(acc)
+}
+
+fn bind(vars: &Rc<Vars>, name: &str, value: Value) -> Rc<Vars> {
+    let mut vars = Vars::clone(vars);
+    vars.push((name.to_string(), value));
+    Rc::new(vars)
+}
+
+// The outputs of an optional slice bound, null if it is missing.
+fn bound<'a>(filter: &'a Option<Box<Filter>>, input: &Input<'a>, vars: &Rc<Vars>) -> Outputs<'a> {
+    match filter {
+        Some(filter) => eval(filter, input, vars),
+        None => once(Ok(Value::Null)),
+    }
+}
+
+fn interpolate(
+    parts: &[StrPart],
+    input: &Input,
+    vars: &Rc<Vars>,
+) -> Result<Vec<Value>, &'static str> {
+    let mut out = vec![String::new()];
+    for part in parts {
+        match part {
+            StrPart::Literal(literal) => out.iter_mut().for_each(|s| s.push_str(literal)),
+            StrPart::Interpolation(filter) => {
+                let values = eval_all(filter, input, vars)?;
+                out = out
+                    .iter()
+                    .flat_map(|s| values.iter().map(move |v| s.clone() + &to_string(v)))
+                    .collect();
+            }
+        }
+    }
+    Ok(out.into_iter().map(Value::String).collect())
+}
+
+fn object(
+    entries: &[(Filter, Filter)],
+    input: &Input,
+    vars: &Rc<Vars>,
+) -> Result<Vec<Value>, &'static str> {
+    let mut out = vec![Map::new()];
+    for (key, value) in entries {
+        let keys = eval_all(key, input, vars)?;
+        let values = eval_all(value, input, vars)?;
+        let mut next = Vec::new();
+        for map in &out {
+            for key in &keys {
+                let Value::String(key) = key else {
+                    return Err("object keys must be strings");
+                };
+                for value in &values {
+                    let mut map = map.clone();
+                    map.insert(key.clone(), value.clone());
+                    next.push(map);
+                }
+            }
+        }
+        out = next;
+    }
+    Ok(out.into_iter().map(Value::Object).collect())
+}
+
+// Counts from the lower bound in steps of 1 while below the upper bound. Integer bounds are
+// stepped exactly, other bounds by the number of steps, so the range always comes to an end.
+fn range(bounds: &[Value]) -> Outputs<'static> {
+    let (from, to) = match bounds {
+        [to] => (Value::from(0), to),
+        [from, to] => (from.clone(), to),
+        _ => unreachable!(),
+    };
+    if let (Some(from), Some(to)) = (from.as_i64(), to.as_i64()) {
+        return Box::new((from..to).map(|i| Ok(Value::from(i))));
+    }
+    let (Some(from), Some(to)) = (from.as_f64(), to.as_f64()) else {
+        return once(Err("range bounds must be numbers"));
+    };
+    let steps = (to - from).ceil().max(0.0) as u64;
+    Box::new((0..steps).map(move |i| Ok(number(from + i as f64))))
+}
+
+fn to_entries(value: &Value) -> Result<Value, &'static str> {
+    let Value::Object(map) = value else {
+        return Err("cannot convert value to entries");
+    };
+    Ok(Value::Array(
+        map.iter()
+            .map(|(key, value)| {
+                let mut entry = Map::new();
+                entry.insert("key".to_string(), Value::String(key.clone()));
+                entry.insert("value".to_string(), value.clone());
+                Value::Object(entry)
+            })
+            .collect(),
+    ))
+}
+
+fn from_entries(value: &Value) -> Result<Value, &'static str> {
+    let mut map = Map::new();
+    for entry in iterate(value)? {
+        let field = |names: &[&str]| {
+            names
+                .iter()
+                .find_map(|name| entry.get(name).filter(|v| !v.is_null()))
+                .cloned()
+        };
+        let key = match field(&["key", "k", "name", "Name", "Key", "K"]) {
+            Some(Value::String(key)) => key,
+            Some(key @ (Value::Number(_) | Value::Bool(_))) => key.to_string(),
+            _ => return Err("entry key must be a string"),
+        };
+        let value = field(&["value", "v", "Value", "V"]).unwrap_or(Value::Null);
+        map.insert(key, value);
+    }
+    Ok(Value::Object(map))
+}
+
+fn sort_by_keys(items: Vec<Value>, keys: Vec<Value>) -> Vec<(Value, Value)> {
+    let mut pairs: Vec<(Value, Value)> = keys.into_iter().zip(items).collect();
+    pairs.sort_by(|a, b| jq_cmp(&a.0, &b.0));
+    pairs
+}
+
+fn array_arg(value: &Value) -> Result<&Vec<Value>, &'static str> {
+    value.as_array().ok_or("expected an array")
+}
+
+fn string_arg(value: &Value) -> Result<&str, &'static str> {
+    value.as_str().ok_or("expected a string")
+}
+
+fn flatten(array: &[Value], depth: f64, out: &mut Vec<Value>) {
+    for v in array {
+        match v {
+            Value::Array(inner) if depth > 0.0 => flatten(inner, depth - 1.0, out),
+            v => out.push(v.clone()),
+        }
+    }
+}
+
+fn contains(a: &Value, b: &Value) -> Result<bool, &'static str> {
+    match (a, b) {
+        (Value::String(a), Value::String(b)) => Ok(a.contains(b.as_str())),
+        (Value::Array(a), Value::Array(b)) => {
+            for b in b {
+                let mut found = false;
+                for a in a {
+                    if contains(a, b).unwrap_or(false) {
+                        found = true;
+                        break;
+                    }
+                }
+                if !found {
+                    return Ok(false);
+                }
+            }
+            Ok(true)
+        }
+        (Value::Object(a), Value::Object(b)) => {
+            for (key, b) in b {
+                match a.get(key) {
+                    Some(a) if contains(a, b)? => {}
+                    _ => return Ok(false),
+                }
+            }
+            Ok(true)
+        }
+        (a, b) if type_name(a) == type_name(b) => Ok(jq_cmp(a, b).is_eq()),
+        _ => Err("cannot check whether values of different types contain each other"),
+    }
+}
+
+// Evaluates each argument and calls f with every combination of their outputs.
+fn with_args(
+    args: &[Filter],
+    input: &Input,
+    vars: &Rc<Vars>,
+    f: impl Fn(&[Value]) -> Result<Value, &'static str>,
+) -> Result<Vec<Value>, &'static str> {
+    let mut combinations: Vec<Vec<Value>> = vec![Vec::new()];
+    for arg in args {
+        let values = eval_all(arg, input, vars)?;
+        combinations = combinations
+            .into_iter()
+            .flat_map(|c| {
+                values.iter().map(move |v| {
+                    let mut c = c.clone();
+                    c.push(v.clone());
+                    c
+                })
+            })
+            .collect();
+    }
+    combinations.iter().map(|args| f(args)).collect()
+}
+
+fn call<'a>(name: &str, args: &'a [Filter], input: &Input<'a>, vars: &Rc<Vars>) -> Outputs<'a> {
+    match (name, args) {
+        ("empty", []) => Box::new(std::iter::empty()),
+        ("select", [f]) => {
+            let input = input.clone();
+            Box::new(eval(f, &input, vars).filter_map(move |c| match c {
+                Ok(c) => truthy(&c).then(|| Ok(Value::clone(&input))),
+                Err(err) => Some(Err(err)),
+            }))
+        }
+        ("range", [_] | [_, _]) => {
+            match with_args(args, input, vars, |args| Ok(Value::Array(args.to_vec()))) {
+                Ok(bounds) => Box::new(bounds.into_iter().flat_map(|bounds| match bounds {
+                    Value::Array(bounds) => range(&bounds),
+                    _ => unreachable!(),
+                })),
+                Err(err) => once(Err(err)),
+            }
+        }
+        ("first", [f]) => Box::new(eval(f, input, vars).take(1)),
+        ("recurse", []) => {
+            let mut out = Vec::new();
+            recurse(input, &mut out);
+            eager(Ok(out))
+        }
+        _ => eager(builtin(name, args, input, vars)),
+    }
+}
+
+// The builtins which evaluate all outputs of their arguments.
+fn builtin(
+    name: &str,
+    args: &[Filter],
+    input: &Input,
+    vars: &Rc<Vars>,
+) -> Result<Vec<Value>, &'static str> {
+    let one = |v: Result<Value, &'static str>| v.map(|v| vec![v]);
+    match (name, args) {
+        ("not", []) => one(Ok(Value::Bool(!truthy(input)))),
+        ("length", []) => one(match &**input {
+            Value::Null => Ok(Value::from(0)),
+            Value::Bool(_) => Err("boolean has no length"),
+            Value::Number(n) => Ok(number(n.as_f64().unwrap_or(0.0).abs())),
+            Value::String(s) => Ok(Value::from(s.chars().count())),
+            Value::Array(array) => Ok(Value::from(array.len())),
+            Value::Object(map) => Ok(Value::from(map.len())),
+        }),
+        ("keys" | "keys_unsorted", []) => one(match &**input {
+            Value::Object(map) => {
+                let mut keys: Vec<&String> = map.keys().collect();
+                keys.sort();
+                Ok(Value::Array(
+                    keys.into_iter().cloned().map(Value::String).collect(),
+                ))
+            }
+            Value::Array(array) => Ok(Value::Array((0..array.len()).map(Value::from).collect())),
+            _ => Err("value has no keys"),
+        }),
+        ("has", [key]) => with_args(std::slice::from_ref(key), input, vars, |args| {
+            match (&**input, &args[0]) {
+                (Value::Object(map), Value::String(key)) => Ok(Value::Bool(map.contains_key(key))),
+                (Value::Array(array), Value::Number(n)) => Ok(Value::Bool(
+                    n.as_f64()
+                        .is_some_and(|n| n >= 0.0 && n < array.len() as f64),
+                )),
+                _ => Err("cannot check whether value has a key"),
+            }
+        }),
+        ("values", []) => Ok(if input.is_null() {
+            Vec::new()
+        } else {
+            vec![Value::clone(input)]
+        }),
+        ("map", [f]) => {
+            let mut out = Vec::new();
+            for v in iterate(input)? {
+                out.extend(eval_all(f, &Input::from(v), vars)?);
+            }
+            one(Ok(Value::Array(out)))
+        }
+        ("map_values", [f]) => one(match &**input {
+            Value::Object(map) => {
+                let mut out = Map::new();
+                for (key, v) in map {
+                    if let Some(v) = eval_all(f, &Input::Borrowed(v), vars)?.into_iter().next() {
+                        out.insert(key.clone(), v);
+                    }
+                }
+                Ok(Value::Object(out))
+            }
+            Value::Array(array) => {
+                let mut out = Vec::new();
+                for v in array {
+                    out.extend(eval_all(f, &Input::Borrowed(v), vars)?.into_iter().next());
+                }
+                Ok(Value::Array(out))
+            }
+            _ => Err("cannot iterate over value"),
+        }),
+        ("to_entries", []) => one(to_entries(input)),
+        ("from_entries", []) => one(from_entries(input)),
+        ("with_entries", [f]) => {
+            let mut entries = Vec::new();
+            for entry in iterate(&to_entries(input)?)? {
+                entries.extend(eval_all(f, &Input::from(entry), vars)?);
+            }
+            one(from_entries(&Value::Array(entries)))
+        }
+        ("add", []) => {
+            let mut acc = Value::Null;
+            for v in iterate(input)? {
+                acc = binary(BinaryOp::Add, &acc, &v)?;
+            }
+            one(Ok(acc))
+        }
+        ("type", []) => one(Ok(Value::String(type_name(input).to_string()))),
+        ("tostring", []) => one(Ok(Value::String(to_string(input)))),
+        ("tonumber", []) => one(match &**input {
+            Value::Number(_) => Ok(Value::clone(input)),
+            Value::String(s) => s
+                .trim()
+                .parse::<f64>()
+                .map(number)
+                .map_err(|_| "cannot parse string as number"),
+            _ => Err("cannot convert value to number"),
+        }),
+        ("tojson", []) => one(Ok(Value::String(input.to_string()))),
+        ("fromjson", []) => {
+            one(serde_json::from_str(string_arg(input)?).map_err(|_| "invalid JSON text"))
+        }
+        ("ascii_downcase", []) => one(Ok(Value::String(string_arg(input)?.to_ascii_lowercase()))),
+        ("ascii_upcase", []) => one(Ok(Value::String(string_arg(input)?.to_ascii_uppercase()))),
+        ("sort", []) => {
+            let mut array = array_arg(input)?.clone();
+            array.sort_by(jq_cmp);
+            one(Ok(Value::Array(array)))
+        }
+        ("sort_by" | "group_by" | "unique_by" | "min_by" | "max_by", [f]) => {
+            let array = array_arg(input)?.clone();
+            let mut keys = Vec::new();
+            for v in &array {
+                keys.push(Value::Array(eval_all(f, &Input::Borrowed(v), vars)?));
+            }
+            let pairs = sort_by_keys(array, keys);
+            one(Ok(match name {
+                "sort_by" => Value::Array(pairs.into_iter().map(|(_, v)| v).collect()),
+                "min_by" => pairs.into_iter().next().map_or(Value::Null, |(_, v)| v),
+                "max_by" => pairs.into_iter().last().map_or(Value::Null, |(_, v)| v),
+                _ => {
+                    let mut groups: Vec<(Value, Vec<Value>)> = Vec::new();
+                    for (key, v) in pairs {
+                        match groups.last_mut() {
+                            Some((last, group)) if jq_cmp(last, &key).is_eq() => group.push(v),
+                            _ => groups.push((key, vec![v])),
+                        }
+                    }
+                    Value::Array(
+                        groups
+                            .into_iter()
+                            .map(|(_, mut group)| {
+                                if name == "unique_by" {
+                                    group.swap_remove(0)
+                                } else {
+                                    Value::Array(group)
+                                }
+                            })
+                            .collect(),
+                    )
+                }
+            }))
+        }
+        ("unique", []) => {
+            let mut array = array_arg(input)?.clone();
+            array.sort_by(jq_cmp);
+            array.dedup_by(|a, b| jq_cmp(a, b).is_eq());
+            one(Ok(Value::Array(array)))
+        }
+        ("min", []) => one(Ok(array_arg(input)?
+            .iter()
+            .min_by(|a, b| jq_cmp(a, b))
+            .cloned()
+            .unwrap_or(Value::Null))),
+        ("max", []) => one(Ok(array_arg(input)?
+            .iter()
+            .max_by(|a, b| jq_cmp(a, b))
+            .cloned()
+            .unwrap_or(Value::Null))),
+        ("reverse", []) => one(match &**input {
+            Value::Null => Ok(Value::Array(Vec::new())),
+            Value::String(s) => Ok(Value::String(s.chars().rev().collect())),
+            Value::Array(array) => Ok(Value::Array(array.iter().rev().cloned().collect())),
+            _ => Err("cannot reverse value"),
+        }),
+        ("join", [separator]) => with_args(std::slice::from_ref(separator), input, vars, |args| {
+            let separator = string_arg(&args[0])?;
+            let mut parts = Vec::new();
+            for v in array_arg(input)? {
+                parts.push(match v {
+                    Value::Null => String::new(),
+                    Value::String(s) => s.clone(),
+                    Value::Number(_) | Value::Bool(_) => v.to_string(),
+                    _ => return Err("cannot join arrays or objects"),
+                });
+            }
+            Ok(Value::String(parts.join(separator)))
+        }),
+        ("split", [separator]) => with_args(std::slice::from_ref(separator), input, vars, |args| {
+            Ok(split(string_arg(input)?, string_arg(&args[0])?))
+        }),
+        ("startswith" | "endswith" | "ltrimstr" | "rtrimstr", [arg]) => {
+            with_args(std::slice::from_ref(arg), input, vars, |args| {
+                let (Value::String(s), Value::String(affix)) = (&**input, &args[0]) else {
+                    return match name {
+                        "startswith" | "endswith" => Err("expected strings"),
+                        _ => Ok(Value::clone(input)),
+                    };
+                };
+                Ok(match name {
+                    "startswith" => Value::Bool(s.starts_with(affix.as_str())),
+                    "endswith" => Value::Bool(s.ends_with(affix.as_str())),
+                    "ltrimstr" => {
+                        Value::String(s.strip_prefix(affix.as_str()).unwrap_or(s).to_string())
+                    }
+                    _ => Value::String(s.strip_suffix(affix.as_str()).unwrap_or(s).to_string()),
+                })
+            })
+        }
+        ("contains", [b]) => with_args(std::slice::from_ref(b), input, vars, |args| {
+            contains(input, &args[0]).map(Value::Bool)
+        }),
+        ("floor" | "ceil" | "sqrt", []) => {
+            let n = input.as_f64().ok_or("expected a number")?;
+            one(Ok(number(match name {
+                "floor" => n.floor(),
+                "ceil" => n.ceil(),
+                _ => n.sqrt(),
+            })))
+        }
+        ("any", []) => one(Ok(Value::Bool(array_arg(input)?.iter().any(truthy)))),
+        ("all", []) => one(Ok(Value::Bool(array_arg(input)?.iter().all(truthy)))),
+        ("flatten", []) => {
+            let mut out = Vec::new();
+            flatten(array_arg(input)?, f64::INFINITY, &mut out);
+            one(Ok(Value::Array(out)))
+        }
+        ("flatten", [depth]) => with_args(std::slice::from_ref(depth), input, vars, |args| {
+            let depth = args[0].as_f64().ok_or("flatten depth must be a number")?;
+            if depth < 0.0 {
+                return Err("flatten depth must not be negative");
+            }
+            let mut out = Vec::new();
+            flatten(array_arg(input)?, depth, &mut out);
+            Ok(Value::Array(out))
+        }),
+        ("first", []) => one(index_value(input, &Value::from(0))),
+        ("last", []) => one(index_value(input, &Value::from(-1))),
+        _ => Err("unknown function"),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::*;
+
+    #[test]
+    fn lowering() {
+        let tests = vec![
+            (".a.b[0]", Filter::Path("$.a.b[0]".try_into().unwrap())),
+            (".[-1].a", Filter::Path("$[#-1].a".try_into().unwrap())),
+            (r#".["a"].b"#, Filter::Path("$.a.b".try_into().unwrap())),
+            (
+                ".a[].b",
+                Filter::Pipe(
+                    Box::new(Filter::Iterate(Box::new(Filter::Path(
+                        "$.a".try_into().unwrap(),
+                    )))),
+                    Box::new(Filter::Path("$.b".try_into().unwrap())),
+                ),
+            ),
+        ];
+        for (filter, expected) in tests {
+            let parsed: JqFilter = filter.parse().unwrap();
+            assert_eq!(
+                parsed.0, expected,
+                "expected {} to be {:?}",
+                filter, expected
+            );
+        }
+    }
+
+    #[test]
+    fn run() {
+        let input = json!({
+            "a": {"b": [1, 2, 3]},
+            "users": [
+                {"name": "ann", "age": 31, "tags": ["x"]},
+                {"name": "bob", "age": 17, "tags": []},
+                {"name": "cid", "age": 45, "tags": ["y", "z"]}
+            ],
+            "n": null
+        });
+        let tests = vec![
+            (".", Ok(vec![input.clone()])),
+            (".a.b[1]", Ok(vec![json!(2)])),
+            (".a.b[-1]", Ok(vec![json!(3)])),
+            (".a.b[5]", Ok(vec![json!(null)])),
+            (".missing.deeper", Ok(vec![json!(null)])),
+            (".a.b.c", Err("cannot index value")),
+            (".a.b.c?", Ok(vec![])),
+            (".a.b[]", Ok(vec![json!(1), json!(2), json!(3)])),
+            (".a.b[1:]", Ok(vec![json!([2, 3])])),
+            (".a.b[:-1]", Ok(vec![json!([1, 2])])),
+            (
+                ".users[].name",
+                Ok(vec![json!("ann"), json!("bob"), json!("cid")]),
+            ),
+            (
+                ".users[] | select(.age >= 18) | .name",
+                Ok(vec![json!("ann"), json!("cid")]),
+            ),
+            (".users | map(.age) | add", Ok(vec![json!(93)])),
+            (".users | map(.tags | length)", Ok(vec![json!([1, 0, 2])])),
+            (
+                "[.users[] | {name, adult: (.age > 18)}]",
+                Ok(vec![json!([
+                    {"name": "ann", "adult": true},
+                    {"name": "bob", "adult": false},
+                    {"name": "cid", "adult": true}
+                ])]),
+            ),
+            (
+                "{(.users[0].name): .a.b[0], \"k\": 1}",
+                Ok(vec![json!({"ann": 1, "k": 1})]),
+            ),
+            ("{a: (1, 2)}", Ok(vec![json!({"a": 1}), json!({"a": 2})])),
+            (".n // \"default\"", Ok(vec![json!("default")])),
+            (".a.b[0] // 5", Ok(vec![json!(1)])),
+            (
+                "(1, 2) + (10, 20)",
+                Ok(vec![json!(11), json!(12), json!(21), json!(22)]),
+            ),
+            ("1 + 2 * 3 - 4 / 2", Ok(vec![json!(5)])),
+            ("7 % 3, -(1 + 2)", Ok(vec![json!(1), json!(-3)])),
+            (
+                "\"a\" + \"b\", [1] + [2], {a: 1} + {b: 2}",
+                Ok(vec![json!("ab"), json!([1, 2]), json!({"a": 1, "b": 2})]),
+            ),
+            ("[1, 2, 3, 2] - [2]", Ok(vec![json!([1, 3])])),
+            (
+                "{a: {b: 1}} * {a: {c: 2}}",
+                Ok(vec![json!({"a": {"b": 1, "c": 2}})]),
+            ),
+            ("1 / 0", Err("cannot divide by zero")),
+            ("{} + 1", Err("cannot add values")),
+            ("reduce .a.b[] as $x (0; . + $x)", Ok(vec![json!(6)])),
+            (".users[0] as $u | $u.name", Ok(vec![json!("ann")])),
+            (
+                ".users[] | \"\\(.name) is \\(.age)\"",
+                Ok(vec![
+                    json!("ann is 31"),
+                    json!("bob is 17"),
+                    json!("cid is 45"),
+                ]),
+            ),
+            (
+                ".a | to_entries",
+                Ok(vec![json!([{"key": "b", "value": [1, 2, 3]}])]),
+            ),
+            (
+                "[{name: \"x\", value: 1}, {k: \"y\", v: 2}] | from_entries",
+                Ok(vec![json!({"x": 1, "y": 2})]),
+            ),
+            (
+                "{a: 1, b: 2} | with_entries(.value += 1)",
+                Err("unexpected character"),
+            ),
+            (
+                "{a: 1, b: 2} | with_entries({key: (.key + \"!\"), value})",
+                Ok(vec![json!({"a!": 1, "b!": 2})]),
+            ),
+            ("if .n then 1 elif .a then 2 else 3 end", Ok(vec![json!(2)])),
+            ("[.[] | type]", Ok(vec![json!(["object", "null", "array"])])),
+            (
+                ".users | sort_by(-.age) | map(.name)",
+                Ok(vec![json!(["cid", "ann", "bob"])]),
+            ),
+            (
+                "[3, 1, null, \"a\", [1], {}] | sort",
+                Ok(vec![json!([null, 1, 3, "a", [1], {}])]),
+            ),
+            ("[1, 2, 1] | unique", Ok(vec![json!([1, 2])])),
+            ("[..] | length", Ok(vec![json!(23)])),
+            (".\"a\".b | length", Ok(vec![json!(3)])),
+            (
+                "[range(3)], [range(1; 3)]",
+                Ok(vec![json!([0, 1, 2]), json!([1, 2])]),
+            ),
+            (
+                "\"a,b\" | split(\",\") | join(\"-\")",
+                Ok(vec![json!("a-b")]),
+            ),
+            (
+                "true and (true, false), false or false",
+                Ok(vec![json!(true), json!(false), json!(false)]),
+            ),
+            (
+                "[1, [2, [3]]] | flatten, flatten(1)",
+                Ok(vec![json!([1, 2, 3]), json!([1, 2, [3]])]),
+            ),
+            (
+                "\"foobar\" | contains(\"bar\"), ltrimstr(\"foo\")",
+                Ok(vec![json!(true), json!("bar")]),
+            ),
+            (
+                ".users | group_by(.tags | length) | map(length)",
+                Ok(vec![json!([1, 1, 1])]),
+            ),
+            (
+                "\"1.5\" | tonumber, (1 | tostring)",
+                Ok(vec![json!(1.5), json!("1")]),
+            ),
+            ("$x", Err("undefined variable")),
+            ("foo(1)", Err("unknown function")),
+            ("-9223372036854775808 % -1", Ok(vec![json!(0)])),
+            ("\"ab\" * 3", Ok(vec![json!("ababab")])),
+            ("\"a\" * 1e18", Err("repeat string result too long")),
+        ];
+        for (filter, expected) in tests {
+            let result = JqFilter::from_str(filter).and_then(|f| f.run(&input));
+            assert_eq!(result, expected, "expected {} to be {:?}", filter, expected);
+        }
+    }
+
+    #[test]
+    fn stream() {
+        let input = json!([
+            1,
+            0,
+            2,
+            9007199254740992u64,
+            9007199254740994u64,
+            9007199254740993u64
+        ]);
+        let tests = vec![
+            ("first(range(1e18))", vec![Ok(json!(0))]),
+            ("first(range(1e18) | select(. > 2))", vec![Ok(json!(3))]),
+            (
+                "range(.[3]; .[4])",
+                vec![
+                    Ok(json!(9007199254740992u64)),
+                    Ok(json!(9007199254740993u64)),
+                ],
+            ),
+            // Literals are floats, steps beyond 2^53 round to the same number
+            (
+                "range(9007199254740992; 9007199254740994)",
+                vec![Ok(json!(9007199254740992.0)), Ok(json!(9007199254740992.0))],
+            ),
+            (
+                "range(0.5; 3)",
+                vec![Ok(json!(0.5)), Ok(json!(1.5)), Ok(json!(2.5))],
+            ),
+            ("range(1e300; 1e300 + 1)", vec![]),
+            (
+                "1, (1 / 0), first(range(1e18))",
+                vec![Ok(json!(1)), Err("cannot divide by zero")],
+            ),
+            (
+                ".[] | 1 / .",
+                vec![Ok(json!(1)), Err("cannot divide by zero")],
+            ),
+            ("[.[:3][] | (1 / .)?]", vec![Ok(json!([1, 0.5]))]),
+            ("(1, (1 / 0), 2)?", vec![Ok(json!(1))]),
+            (
+                ".[5] == 9007199254740992, .[5] > 9007199254740992",
+                vec![Ok(json!(false)), Ok(json!(true))],
+            ),
+        ];
+        for (filter, expected) in tests {
+            let filter = JqFilter::from_str(filter).unwrap();
+            let result: Vec<_> = filter.stream(&input).collect();
+            assert_eq!(
+                result, expected,
+                "expected {:?} to be {:?}",
+                filter, expected
+            );
+        }
+    }
+
+    #[test]
+    fn try_from() {
+        let tests = vec![
+            (".a | ", Err("expected term")),
+            ("[1", Err("expected ]")),
+            ("{a: 1", Err("expected , or }")),
+            ("\"abc", Err("unterminated string")),
+            ("if . then 1", Err("expected end")),
+            (". .", Err("unexpected token")),
+            ("\"\\(1\"", Err("unterminated string")),
+            ("\"\\ud83d\\ude00\"", Ok(())),
+            ("\"\\udbff\\u0000\"", Err("invalid unicode escape")),
+            ("\"\\ud83d\\ud83d\"", Err("invalid unicode escape")),
+        ];
+        for (filter, expected) in tests {
+            let result = JqFilter::try_from(filter).map(|_| ());
+            assert_eq!(result, expected, "expected {} to be {:?}", filter, expected);
+        }
+    }
+}
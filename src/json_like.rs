@@ -0,0 +1,421 @@
+use serde_json::{Number, Value};
+
+/// A JSON-like value which paths and comparisons operate on.
+///
+/// Implemented for `serde_json::Value` and, behind cargo features of the same name, for
+/// `simd_json::OwnedValue`, `serde_yaml::Value` and `toml::Value`.
+pub trait JsonLike: Sized + PartialEq {
+    fn is_null(&self) -> bool;
+    fn as_bool(&self) -> Option<bool>;
+    fn as_number(&self) -> Option<Number>;
+    fn as_str(&self) -> Option<&str>;
+    fn as_array(&self) -> Option<&Vec<Self>>;
+    fn as_array_mut(&mut self) -> Option<&mut Vec<Self>>;
+    fn is_object(&self) -> bool;
+    /// Returns the value of `key` if self is an object.
+    fn get_key(&self, key: &str) -> Option<&Self>;
+    fn get_key_mut(&mut self, key: &str) -> Option<&mut Self>;
+    /// Returns the keys if self is an object, non-string keys are skipped.
+    fn keys(&self) -> Vec<&str>;
+    /// Inserts `value` at `key` if self is an object, returns the previous value.
+    fn insert_key(&mut self, key: &str, value: Self) -> Option<Self>;
+    /// Removes `key` if self is an object, returns the removed value.
+    fn remove_key(&mut self, key: &str) -> Option<Self>;
+}
+
+impl JsonLike for Value {
+    #[inline]
+    fn is_null(&self) -> bool {
+        self.is_null()
+    }
+
+    #[inline]
+    fn as_bool(&self) -> Option<bool> {
+        self.as_bool()
+    }
+
+    #[inline]
+    fn as_number(&self) -> Option<Number> {
+        match self {
+            Value::Number(n) => Some(n.clone()),
+            _ => None,
+        }
+    }
+
+    #[inline]
+    fn as_str(&self) -> Option<&str> {
+        self.as_str()
+    }
+
+    #[inline]
+    fn as_array(&self) -> Option<&Vec<Self>> {
+        self.as_array()
+    }
+
+    #[inline]
+    fn as_array_mut(&mut self) -> Option<&mut Vec<Self>> {
+        self.as_array_mut()
+    }
+
+    #[inline]
+    fn is_object(&self) -> bool {
+        self.is_object()
+    }
+
+    #[inline]
+    fn get_key(&self, key: &str) -> Option<&Self> {
+        self.as_object().and_then(|map| map.get(key))
+    }
+
+    #[inline]
+    fn get_key_mut(&mut self, key: &str) -> Option<&mut Self> {
+        self.as_object_mut().and_then(|map| map.get_mut(key))
+    }
+
+    #[inline]
+    fn keys(&self) -> Vec<&str> {
+        self.as_object()
+            .map(|map| map.keys().map(String::as_str).collect())
+            .unwrap_or_default()
+    }
+
+    #[inline]
+    fn insert_key(&mut self, key: &str, value: Self) -> Option<Self> {
+        self.as_object_mut()
+            .and_then(|map| map.insert(key.to_string(), value))
+    }
+
+    #[inline]
+    fn remove_key(&mut self, key: &str) -> Option<Self> {
+        self.as_object_mut().and_then(|map| map.remove(key))
+    }
+}
+
+#[cfg(feature = "simd-json")]
+impl JsonLike for simd_json::OwnedValue {
+    fn is_null(&self) -> bool {
+        matches!(self, Self::Static(simd_json::StaticNode::Null))
+    }
+
+    fn as_bool(&self) -> Option<bool> {
+        match self {
+            Self::Static(simd_json::StaticNode::Bool(b)) => Some(*b),
+            _ => None,
+        }
+    }
+
+    fn as_number(&self) -> Option<Number> {
+        match self {
+            Self::Static(simd_json::StaticNode::I64(i)) => Some(Number::from(*i)),
+            Self::Static(simd_json::StaticNode::U64(u)) => Some(Number::from(*u)),
+            Self::Static(simd_json::StaticNode::F64(f)) => Number::from_f64(*f),
+            _ => None,
+        }
+    }
+
+    fn as_str(&self) -> Option<&str> {
+        match self {
+            Self::String(s) => Some(s),
+            _ => None,
+        }
+    }
+
+    fn as_array(&self) -> Option<&Vec<Self>> {
+        match self {
+            Self::Array(array) => Some(array),
+            _ => None,
+        }
+    }
+
+    fn as_array_mut(&mut self) -> Option<&mut Vec<Self>> {
+        match self {
+            Self::Array(array) => Some(array),
+            _ => None,
+        }
+    }
+
+    fn is_object(&self) -> bool {
+        matches!(self, Self::Object(_))
+    }
+
+    fn get_key(&self, key: &str) -> Option<&Self> {
+        match self {
+            Self::Object(map) => map.get(key),
+            _ => None,
+        }
+    }
+
+    fn get_key_mut(&mut self, key: &str) -> Option<&mut Self> {
+        match self {
+            Self::Object(map) => map.get_mut(key),
+            _ => None,
+        }
+    }
+
+    fn keys(&self) -> Vec<&str> {
+        match self {
+            Self::Object(map) => map.keys().map(String::as_str).collect(),
+            _ => Vec::new(),
+        }
+    }
+
+    fn insert_key(&mut self, key: &str, value: Self) -> Option<Self> {
+        match self {
+            Self::Object(map) => map.insert(key.to_string(), value),
+            _ => None,
+        }
+    }
+
+    fn remove_key(&mut self, key: &str) -> Option<Self> {
+        match self {
+            Self::Object(map) => map.remove(key),
+            _ => None,
+        }
+    }
+}
+
+#[cfg(feature = "serde_yaml")]
+impl JsonLike for serde_yaml::Value {
+    fn is_null(&self) -> bool {
+        self.is_null()
+    }
+
+    fn as_bool(&self) -> Option<bool> {
+        self.as_bool()
+    }
+
+    fn as_number(&self) -> Option<Number> {
+        match self {
+            serde_yaml::Value::Number(n) => {
+                if let Some(i) = n.as_i64() {
+                    Some(Number::from(i))
+                } else if let Some(u) = n.as_u64() {
+                    Some(Number::from(u))
+                } else {
+                    n.as_f64().and_then(Number::from_f64)
+                }
+            }
+            _ => None,
+        }
+    }
+
+    fn as_str(&self) -> Option<&str> {
+        self.as_str()
+    }
+
+    fn as_array(&self) -> Option<&Vec<Self>> {
+        self.as_sequence()
+    }
+
+    fn as_array_mut(&mut self) -> Option<&mut Vec<Self>> {
+        self.as_sequence_mut()
+    }
+
+    fn is_object(&self) -> bool {
+        self.is_mapping()
+    }
+
+    fn get_key(&self, key: &str) -> Option<&Self> {
+        self.as_mapping().and_then(|map| map.get(key))
+    }
+
+    fn get_key_mut(&mut self, key: &str) -> Option<&mut Self> {
+        self.as_mapping_mut().and_then(|map| map.get_mut(key))
+    }
+
+    fn keys(&self) -> Vec<&str> {
+        self.as_mapping()
+            .map(|map| map.keys().filter_map(serde_yaml::Value::as_str).collect())
+            .unwrap_or_default()
+    }
+
+    fn insert_key(&mut self, key: &str, value: Self) -> Option<Self> {
+        self.as_mapping_mut()
+            .and_then(|map| map.insert(serde_yaml::Value::String(key.to_string()), value))
+    }
+
+    fn remove_key(&mut self, key: &str) -> Option<Self> {
+        self.as_mapping_mut().and_then(|map| map.remove(key))
+    }
+}
+
+// TOML has no null, datetimes are neither strings nor numbers.
+#[cfg(feature = "toml")]
+impl JsonLike for toml::Value {
+    fn is_null(&self) -> bool {
+        false
+    }
+
+    fn as_bool(&self) -> Option<bool> {
+        self.as_bool()
+    }
+
+    fn as_number(&self) -> Option<Number> {
+        match self {
+            toml::Value::Integer(i) => Some(Number::from(*i)),
+            toml::Value::Float(f) => Number::from_f64(*f),
+            _ => None,
+        }
+    }
+
+    fn as_str(&self) -> Option<&str> {
+        self.as_str()
+    }
+
+    fn as_array(&self) -> Option<&Vec<Self>> {
+        self.as_array()
+    }
+
+    fn as_array_mut(&mut self) -> Option<&mut Vec<Self>> {
+        self.as_array_mut()
+    }
+
+    fn is_object(&self) -> bool {
+        self.is_table()
+    }
+
+    fn get_key(&self, key: &str) -> Option<&Self> {
+        self.as_table().and_then(|map| map.get(key))
+    }
+
+    fn get_key_mut(&mut self, key: &str) -> Option<&mut Self> {
+        self.as_table_mut().and_then(|map| map.get_mut(key))
+    }
+
+    fn keys(&self) -> Vec<&str> {
+        self.as_table()
+            .map(|map| map.keys().map(String::as_str).collect())
+            .unwrap_or_default()
+    }
+
+    fn insert_key(&mut self, key: &str, value: Self) -> Option<Self> {
+        self.as_table_mut()
+            .and_then(|map| map.insert(key.to_string(), value))
+    }
+
+    fn remove_key(&mut self, key: &str) -> Option<Self> {
+        self.as_table_mut().and_then(|map| map.remove(key))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::cmp::Ordering;
+
+    use crate::{json_path::JsonPath, value::partial_cmp};
+
+    use super::*;
+
+    // Runs the same lookups, mutations and comparisons on any JsonLike parsed from JSON text.
+    fn check<V: JsonLike + std::fmt::Debug>(parse: impl Fn(&str) -> V) {
+        let mut value = parse(r#"{"a": [1, "x", {"b": true}], "c": 2.5}"#);
+        let tests = vec![
+            ("$.a[0]", Some("1")),
+            ("$.a[#-1].b", Some("true")),
+            ("$.a[1]", Some("\"x\"")),
+            ("$.c", Some("2.5")),
+            ("$.a[3]", None),
+            ("$.a.b", None),
+        ];
+        for (path, expected) in tests {
+            let path: JsonPath = path.try_into().unwrap();
+            let expected = expected.map(&parse);
+            assert_eq!(
+                path.find(&value),
+                expected.as_ref(),
+                "expected {} to be {:?}",
+                path,
+                expected
+            );
+        }
+
+        let path: JsonPath = "$.a.b".try_into().unwrap();
+        assert_eq!(
+            path.find_explain(&value).unwrap_err().to_string(),
+            "expected object at `$.a`, found array of length 3"
+        );
+        let path: JsonPath = "$.aa".try_into().unwrap();
+        assert_eq!(path.find_explain(&value).unwrap_err().suggestions, ["a"]);
+
+        let path: JsonPath = "$.a[0]".try_into().unwrap();
+        assert!(path.set(&mut value, parse("3")).is_ok());
+        let path: JsonPath = "$.d".try_into().unwrap();
+        assert!(path.insert(&mut value, parse("[]")).is_ok());
+        assert!(path.insert(&mut value, parse("[]")).is_err());
+        let path: JsonPath = "$.d[0]".try_into().unwrap();
+        assert!(path.insert(&mut value, parse("\"y\"")).is_ok());
+        let path: JsonPath = "$.a[1]".try_into().unwrap();
+        assert!(path.remove(&mut value).is_ok());
+        assert_eq!(
+            value,
+            parse(r#"{"a": [3, {"b": true}], "c": 2.5, "d": ["y"]}"#)
+        );
+
+        let tests = vec![
+            ("1", "2", Some(Ordering::Less)),
+            ("2.5", "2", Some(Ordering::Greater)),
+            ("true", "1", Some(Ordering::Equal)),
+            ("\"10\"", "9", Some(Ordering::Greater)),
+            ("\"a\"", "\"b\"", Some(Ordering::Less)),
+            ("[1]", "\"b\"", Some(Ordering::Greater)),
+            ("[1, 2]", "[1, 3]", Some(Ordering::Less)),
+            (
+                r#"{"a": [1], "b": 2}"#,
+                r#"{"a": [1]}"#,
+                Some(Ordering::Greater),
+            ),
+            ("[]", "{}", Some(Ordering::Less)),
+        ];
+        for (a, b, expected) in tests {
+            assert_eq!(
+                partial_cmp(&parse(a), &parse(b)),
+                expected,
+                "expected {}.partial_cmp({}) to be {:?}",
+                a,
+                b,
+                expected
+            );
+        }
+    }
+
+    #[test]
+    fn serde_json() {
+        check(|s| serde_json::from_str::<Value>(s).unwrap());
+    }
+
+    #[cfg(feature = "simd-json")]
+    #[test]
+    fn simd_json() {
+        check(|s| simd_json::to_owned_value(&mut s.as_bytes().to_vec()).unwrap());
+    }
+
+    #[cfg(feature = "serde_yaml")]
+    #[test]
+    fn serde_yaml() {
+        check(|s| serde_yaml::from_str::<serde_yaml::Value>(s).unwrap());
+    }
+
+    // Converted by hand, with arbitrary_precision serde_json serializes numbers as maps.
+    #[cfg(feature = "toml")]
+    fn to_toml(value: &Value) -> toml::Value {
+        match value {
+            Value::Bool(b) => toml::Value::Boolean(*b),
+            Value::Number(n) => match n.as_i64() {
+                Some(i) => toml::Value::Integer(i),
+                None => toml::Value::Float(n.as_f64().unwrap()),
+            },
+            Value::String(s) => toml::Value::String(s.clone()),
+            Value::Array(array) => toml::Value::Array(array.iter().map(to_toml).collect()),
+            Value::Object(map) => {
+                toml::Value::Table(map.iter().map(|(k, v)| (k.clone(), to_toml(v))).collect())
+            }
+            Value::Null => unreachable!("TOML has no null"),
+        }
+    }
+
+    #[cfg(feature = "toml")]
+    #[test]
+    fn toml() {
+        check(|s| to_toml(&serde_json::from_str(s).unwrap()));
+    }
+}
@@ -1,10 +1,20 @@
 use std::{
+    borrow::Cow,
+    fmt,
     ops::{Deref, DerefMut},
     str::FromStr,
 };
 
+use serde::{Deserialize, Deserializer};
 use serde_json::Value;
 
+use crate::{
+    entry::{self, Entry},
+    explain::{self, FindError},
+    json_like::JsonLike,
+    relocate,
+};
+
 #[derive(Debug, Default, Clone, PartialEq, Eq, PartialOrd, Ord)]
 pub struct JsonPath(Vec<JsonPathElement>);
 
@@ -14,11 +24,52 @@ pub enum JsonPathElement {
     Index(JsonPathIndex),
 }
 
-impl ToString for JsonPathElement {
-    fn to_string(&self) -> String {
+impl JsonPathElement {
+    /// Borrows the element without copying its key.
+    #[inline]
+    pub fn borrowed(&self) -> JsonPathElementRef<'_> {
+        match self {
+            JsonPathElement::Field(key) => JsonPathElementRef::Field(Cow::Borrowed(key)),
+            JsonPathElement::Index(i) => JsonPathElementRef::Index(*i),
+        }
+    }
+}
+
+impl fmt::Display for JsonPathElement {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            JsonPathElement::Field(v) => f.write_str(v),
+            JsonPathElement::Index(i) => i.fmt(f),
+        }
+    }
+}
+
+/// A JsonPath which borrows its keys from the parsed query.
+///
+/// Lookups and mutations with a JsonPathRef don't allocate.
+#[derive(Debug, Default, Clone, PartialEq, Eq, PartialOrd, Ord)]
+pub struct JsonPathRef<'a>(Vec<JsonPathElementRef<'a>>);
+
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord)]
+pub enum JsonPathElementRef<'a> {
+    Field(Cow<'a, str>), // key of an object, owned only if the query escaped characters in it
+    Index(JsonPathIndex),
+}
+
+impl<'a> JsonPathElementRef<'a> {
+    /// Borrows the element without copying its key.
+    #[inline]
+    pub fn borrowed(&self) -> JsonPathElementRef<'_> {
         match self {
-            JsonPathElement::Field(v) => v.clone(),
-            JsonPathElement::Index(i) => i.to_string(),
+            JsonPathElementRef::Field(key) => JsonPathElementRef::Field(Cow::Borrowed(key)),
+            JsonPathElementRef::Index(i) => JsonPathElementRef::Index(*i),
+        }
+    }
+
+    pub fn into_owned(self) -> JsonPathElement {
+        match self {
+            JsonPathElementRef::Field(key) => JsonPathElement::Field(key.into_owned()),
+            JsonPathElementRef::Index(i) => JsonPathElement::Index(i),
         }
     }
 }
@@ -29,23 +80,31 @@ pub enum JsonPathIndex {
     NthRight(usize), // # represents the length of the array, #-1 is the last element
 }
 
-impl ToString for JsonPathIndex {
-    fn to_string(&self) -> String {
+impl From<usize> for JsonPathIndex {
+    #[inline]
+    fn from(i: usize) -> Self {
+        JsonPathIndex::NthLefth(i)
+    }
+}
+
+impl fmt::Display for JsonPathIndex {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         match self {
-            JsonPathIndex::NthLefth(i) => i.to_string(),
-            JsonPathIndex::NthRight(i) => format!("#-{i}"),
+            JsonPathIndex::NthLefth(i) => write!(f, "{i}"),
+            JsonPathIndex::NthRight(i) => write!(f, "#-{i}"),
         }
     }
 }
 
 const ROOT: char = '$';
 const DOT: char = '.';
+const QUOTE: char = '"';
 const BEGIN_INDEX: char = '[';
 const CLOSE_INDEX: char = ']';
 const BEGIN_REVERSE_INDEX: char = '#';
 
 // Get element of arrray from right side without panic.
-fn get_right(array: &Vec<Value>, i: usize) -> Option<&Value> {
+fn get_right<V>(array: &[V], i: usize) -> Option<&V> {
     if array.len() < i {
         None
     } else {
@@ -54,7 +113,7 @@ fn get_right(array: &Vec<Value>, i: usize) -> Option<&Value> {
 }
 
 // Get mutable element of array from right side without panic.
-fn get_right_mut(array: &mut Vec<Value>, i: usize) -> Option<&mut Value> {
+fn get_right_mut<V>(array: &mut [V], i: usize) -> Option<&mut V> {
     if array.len() < i {
         None
     } else {
@@ -63,6 +122,341 @@ fn get_right_mut(array: &mut Vec<Value>, i: usize) -> Option<&mut Value> {
     }
 }
 
+pub(crate) fn step<'v, V: JsonLike>(value: &'v V, e: &JsonPathElementRef) -> Option<&'v V> {
+    match e {
+        JsonPathElementRef::Field(key) => value.get_key(key),
+        JsonPathElementRef::Index(JsonPathIndex::NthLefth(i)) => {
+            value.as_array().and_then(|a| a.get(*i))
+        }
+        JsonPathElementRef::Index(JsonPathIndex::NthRight(i)) => {
+            value.as_array().and_then(|a| get_right(a, *i))
+        }
+    }
+}
+
+pub(crate) fn step_mut<'v, V: JsonLike>(
+    value: &'v mut V,
+    e: &JsonPathElementRef,
+) -> Option<&'v mut V> {
+    match e {
+        JsonPathElementRef::Field(key) => value.get_key_mut(key),
+        JsonPathElementRef::Index(JsonPathIndex::NthLefth(i)) => {
+            value.as_array_mut().and_then(|a| a.get_mut(*i))
+        }
+        JsonPathElementRef::Index(JsonPathIndex::NthRight(i)) => {
+            value.as_array_mut().and_then(|a| get_right_mut(a, *i))
+        }
+    }
+}
+
+/// A path given as a `JsonPath` or a `JsonPathRef`, accepted by functions which take either.
+pub trait JsonPathLike {
+    /// Returns the number of elements, 0 for the root.
+    fn depth(&self) -> usize;
+
+    /// Returns the element at position i, None after the last element.
+    fn element(&self, i: usize) -> Option<JsonPathElementRef<'_>>;
+
+    /// Copies the path into a `JsonPath`.
+    fn to_path(&self) -> JsonPath {
+        JsonPath(elements(self).map(JsonPathElementRef::into_owned).collect())
+    }
+}
+
+impl JsonPathLike for JsonPath {
+    #[inline]
+    fn depth(&self) -> usize {
+        self.len()
+    }
+
+    #[inline]
+    fn element(&self, i: usize) -> Option<JsonPathElementRef<'_>> {
+        self.get(i).map(JsonPathElement::borrowed)
+    }
+}
+
+impl<'a> JsonPathLike for JsonPathRef<'a> {
+    #[inline]
+    fn depth(&self) -> usize {
+        self.len()
+    }
+
+    #[inline]
+    fn element(&self, i: usize) -> Option<JsonPathElementRef<'_>> {
+        self.get(i).map(JsonPathElementRef::borrowed)
+    }
+}
+
+// Lookup and mutation shared by JsonPath and JsonPathRef.
+
+// Borrows the elements of path.
+pub(crate) fn elements<P: JsonPathLike + ?Sized>(
+    path: &P,
+) -> impl Iterator<Item = JsonPathElementRef<'_>> + Clone {
+    (0..path.depth()).filter_map(|i| path.element(i))
+}
+
+// Splits path into its last element and the elements before it, None for the root.
+pub(crate) fn split_last<P: JsonPathLike + ?Sized>(
+    path: &P,
+) -> Option<(
+    JsonPathElementRef<'_>,
+    impl Iterator<Item = JsonPathElementRef<'_>>,
+)> {
+    let n = path.depth().checked_sub(1)?;
+    Some((path.element(n)?, elements(path).take(n)))
+}
+
+pub(crate) fn find<'v, 'e, V: JsonLike>(
+    value: &'v V,
+    elements: impl IntoIterator<Item = JsonPathElementRef<'e>>,
+) -> Option<&'v V> {
+    elements
+        .into_iter()
+        .try_fold(value, |value, e| step(value, &e))
+}
+
+pub(crate) fn find_mut<'v, 'e, V: JsonLike>(
+    value: &'v mut V,
+    elements: impl IntoIterator<Item = JsonPathElementRef<'e>>,
+) -> Option<&'v mut V> {
+    elements
+        .into_iter()
+        .try_fold(value, |value, e| step_mut(value, &e))
+}
+
+/// The result of a successful mutation.
+#[derive(Debug, Clone, PartialEq)]
+pub struct MutationOutcome<V = Value> {
+    pub previous: Option<V>, // value which was overwritten or removed, None if there was none
+}
+
+/// The reason a mutation left the value unchanged.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MutationError {
+    MissingParent, // the value containing the last element doesn't exist, or the path is the root
+    MissingKey,    // the key to replace or remove doesn't exist
+    TypeMismatch,  // the parent is not an object for a key or not an array for an index
+    OutOfRange,    // the index is outside of the array
+    ExistingKey,   // the key to insert already exists
+    InsideSource,  // the destination of a move or swap is inside the moved value
+}
+
+impl fmt::Display for MutationError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            MutationError::MissingParent => "parent of path doesn't exist",
+            MutationError::MissingKey => "key doesn't exist",
+            MutationError::TypeMismatch => "parent is not an object or array",
+            MutationError::OutOfRange => "index out of range",
+            MutationError::ExistingKey => "key already exists",
+            MutationError::InsideSource => "destination is inside the source",
+        })
+    }
+}
+
+impl std::error::Error for MutationError {}
+
+// Resolves index against an array of len elements, the position may equal len.
+pub(crate) fn position(index: JsonPathIndex, len: usize) -> Result<usize, MutationError> {
+    match index {
+        JsonPathIndex::NthLefth(i) if i <= len => Ok(i),
+        JsonPathIndex::NthRight(i) if i <= len => Ok(len - i),
+        _ => Err(MutationError::OutOfRange),
+    }
+}
+
+// Resolves index to an existing element of target.
+fn element_mut<V: JsonLike>(
+    target: &mut V,
+    index: JsonPathIndex,
+) -> Result<(&mut Vec<V>, usize), MutationError> {
+    let target = target.as_array_mut().ok_or(MutationError::TypeMismatch)?;
+    match position(index, target.len())? {
+        i if i < target.len() => Ok((target, i)),
+        _ => Err(MutationError::OutOfRange),
+    }
+}
+
+fn object<V: JsonLike>(target: &mut V) -> Result<&mut V, MutationError> {
+    if target.is_object() {
+        Ok(target)
+    } else {
+        Err(MutationError::TypeMismatch)
+    }
+}
+
+fn parent_mut<'v, 'e, V: JsonLike>(
+    value: &'v mut V,
+    rest: impl IntoIterator<Item = JsonPathElementRef<'e>>,
+) -> Result<&'v mut V, MutationError> {
+    find_mut(value, rest).ok_or(MutationError::MissingParent)
+}
+
+// Inserts v into target at last, array elements from the position on move to the right.
+pub(crate) fn insert<V: JsonLike>(
+    target: &mut V,
+    last: &JsonPathElementRef,
+    v: V,
+) -> Result<Option<V>, MutationError> {
+    match last {
+        JsonPathElementRef::Index(index) => {
+            let target = target.as_array_mut().ok_or(MutationError::TypeMismatch)?;
+            let i = position(*index, target.len())?;
+            target.insert(i, v);
+        }
+        JsonPathElementRef::Field(key) => {
+            let target = object(target)?;
+            if target.get_key(key).is_some() {
+                return Err(MutationError::ExistingKey);
+            }
+            target.insert_key(key, v);
+        }
+    }
+    Ok(None)
+}
+
+// Sets the value at last of target to v, keys are created but array elements must exist.
+pub(crate) fn set<V: JsonLike>(
+    target: &mut V,
+    last: &JsonPathElementRef,
+    v: V,
+) -> Result<Option<V>, MutationError> {
+    match last {
+        JsonPathElementRef::Index(index) => {
+            let (target, i) = element_mut(target, *index)?;
+            Ok(Some(std::mem::replace(&mut target[i], v)))
+        }
+        JsonPathElementRef::Field(key) => Ok(object(target)?.insert_key(key, v)),
+    }
+}
+
+// Replaces the existing value at last of target with v.
+fn replace<V: JsonLike>(
+    target: &mut V,
+    last: &JsonPathElementRef,
+    v: V,
+) -> Result<Option<V>, MutationError> {
+    let target = match last {
+        JsonPathElementRef::Index(index) => {
+            let (target, i) = element_mut(target, *index)?;
+            &mut target[i]
+        }
+        JsonPathElementRef::Field(key) => object(target)?
+            .get_key_mut(key)
+            .ok_or(MutationError::MissingKey)?,
+    };
+    Ok(Some(std::mem::replace(target, v)))
+}
+
+// Removes the value at last of target, following array elements move to the left.
+pub(crate) fn remove<V: JsonLike>(
+    target: &mut V,
+    last: &JsonPathElementRef,
+) -> Result<Option<V>, MutationError> {
+    match last {
+        JsonPathElementRef::Index(index) => {
+            let (target, i) = element_mut(target, *index)?;
+            Ok(Some(target.remove(i)))
+        }
+        JsonPathElementRef::Field(key) => object(target)?
+            .remove_key(key)
+            .map(Some)
+            .ok_or(MutationError::MissingKey),
+    }
+}
+
+fn fmt_path<'e>(
+    f: &mut fmt::Formatter<'_>,
+    elements: impl IntoIterator<Item = JsonPathElementRef<'e>>,
+) -> fmt::Result {
+    write!(f, "{ROOT}")?;
+    for e in elements {
+        match e {
+            JsonPathElementRef::Field(key) if key.chars().all(char::is_alphabetic) => {
+                write!(f, "{DOT}{key}")?
+            }
+            JsonPathElementRef::Field(key) => {
+                write!(f, "{DOT}{}", Value::String(key.into_owned()))?
+            }
+            JsonPathElementRef::Index(i) => write!(f, "{BEGIN_INDEX}{i}{CLOSE_INDEX}")?,
+        }
+    }
+    Ok(())
+}
+
+fn insert_at<V: JsonLike, P: JsonPathLike + ?Sized>(
+    path: &P,
+    value: &mut V,
+    v: V,
+) -> Result<MutationOutcome<V>, MutationError> {
+    let (last, rest) = split_last(path).ok_or(MutationError::MissingParent)?;
+    let target = parent_mut(value, rest)?;
+    insert(target, &last, v).map(|previous| MutationOutcome { previous })
+}
+
+fn replace_at<V: JsonLike, P: JsonPathLike + ?Sized>(
+    path: &P,
+    value: &mut V,
+    v: V,
+) -> Result<MutationOutcome<V>, MutationError> {
+    let Some((last, rest)) = split_last(path) else {
+        let previous = Some(std::mem::replace(value, v));
+        return Ok(MutationOutcome { previous });
+    };
+    let target = parent_mut(value, rest)?;
+    replace(target, &last, v).map(|previous| MutationOutcome { previous })
+}
+
+fn set_at<V: JsonLike, P: JsonPathLike + ?Sized>(
+    path: &P,
+    value: &mut V,
+    v: V,
+) -> Result<MutationOutcome<V>, MutationError> {
+    let (last, rest) = split_last(path).ok_or(MutationError::MissingParent)?;
+    let target = parent_mut(value, rest)?;
+    set(target, &last, v).map(|previous| MutationOutcome { previous })
+}
+
+fn remove_at<V: JsonLike, P: JsonPathLike + ?Sized>(
+    path: &P,
+    value: &mut V,
+) -> Result<MutationOutcome<V>, MutationError> {
+    let (last, rest) = split_last(path).ok_or(MutationError::MissingParent)?;
+    let target = parent_mut(value, rest)?;
+    remove(target, &last).map(|previous| MutationOutcome { previous })
+}
+
+fn entry_at<'v, V: JsonLike, P: JsonPathLike + ?Sized>(
+    path: &P,
+    value: &'v mut V,
+) -> Option<Entry<'v, V>> {
+    let (last, rest) = split_last(path)?;
+    let target = find_mut(value, rest)?;
+    entry::entry(target, &last)
+}
+
+// Resolves the #-N indices of path against value, see JsonPath::normalize.
+fn normalize_at<'p, V: JsonLike, P: JsonPathLike + ?Sized>(
+    path: &'p P,
+    value: &V,
+) -> Option<Vec<JsonPathElementRef<'p>>> {
+    let mut value = Some(value);
+    let mut out = Vec::with_capacity(path.depth());
+    for e in elements(path) {
+        let e = match e {
+            JsonPathElementRef::Index(JsonPathIndex::NthRight(i)) => {
+                let len = value?.as_array()?.len();
+                JsonPathElementRef::Index(JsonPathIndex::NthLefth(len.checked_sub(i)?))
+            }
+            e => e,
+        };
+        value = value.and_then(|value| step(value, &e));
+        out.push(e);
+    }
+    Some(out)
+}
+
 impl Deref for JsonPath {
     type Target = Vec<JsonPathElement>;
 
@@ -78,213 +472,503 @@ impl DerefMut for JsonPath {
     }
 }
 
+impl fmt::Display for JsonPath {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        fmt_path(f, self.iter().map(JsonPathElement::borrowed))
+    }
+}
+
+impl From<Vec<JsonPathElement>> for JsonPath {
+    #[inline]
+    fn from(elements: Vec<JsonPathElement>) -> Self {
+        JsonPath(elements)
+    }
+}
+
 impl JsonPath {
-    pub fn find<'a>(&self, value: &'a Value) -> Option<&'a Value> {
-        let mut value = value;
-        for e in self.iter() {
-            let sub = match e {
-                JsonPathElement::Field(key) => value.get(key),
-                JsonPathElement::Index(JsonPathIndex::NthLefth(i)) => value.get(i),
-                JsonPathElement::Index(JsonPathIndex::NthRight(i)) => {
-                    value.as_array().and_then(|a| get_right(a, *i))
-                }
-            };
-            if let Some(sub) = sub {
-                value = sub;
-            } else {
-                return None;
-            }
+    pub fn find<'a, V: JsonLike>(&self, value: &'a V) -> Option<&'a V> {
+        find(value, elements(self))
+    }
+
+    pub fn find_mut<'a, V: JsonLike>(&self, value: &'a mut V) -> Option<&'a mut V> {
+        find_mut(value, elements(self))
+    }
+
+    /// Finds the value like `find`, on failure explains which element couldn't be followed.
+    pub fn find_explain<'a, V: JsonLike>(&self, value: &'a V) -> Result<&'a V, FindError> {
+        explain::find_explain(value, elements(self))
+    }
+
+    /// Inserts v at the path, fails if the key exists. Array elements from the index on move
+    /// to the right, the index may equal the length of the array.
+    pub fn insert<V: JsonLike>(
+        &self,
+        value: &mut V,
+        v: V,
+    ) -> Result<MutationOutcome<V>, MutationError> {
+        insert_at(self, value, v)
+    }
+
+    /// Replaces the existing value at the path with v, the root can be replaced too.
+    pub fn replace<V: JsonLike>(
+        &self,
+        value: &mut V,
+        v: V,
+    ) -> Result<MutationOutcome<V>, MutationError> {
+        replace_at(self, value, v)
+    }
+
+    /// Sets the value at the path to v, keys are created if they don't exist.
+    pub fn set<V: JsonLike>(
+        &self,
+        value: &mut V,
+        v: V,
+    ) -> Result<MutationOutcome<V>, MutationError> {
+        set_at(self, value, v)
+    }
+
+    /// Removes the value at the path, following array elements move to the left.
+    pub fn remove<V: JsonLike>(&self, value: &mut V) -> Result<MutationOutcome<V>, MutationError> {
+        remove_at(self, value)
+    }
+
+    /// Moves the value at the path to dest. Keys at dest are set, array elements are inserted.
+    ///
+    /// Unlike a JSON Patch move, indices in dest refer to the document before the move: moving
+    /// `$.a[0]` to `$.a[2]` in `[1, 2, 3]` gives `[2, 1, 3]`, the value is inserted before the
+    /// element which was at index 2. JSON Patch resolves dest after the removal and gives
+    /// `[2, 3, 1]`.
+    pub fn move_to<V: JsonLike, P: JsonPathLike + ?Sized>(
+        &self,
+        dest: &P,
+        value: &mut V,
+    ) -> Result<MutationOutcome<V>, MutationError> {
+        relocate::move_to(self, dest, value)
+    }
+
+    /// Copies the value at the path to dest, see `move_to`.
+    pub fn copy_to<V: JsonLike + Clone, P: JsonPathLike + ?Sized>(
+        &self,
+        dest: &P,
+        value: &mut V,
+    ) -> Result<MutationOutcome<V>, MutationError> {
+        relocate::copy_to(self, dest, value)
+    }
+
+    /// Exchanges the values at a and b, neither may be inside the other.
+    pub fn swap<V, A, B>(a: &A, b: &B, value: &mut V) -> Result<(), MutationError>
+    where
+        V: JsonLike + Clone,
+        A: JsonPathLike + ?Sized,
+        B: JsonPathLike + ?Sized,
+    {
+        relocate::swap(a, b, value)
+    }
+
+    /// Returns the entry at the end of the path, None if its parent can't hold a value there.
+    pub fn entry<'a, V: JsonLike>(&self, value: &'a mut V) -> Option<Entry<'a, V>> {
+        entry_at(self, value)
+    }
+
+    /// Borrows the path without copying its keys.
+    pub fn borrowed(&self) -> JsonPathRef<'_> {
+        JsonPathRef(elements(self).collect())
+    }
+}
+
+// Building and comparing paths.
+impl JsonPath {
+    /// Returns the path to the root value, start of the fluent builder.
+    ///
+    /// `JsonPath::root().field("a").index(0)` is the path `$.a[0]`.
+    pub fn root() -> Self {
+        JsonPath::default()
+    }
+
+    /// Appends a key, see `root`.
+    pub fn field(mut self, key: impl Into<String>) -> Self {
+        self.push_field(key);
+        self
+    }
+
+    /// Appends an index, see `root`.
+    pub fn index(mut self, index: impl Into<JsonPathIndex>) -> Self {
+        self.push_index(index);
+        self
+    }
+
+    /// Appends the index `#-n`, n elements from the end of an array.
+    pub fn index_from_end(self, n: usize) -> Self {
+        self.index(JsonPathIndex::NthRight(n))
+    }
+
+    pub fn push_field(&mut self, key: impl Into<String>) -> &mut Self {
+        self.0.push(JsonPathElement::Field(key.into()));
+        self
+    }
+
+    pub fn push_index(&mut self, index: impl Into<JsonPathIndex>) -> &mut Self {
+        self.0.push(JsonPathElement::Index(index.into()));
+        self
+    }
+
+    /// Returns the path with other appended, other is relative to self.
+    pub fn join(&self, other: &JsonPath) -> JsonPath {
+        JsonPath(self.iter().chain(other.iter()).cloned().collect())
+    }
+
+    /// Returns the path to the value containing self, None for the root.
+    pub fn parent(&self) -> Option<JsonPath> {
+        self.split_last().map(|(_, rest)| JsonPath(rest.to_vec()))
+    }
+
+    /// Returns the last element, None for the root.
+    pub fn last(&self) -> Option<&JsonPathElement> {
+        self.0.last()
+    }
+
+    /// Returns whether prefix is the path or a path containing it.
+    pub fn starts_with(&self, prefix: &JsonPath) -> bool {
+        self.0.starts_with(&prefix.0)
+    }
+
+    /// Returns the path relative to prefix, None if it doesn't start with prefix.
+    pub fn strip_prefix(&self, prefix: &JsonPath) -> Option<JsonPath> {
+        self.0
+            .strip_prefix(prefix.0.as_slice())
+            .map(|rest| JsonPath(rest.to_vec()))
+    }
+
+    /// Returns the longest path both paths start with.
+    pub fn common_prefix(&self, other: &JsonPath) -> JsonPath {
+        let len = self
+            .iter()
+            .zip(other.iter())
+            .take_while(|(a, b)| a == b)
+            .count();
+        JsonPath(self[..len].to_vec())
+    }
+
+    /// Resolves `#-N` indices to absolute indices against value.
+    ///
+    /// Returns None if a `#-N` index can't be resolved, because there is no array at its
+    /// position or the array is shorter than N. `[#]` resolves to the length of its array.
+    pub fn normalize<V: JsonLike>(&self, value: &V) -> Option<JsonPath> {
+        let elements = normalize_at(self, value)?;
+        Some(JsonPath(
+            elements
+                .into_iter()
+                .map(JsonPathElementRef::into_owned)
+                .collect(),
+        ))
+    }
+}
+
+impl<'a> Deref for JsonPathRef<'a> {
+    type Target = Vec<JsonPathElementRef<'a>>;
+
+    #[inline]
+    fn deref(&self) -> &Self::Target {
+        &self.0
+    }
+}
+
+impl<'a> DerefMut for JsonPathRef<'a> {
+    fn deref_mut(&mut self) -> &mut Self::Target {
+        &mut self.0
+    }
+}
+
+impl<'a> fmt::Display for JsonPathRef<'a> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        fmt_path(f, self.iter().map(JsonPathElementRef::borrowed))
+    }
+}
+
+impl<'a> From<Vec<JsonPathElementRef<'a>>> for JsonPathRef<'a> {
+    #[inline]
+    fn from(elements: Vec<JsonPathElementRef<'a>>) -> Self {
+        JsonPathRef(elements)
+    }
+}
+
+impl<'a> JsonPathRef<'a> {
+    /// Parses a query, keys are borrowed from the query unless they contain escapes.
+    pub fn parse(query: &'a str) -> Result<Self, &'static str> {
+        Elements::new(query)
+            .collect::<Result<_, _>>()
+            .map(JsonPathRef)
+    }
+
+    pub fn into_owned(self) -> JsonPath {
+        JsonPath(
+            self.0
+                .into_iter()
+                .map(JsonPathElementRef::into_owned)
+                .collect(),
+        )
+    }
+
+    pub fn find<'v, V: JsonLike>(&self, value: &'v V) -> Option<&'v V> {
+        find(value, elements(self))
+    }
+
+    pub fn find_mut<'v, V: JsonLike>(&self, value: &'v mut V) -> Option<&'v mut V> {
+        find_mut(value, elements(self))
+    }
+
+    /// Finds the value like `find`, see `JsonPath::find_explain`.
+    pub fn find_explain<'v, V: JsonLike>(&self, value: &'v V) -> Result<&'v V, FindError> {
+        explain::find_explain(value, elements(self))
+    }
+
+    /// Inserts v at the path, see `JsonPath::insert`.
+    pub fn insert<V: JsonLike>(
+        &self,
+        value: &mut V,
+        v: V,
+    ) -> Result<MutationOutcome<V>, MutationError> {
+        insert_at(self, value, v)
+    }
+
+    /// Replaces the existing value at the path with v, see `JsonPath::replace`.
+    pub fn replace<V: JsonLike>(
+        &self,
+        value: &mut V,
+        v: V,
+    ) -> Result<MutationOutcome<V>, MutationError> {
+        replace_at(self, value, v)
+    }
+
+    /// Sets the value at the path to v, see `JsonPath::set`.
+    pub fn set<V: JsonLike>(
+        &self,
+        value: &mut V,
+        v: V,
+    ) -> Result<MutationOutcome<V>, MutationError> {
+        set_at(self, value, v)
+    }
+
+    /// Removes the value at the path, see `JsonPath::remove`.
+    pub fn remove<V: JsonLike>(&self, value: &mut V) -> Result<MutationOutcome<V>, MutationError> {
+        remove_at(self, value)
+    }
+
+    /// Moves the value at the path to dest, see `JsonPath::move_to`.
+    pub fn move_to<V: JsonLike, P: JsonPathLike + ?Sized>(
+        &self,
+        dest: &P,
+        value: &mut V,
+    ) -> Result<MutationOutcome<V>, MutationError> {
+        relocate::move_to(self, dest, value)
+    }
+
+    /// Copies the value at the path to dest, see `JsonPath::move_to`.
+    pub fn copy_to<V: JsonLike + Clone, P: JsonPathLike + ?Sized>(
+        &self,
+        dest: &P,
+        value: &mut V,
+    ) -> Result<MutationOutcome<V>, MutationError> {
+        relocate::copy_to(self, dest, value)
+    }
+
+    /// Returns the entry at the end of the path, see `JsonPath::entry`.
+    pub fn entry<'v, V: JsonLike>(&self, value: &'v mut V) -> Option<Entry<'v, V>> {
+        entry_at(self, value)
+    }
+
+    /// Resolves `#-N` indices to absolute indices against value, see `JsonPath::normalize`.
+    pub fn normalize<V: JsonLike>(&self, value: &V) -> Option<JsonPathRef<'_>> {
+        normalize_at(self, value).map(JsonPathRef)
+    }
+}
+
+impl<'a> TryFrom<&'a str> for JsonPathRef<'a> {
+    type Error = &'static str;
+
+    #[inline]
+    fn try_from(value: &'a str) -> Result<Self, Self::Error> {
+        JsonPathRef::parse(value)
+    }
+}
+
+// Parses the elements of a query one at a time, stops after the first error.
+pub(crate) struct Elements<'a> {
+    query: &'a str,
+    pos: usize,
+    started: bool,
+    done: bool,
+}
+
+impl<'a> Elements<'a> {
+    pub(crate) fn new(query: &'a str) -> Self {
+        Elements {
+            query,
+            pos: 0,
+            started: false,
+            done: false,
         }
-        Some(value)
     }
 
-    pub fn find_mut<'a>(&self, value: &'a mut Value) -> Option<&'a mut Value> {
-        let mut value = value;
-        for e in self.iter() {
-            let sub = match e {
-                JsonPathElement::Field(key) => value.get_mut(key),
-                JsonPathElement::Index(JsonPathIndex::NthLefth(i)) => value.get_mut(i),
-                JsonPathElement::Index(JsonPathIndex::NthRight(i)) => {
-                    value.as_array_mut().and_then(|a| get_right_mut(a, *i))
-                }
-            };
-            if let Some(sub) = sub {
-                value = sub;
-            } else {
-                return None;
-            }
+    fn rest(&self) -> &'a str {
+        &self.query[self.pos..]
+    }
+
+    fn eat(&mut self, c: char) -> bool {
+        if self.rest().starts_with(c) {
+            self.pos += c.len_utf8();
+            true
+        } else {
+            false
         }
-        Some(value)
     }
 
-    pub fn insert<'a>(&self, value: &'a mut Value, v: Value) -> Option<&'a Value> {
-        match self.find_last_mut(value) {
-            Some((Value::Array(target), JsonPathElement::Index(JsonPathIndex::NthLefth(i)))) => {
-                let i = *i;
-                if i <= target.len() {
-                    target.insert(i, v);
-                    Some(value)
-                } else {
-                    None
-                }
-            }
-            Some((Value::Array(target), JsonPathElement::Index(JsonPathIndex::NthRight(i)))) => {
-                if target.len() < *i {
-                    return None;
-                }
-                let i = target.len() - i;
-                if i <= target.len() {
-                    target.insert(i, v);
-                    Some(value)
-                } else {
-                    None
+    fn take_while(&mut self, f: impl Fn(char) -> bool) -> &'a str {
+        let rest = self.rest();
+        let len = rest.find(|c| !f(c)).unwrap_or(rest.len());
+        self.pos += len;
+        &rest[..len]
+    }
+
+    // Parses a key in quotes, the rest starts with the opening quote.
+    fn quoted_field(&mut self) -> Result<JsonPathElementRef<'a>, &'static str> {
+        let rest = self.rest();
+        let mut escaped = false;
+        let mut has_escape = false;
+        for (i, c) in rest.char_indices().skip(1) {
+            match c {
+                _ if escaped => escaped = false,
+                '\\' => {
+                    escaped = true;
+                    has_escape = true;
                 }
-            }
-            Some((Value::Object(target), JsonPathElement::Field(key))) => {
-                if target.contains_key(key) {
-                    None
-                } else {
-                    target.insert(key.clone(), v);
-                    Some(value)
+                QUOTE => {
+                    self.pos += i + 1;
+                    let key = if has_escape {
+                        Cow::Owned(serde_json::from_str(&rest[..=i]).map_err(|_| "invalid escape")?)
+                    } else {
+                        Cow::Borrowed(&rest[1..i])
+                    };
+                    return Ok(JsonPathElementRef::Field(key));
                 }
+                _ => {}
             }
-            _ => None,
         }
+        Err("expected \"")
     }
 
-    pub fn replace<'a>(&self, value: &'a mut Value, v: Value) -> Option<&'a Value> {
-        if let Some(target) = self.find_mut(value) {
-            *target = v;
-            Some(value)
-        } else {
-            None
+    // Parses the root, returns the element if the query is only an index into the root.
+    fn start(&mut self) -> Option<Result<JsonPathElementRef<'a>, &'static str>> {
+        self.started = true;
+        if self.eat(ROOT) {
+            return None;
         }
+        if !self.rest().starts_with(char::is_numeric) {
+            return Some(Err("expected $ or numeric"));
+        }
+        // A number alone is an index into the root
+        let field = self.take_while(char::is_numeric);
+        self.done = true;
+        Some(index(field).map(|i| JsonPathElementRef::Index(JsonPathIndex::NthLefth(i))))
     }
 
-    pub fn set<'a>(&self, value: &'a mut Value, v: Value) -> Option<&'a Value> {
-        match self.find_last_mut(value) {
-            Some((Value::Array(target), JsonPathElement::Index(JsonPathIndex::NthLefth(i)))) => {
-                if let Some(target) = target.get_mut(*i) {
-                    *target = v;
-                    Some(value)
-                } else {
-                    None
-                }
+    /// Consumes `[*]` if it is the next element, a wildcard only `stream::PathPattern` accepts.
+    pub(crate) fn eat_wildcard(&mut self) -> bool {
+        if !self.started {
+            if !self.rest().starts_with(ROOT) {
+                return false;
             }
-            Some((Value::Array(target), JsonPathElement::Index(JsonPathIndex::NthRight(i)))) => {
-                if let Some(target) = get_right_mut(target, *i) {
-                    *target = v;
-                    Some(value)
-                } else {
-                    None
-                }
-            }
-            Some((Value::Object(target), JsonPathElement::Field(key))) => {
-                target.insert(key.clone(), v);
-                Some(value)
-            }
-            _ => None,
+            self.start();
+        }
+        if self.done || !self.rest().starts_with("[*]") {
+            return false;
         }
+        self.pos += "[*]".len();
+        true
     }
 
-    pub fn remove<'a>(&self, value: &'a mut Value) -> Option<&'a Value> {
-        match self.find_last_mut(value) {
-            Some((Value::Array(target), JsonPathElement::Index(JsonPathIndex::NthLefth(i)))) => {
-                if target.len() < *i {
-                    None
-                } else {
-                    target.remove(*i);
-                    Some(value)
-                }
+    fn parse_next(&mut self) -> Option<Result<JsonPathElementRef<'a>, &'static str>> {
+        if !self.started {
+            if let Some(e) = self.start() {
+                return Some(e);
             }
-            Some((Value::Array(target), JsonPathElement::Index(JsonPathIndex::NthRight(i)))) => {
-                if target.len() < *i {
-                    None
-                } else {
-                    let i = target.len() - i;
-                    target.remove(i);
-                    Some(value)
-                }
+        }
+
+        let c = self.rest().chars().next()?;
+        self.pos += c.len_utf8();
+        match c {
+            DOT if self.rest().starts_with(QUOTE) => Some(self.quoted_field()),
+            DOT => {
+                let field = self.take_while(char::is_alphabetic);
+                Some(Ok(JsonPathElementRef::Field(Cow::Borrowed(field))))
             }
-            Some((Value::Object(target), JsonPathElement::Field(key))) => {
-                if target.remove(key).is_some() {
-                    Some(value)
-                } else {
-                    None
+            BEGIN_INDEX => {
+                let reverse = self.eat(BEGIN_REVERSE_INDEX);
+                let minus = reverse && self.eat('-');
+                let field = self.take_while(char::is_numeric);
+                if !self.eat(CLOSE_INDEX) {
+                    return Some(Err("expected ]"));
                 }
+                // [#] is the length of the array
+                let i = match field {
+                    "" if reverse && !minus => Ok(0),
+                    _ => index(field),
+                };
+                Some(i.map(|i| {
+                    JsonPathElementRef::Index(if reverse {
+                        JsonPathIndex::NthRight(i)
+                    } else {
+                        JsonPathIndex::NthLefth(i)
+                    })
+                }))
+            }
+            _ => {
+                // Point at the unexpected character
+                self.pos -= c.len_utf8();
+                Some(Err("expected . or ["))
             }
-            _ => None,
         }
     }
+}
 
-    fn find_last_mut<'a>(&self, value: &'a mut Value) -> Option<(&'a mut Value, &JsonPathElement)> {
-        self.split_last().and_then(|(last, rest)| {
-            JsonPath(rest.to_vec())
-                .find_mut(value)
-                .map(|target| (target, last))
-        })
+// Parses the digits of an index, which must fit into a usize.
+fn index(digits: &str) -> Result<usize, &'static str> {
+    digits.parse().map_err(|_| "expected array index")
+}
+
+impl<'a> Iterator for Elements<'a> {
+    type Item = Result<JsonPathElementRef<'a>, &'static str>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.done {
+            return None;
+        }
+        let next = self.parse_next();
+        if let Some(Err(_)) = next {
+            self.done = true;
+        }
+        next
     }
 }
 
 impl FromStr for JsonPath {
     type Err = &'static str;
     fn from_str(value: &str) -> Result<Self, Self::Err> {
-        let mut iter = value.chars().peekable();
-
-        match iter.peek() {
-            Some(&ROOT) => _ = iter.next(),
-            Some(v) if v.is_numeric() => {
-                let mut field = String::new();
-                while let Some(c) = iter.next_if(|c| c.is_numeric()) {
-                    field.push(c);
-                }
-                let index =
-                    JsonPathElement::Index(JsonPathIndex::NthLefth(field.parse().unwrap_or(0)));
-                return Ok(JsonPath(vec![index]));
-            }
-            _ => return Err("expected $ or numeric"),
-        };
-
-        let mut path: Vec<JsonPathElement> = Vec::new();
-        loop {
-            match iter.next() {
-                Some(DOT) => {
-                    let mut field: String = String::new();
-                    while let Some(c) = iter.next_if(|c| c.is_alphabetic()) {
-                        field.push(c);
-                    }
-                    path.push(JsonPathElement::Field(field));
-                }
-                Some(BEGIN_INDEX) => {
-                    let index = if iter.next_if_eq(&BEGIN_REVERSE_INDEX).is_some() {
-                        iter.next_if_eq(&'-');
-                        JsonPathIndex::NthRight(0)
-                    } else {
-                        JsonPathIndex::NthLefth(0)
-                    };
+        parse_at(value).map_err(|(_, err)| err)
+    }
+}
 
-                    let mut field: String = String::new();
-                    while let Some(c) = iter.next_if(|c| c.is_numeric()) {
-                        field.push(c);
-                    }
-                    if iter.next_if_eq(&CLOSE_INDEX).is_none() {
-                        return Err("expected ]");
-                    }
-                    let index = match index {
-                        JsonPathIndex::NthLefth(_) => {
-                            JsonPathIndex::NthLefth(field.parse().unwrap_or(0))
-                        }
-                        JsonPathIndex::NthRight(_) => {
-                            JsonPathIndex::NthRight(field.parse().unwrap_or(0))
-                        }
-                    };
-                    path.push(JsonPathElement::Index(index));
-                }
-                None => return Ok(JsonPath(path)),
-                _ => return Err("expected . or ["),
-            }
+// Parses a query like FromStr, errors include the byte position where parsing stopped.
+pub(crate) fn parse_at(query: &str) -> Result<JsonPath, (usize, &'static str)> {
+    let mut elements = Elements::new(query);
+    let mut out = Vec::new();
+    while let Some(e) = elements.next() {
+        match e {
+            Ok(e) => out.push(e.into_owned()),
+            Err(err) => return Err((elements.pos, err)),
         }
     }
+    Ok(JsonPath(out))
 }
 
 impl TryFrom<&str> for JsonPath {
@@ -296,27 +980,146 @@ impl TryFrom<&str> for JsonPath {
     }
 }
 
+/// Why a value couldn't be extracted from a path.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ExtractError {
+    Query(String, &'static str), // the query is not a valid path
+    Missing(String),             // there is no value at the path
+    Deserialize(String, String), // the value at the path has the wrong shape, with the serde error
+    Inject(String, String),      // the value couldn't be written to the path
+    Fields(Vec<ExtractError>),   // several paths failed
+}
+
+impl fmt::Display for ExtractError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ExtractError::Query(query, err) => write!(f, "invalid path `{query}`: {err}"),
+            ExtractError::Missing(query) => write!(f, "no value at `{query}`"),
+            ExtractError::Deserialize(query, err) => write!(f, "invalid value at `{query}`: {err}"),
+            ExtractError::Inject(query, err) => write!(f, "unable to write `{query}`: {err}"),
+            ExtractError::Fields(errors) => {
+                for (i, err) in errors.iter().enumerate() {
+                    if i > 0 {
+                        f.write_str("; ")?;
+                    }
+                    err.fmt(f)?;
+                }
+                Ok(())
+            }
+        }
+    }
+}
+
+impl std::error::Error for ExtractError {}
+
+// Finds the value at query, parsing the query while walking the value.
+fn lookup<'a, V: JsonLike>(value: &'a V, query: &str) -> Result<Option<&'a V>, &'static str> {
+    let mut value = Some(value);
+    for e in Elements::new(query) {
+        let e = e?;
+        value = value.and_then(|value| step(value, &e));
+    }
+    Ok(value)
+}
+
 pub trait JsonPathQuery<'a> {
-    fn path(&'a self, query: &str) -> Result<&'a Value, &'static str>;
-    fn path_mut(&'a mut self, query: &str) -> Result<&'a mut Value, &'static str>;
+    fn path(&'a self, query: &str) -> Result<&'a Self, &'static str>;
+    fn path_mut(&'a mut self, query: &str) -> Result<&'a mut Self, &'static str>;
+
+    /// Deserializes the value at query without cloning it.
+    fn path_as<T: Deserialize<'a>>(&'a self, query: &str) -> Result<T, ExtractError>
+    where
+        Self: JsonLike,
+        &'a Self: Deserializer<'a>,
+    {
+        self.path_as_opt(query)?
+            .ok_or_else(|| ExtractError::Missing(query.to_string()))
+    }
+
+    /// Deserializes the value at query like `path_as`, None if there is no value.
+    fn path_as_opt<T: Deserialize<'a>>(&'a self, query: &str) -> Result<Option<T>, ExtractError>
+    where
+        Self: JsonLike,
+        &'a Self: Deserializer<'a>,
+    {
+        let value =
+            lookup(self, query).map_err(|err| ExtractError::Query(query.to_string(), err))?;
+        value
+            .map(T::deserialize)
+            .transpose()
+            .map_err(|err| ExtractError::Deserialize(query.to_string(), err.to_string()))
+    }
+
+    /// Deserializes a tuple of values from a tuple of queries.
+    ///
+    /// `let (name, age): (String, u64) = value.extract(("$.name", "$.age"))?;`
+    fn extract<T: Extract<'a, Self, Q>, Q>(&'a self, queries: Q) -> Result<T, ExtractError>
+    where
+        Self: Sized,
+    {
+        T::extract(self, queries)
+    }
 }
 
-impl<'a> JsonPathQuery<'a> for Value {
+// The query is parsed while walking the value, so a lookup doesn't allocate.
+impl<'a, V: JsonLike> JsonPathQuery<'a> for V {
     #[inline]
-    fn path(&'a self, query: &str) -> Result<&'a Value, &'static str> {
-        let path = JsonPath::try_from(query)?;
-        path.find(self).ok_or("unable to find path to value")
+    fn path(&'a self, query: &str) -> Result<&'a Self, &'static str> {
+        lookup(self, query)?.ok_or("unable to find path to value")
     }
 
     #[inline]
-    fn path_mut(&'a mut self, query: &str) -> Result<&'a mut Value, &'static str> {
-        let path = JsonPath::try_from(query)?;
-        path.find_mut(self).ok_or("unable to find path to value")
+    fn path_mut(&'a mut self, query: &str) -> Result<&'a mut Self, &'static str> {
+        let mut value = Some(self);
+        for e in Elements::new(query) {
+            let e = e?;
+            value = value.and_then(|value| step_mut(value, &e));
+        }
+        value.ok_or("unable to find path to value")
     }
 }
 
+/// A tuple which can be deserialized from a tuple of queries, see `JsonPathQuery::extract`.
+pub trait Extract<'a, V, Q>: Sized {
+    fn extract(value: &'a V, queries: Q) -> Result<Self, ExtractError>;
+}
+
+macro_rules! query_type {
+    ($t:ident) => {
+        &str
+    };
+}
+
+macro_rules! impl_extract {
+    ($($t:ident $q:ident),+) => {
+        impl<'a, V, $($t),+> Extract<'a, V, ($(query_type!($t),)+)> for ($($t,)+)
+        where
+            V: JsonLike + 'a,
+            &'a V: Deserializer<'a>,
+            $($t: Deserialize<'a>),+
+        {
+            fn extract(
+                value: &'a V,
+                ($($q,)+): ($(query_type!($t),)+),
+            ) -> Result<Self, ExtractError> {
+                Ok(($(value.path_as::<$t>($q)?,)+))
+            }
+        }
+    };
+}
+
+impl_extract!(A a);
+impl_extract!(A a, B b);
+impl_extract!(A a, B b, C c);
+impl_extract!(A a, B b, C c, D d);
+impl_extract!(A a, B b, C c, D d, E e);
+impl_extract!(A a, B b, C c, D d, E e, F f);
+impl_extract!(A a, B b, C c, D d, E e, F f, G g);
+impl_extract!(A a, B b, C c, D d, E e, F f, G g, H h);
+
 #[cfg(test)]
 mod tests {
+    use proptest::prelude::*;
     use serde_json::json;
 
     use super::*;
@@ -393,6 +1196,12 @@ mod tests {
             ("a", Err("expected $ or numeric")),
             ("[0]", Err("expected $ or numeric")),
             ("$0]", Err("expected . or [")),
+            ("$.\"a", Err("expected \"")),
+            ("$.\"\\x\"", Err("invalid escape")),
+            ("$[]", Err("expected array index")),
+            ("$[#-]", Err("expected array index")),
+            ("$[18446744073709551616]", Err("expected array index")),
+            ("18446744073709551616", Err("expected array index")),
         ];
         for (path, expected) in tests {
             assert_eq!(
@@ -405,6 +1214,96 @@ mod tests {
         }
     }
 
+    #[test]
+    fn to_string() {
+        let tests = vec![
+            "$",
+            "$.a",
+            "$[4][3]",
+            "$.a[#-4].b[3]",
+            "$.a[#-0]",
+            "$.\"a b\".c",
+            "$.\"a\\\"b\"",
+        ];
+        for path in tests {
+            let parsed: JsonPath = path.try_into().unwrap();
+            assert_eq!(parsed.to_string(), path);
+        }
+    }
+
+    #[test]
+    fn path_ref() {
+        let tests = vec![
+            ("$.a", vec![JsonPathElementRef::Field(Cow::Borrowed("a"))]),
+            (
+                "$.\"a.b\"[#-1]",
+                vec![
+                    JsonPathElementRef::Field(Cow::Borrowed("a.b")),
+                    JsonPathElementRef::Index(JsonPathIndex::NthRight(1)),
+                ],
+            ),
+            (
+                "$.\"a\\nb\"",
+                vec![JsonPathElementRef::Field(Cow::Owned("a\nb".to_string()))],
+            ),
+        ];
+        for (query, expected) in tests {
+            let path = JsonPathRef::parse(query).unwrap();
+            for (e, expected) in path.iter().zip(&expected) {
+                if let (JsonPathElementRef::Field(key), JsonPathElementRef::Field(expected)) =
+                    (e, expected)
+                {
+                    assert_eq!(
+                        matches!(key, Cow::Borrowed(_)),
+                        matches!(expected, Cow::Borrowed(_)),
+                        "expected {} to borrow {}",
+                        query,
+                        expected
+                    );
+                }
+            }
+            assert_eq!(*path, expected, "expected {} to be {:?}", query, expected);
+            assert_eq!(path.to_string(), query);
+            assert_eq!(
+                path.clone().into_owned(),
+                JsonPath::try_from(query).unwrap()
+            );
+            assert_eq!(path.into_owned().borrowed().to_string(), query);
+        }
+
+        let mut value = json!({"a b": [1, 2], "c": {}});
+        let tests = vec![
+            ("$.\"a b\"[#-1]", Some(json!(2))),
+            ("$.\"a b\"[2]", None),
+            ("$.c", Some(json!({}))),
+        ];
+        for (query, expected) in tests {
+            let path = JsonPathRef::parse(query).unwrap();
+            assert_eq!(path.find(&value), expected.as_ref());
+            assert_eq!(path.find_mut(&mut value).cloned(), expected);
+        }
+        let path = JsonPathRef::parse("$.\"a b\"[0]").unwrap();
+        assert!(path.insert(&mut value, json!(0)).is_ok());
+        assert!(path.set(&mut value, json!(3)).is_ok());
+        assert!(path.replace(&mut value, json!(4)).is_ok());
+        assert!(JsonPathRef::parse("$.c.d")
+            .unwrap()
+            .set(&mut value, json!(5))
+            .is_ok());
+        assert!(JsonPathRef::parse("$.c")
+            .unwrap()
+            .remove(&mut value)
+            .is_ok());
+        assert_eq!(value, json!({"a b": [4, 1, 2]}));
+        let first = JsonPathRef::parse("$.\"a b\"[0]").unwrap();
+        let last = JsonPathRef::parse("$.\"a b\"[#-1]").unwrap();
+        assert!(JsonPath::swap(&first, &last, &mut value).is_ok());
+        assert!(first
+            .copy_to(&JsonPath::root().field("c"), &mut value)
+            .is_ok());
+        assert_eq!(value, json!({"a b": [2, 1, 4], "c": 2}));
+    }
+
     #[test]
     fn path() {
         let tests: Vec<(&str, serde_json::Value, Result<serde_json::Value, _>)> = vec![
@@ -428,6 +1327,8 @@ mod tests {
             ),
             ("1", json!([1, 2, 4]), Ok(json!(2))),
             ("$[2]", json!([1]), Err("unable to find path to value")),
+            ("$.b.c]", json!({"a": 1}), Err("expected . or [")),
+            ("$.\"a-b\"", json!({"a-b": 1}), Ok(json!(1))),
             (
                 "$.a[#-2]",
                 json!({ "b": [1] }),
@@ -458,211 +1359,377 @@ mod tests {
 
     #[test]
     fn insert() {
-        let tests: Vec<(
-            JsonPath,
-            serde_json::Value,
-            serde_json::Value,
-            Option<serde_json::Value>,
-        )> = vec![
-            (
-                "$.a".try_into().unwrap(),
-                json!({}),
-                json!("test"),
-                Some(json!({ "a": "test"})),
-            ),
+        let tests = vec![
+            ("$.a", json!({}), Ok(json!({ "a": "test"}))),
             (
-                "$.a.b[1]".try_into().unwrap(),
+                "$.a.b[1]",
                 json!({"a": { "b": [1,2,4] }}),
-                json!("test"),
-                Some(json!({ "a": { "b": [1, "test", 2, 4]}})),
+                Ok(json!({ "a": { "b": [1, "test", 2, 4]}})),
             ),
             (
-                "$.a.b[#]".try_into().unwrap(),
+                "$.a.b[#]",
                 json!({"a": { "b": [1,2,4] }}),
-                json!("test"),
-                Some(json!({ "a": { "b": [1, 2, 4, "test"]}})),
+                Ok(json!({ "a": { "b": [1, 2, 4, "test"]}})),
             ),
             (
-                "$.a.b[#-3]".try_into().unwrap(),
+                "$.a.b[#-3]",
                 json!({"a": { "b": [1,2,4] }}),
-                json!("test"),
-                Some(json!({ "a": { "b": ["test", 1, 2, 4 ]}})),
+                Ok(json!({ "a": { "b": ["test", 1, 2, 4 ]}})),
             ),
+            ("$.a", json!({"a": 10.0}), Err(MutationError::ExistingKey)),
+            ("$.a[1]", json!({"a": []}), Err(MutationError::OutOfRange)),
+            ("$.a[#-3]", json!({"a": []}), Err(MutationError::OutOfRange)),
+            ("$.a.b", json!({"a": []}), Err(MutationError::TypeMismatch)),
+            ("$.a[0]", json!({"a": {}}), Err(MutationError::TypeMismatch)),
+            ("$.a.b", json!({}), Err(MutationError::MissingParent)),
+            ("$", json!({}), Err(MutationError::MissingParent)),
+        ];
+
+        for (path, mut value, expected) in tests {
+            let path: JsonPath = path.try_into().unwrap();
+            let result = path.insert(&mut value, json!("test"));
+            let result = result.map(|outcome| {
+                assert_eq!(outcome.previous, None);
+                value
+            });
+            assert_eq!(result, expected, "expected {} to be {:?}", path, expected);
+        }
+    }
+
+    #[test]
+    fn replace() {
+        let tests = vec![
+            ("$.a", json!({}), Err(MutationError::MissingKey)),
             (
-                "$.a".try_into().unwrap(),
-                json!({"a": 10.0}),
-                json!("test"),
-                None,
+                "$.a.b[1]",
+                json!({"a": { "b": [1,2,4] }}),
+                Ok((json!({ "a": { "b": [1, "test", 4]}}), json!(2))),
             ),
             (
-                "$.a[1]".try_into().unwrap(),
-                json!({"a": []}),
-                json!("test"),
-                None,
+                "$.a.b[#-2]",
+                json!({"a": { "b": [1,2,4] }}),
+                Ok((json!({ "a": { "b": [1, "test", 4 ]}}), json!(2))),
             ),
             (
-                "$.a[#-3]".try_into().unwrap(),
-                json!({"a": []}),
-                json!("test"),
-                None,
+                "$.a",
+                json!({"a": 10.0}),
+                Ok((json!({"a": "test"}), json!(10.0))),
             ),
+            ("$", json!([1]), Ok((json!("test"), json!([1])))),
+            ("$.a[1]", json!({"a": []}), Err(MutationError::OutOfRange)),
+            ("$.a[#-3]", json!({"a": []}), Err(MutationError::OutOfRange)),
+            ("$.a.b", json!({"a": 1}), Err(MutationError::TypeMismatch)),
+            ("$.a.b", json!({}), Err(MutationError::MissingParent)),
         ];
 
-        for (path, mut value, extra, expected) in tests {
-            let value = path.insert(&mut value, extra);
-            assert_eq!(
-                value,
-                expected.as_ref(),
-                "expected {:?} to be {:?}",
-                value,
-                expected
-            );
+        for (path, mut value, expected) in tests {
+            let path: JsonPath = path.try_into().unwrap();
+            let result = path.replace(&mut value, json!("test"));
+            let result = result.map(|outcome| (value, outcome.previous.unwrap()));
+            assert_eq!(result, expected, "expected {} to be {:?}", path, expected);
         }
     }
 
     #[test]
-    fn replace() {
-        let tests: Vec<(
-            JsonPath,
-            serde_json::Value,
-            serde_json::Value,
-            Option<serde_json::Value>,
-        )> = vec![
-            ("$.a".try_into().unwrap(), json!({}), json!("test"), None),
-            (
-                "$.a.b[1]".try_into().unwrap(),
+    fn set() {
+        let tests = vec![
+            ("$.a", json!({}), Ok((json!({ "a": "test" }), None))),
+            (
+                "$.a.b[1]",
                 json!({"a": { "b": [1,2,4] }}),
-                json!("test"),
-                Some(json!({ "a": { "b": [1, "test", 4]}})),
+                Ok((json!({ "a": { "b": [1, "test", 4]}}), Some(json!(2)))),
             ),
             (
-                "$.a.b[#-2]".try_into().unwrap(),
+                "$.a.b[#-2]",
                 json!({"a": { "b": [1,2,4] }}),
-                json!("test"),
-                Some(json!({ "a": { "b": [1, "test", 4 ]}})),
+                Ok((json!({ "a": { "b": [1, "test", 4 ]}}), Some(json!(2)))),
             ),
             (
-                "$.a".try_into().unwrap(),
+                "$.a",
                 json!({"a": 10.0}),
-                json!("test"),
-                Some(json!({"a": "test"})),
-            ),
-            (
-                "$.a[1]".try_into().unwrap(),
-                json!({"a": []}),
-                json!("test"),
-                None,
-            ),
-            (
-                "$.a[#-3]".try_into().unwrap(),
-                json!({"a": []}),
-                json!("test"),
-                None,
+                Ok((json!({"a": "test"}), Some(json!(10.0)))),
             ),
+            ("$.a[1]", json!({"a": []}), Err(MutationError::OutOfRange)),
+            ("$.a[#-3]", json!({"a": []}), Err(MutationError::OutOfRange)),
+            ("$.a[#]", json!({"a": [1]}), Err(MutationError::OutOfRange)),
+            ("$.a.b", json!({"a": "x"}), Err(MutationError::TypeMismatch)),
+            ("$.a.b", json!({}), Err(MutationError::MissingParent)),
+            ("$", json!({}), Err(MutationError::MissingParent)),
         ];
 
-        for (path, mut value, extra, expected) in tests {
-            let value = path.replace(&mut value, extra);
-            assert_eq!(
-                value,
-                expected.as_ref(),
-                "expected {:?} to be {:?}",
-                value,
-                expected
-            );
+        for (path, mut value, expected) in tests {
+            let path: JsonPath = path.try_into().unwrap();
+            let result = path.set(&mut value, json!("test"));
+            let result = result.map(|outcome| (value, outcome.previous));
+            assert_eq!(result, expected, "expected {} to be {:?}", path, expected);
         }
     }
 
     #[test]
-    fn set() {
-        let tests: Vec<(
-            JsonPath,
-            serde_json::Value,
-            serde_json::Value,
-            Option<serde_json::Value>,
-        )> = vec![
+    fn remove() {
+        let tests = vec![
+            ("$.a", json!({}), Err(MutationError::MissingKey)),
             (
-                "$.a".try_into().unwrap(),
-                json!({}),
-                json!("test"),
-                Some(json!({ "a": "test" })),
+                "$.a.b[1]",
+                json!({"a": { "b": [1,2,8] }}),
+                Ok((json!({ "a": { "b": [1, 8]}}), json!(2))),
             ),
             (
-                "$.a.b[1]".try_into().unwrap(),
+                "$.a.b[#-2]",
                 json!({"a": { "b": [1,2,4] }}),
-                json!("test"),
-                Some(json!({ "a": { "b": [1, "test", 4]}})),
+                Ok((json!({ "a": { "b": [1, 4 ]}}), json!(2))),
             ),
+            ("$.a", json!({"a": 10.0}), Ok((json!({}), json!(10.0)))),
+            ("$.a[1]", json!({"a": []}), Err(MutationError::OutOfRange)),
+            ("$.a[#-3]", json!({"a": []}), Err(MutationError::OutOfRange)),
+            // The index equal to the length used to panic
             (
-                "$.a.b[#-2]".try_into().unwrap(),
-                json!({"a": { "b": [1,2,4] }}),
-                json!("test"),
-                Some(json!({ "a": { "b": [1, "test", 4 ]}})),
+                "$.a[2]",
+                json!({"a": [1, 2]}),
+                Err(MutationError::OutOfRange),
             ),
             (
-                "$.a".try_into().unwrap(),
-                json!({"a": 10.0}),
-                json!("test"),
-                Some(json!({"a": "test"})),
-            ),
-            (
-                "$.a[1]".try_into().unwrap(),
-                json!({"a": []}),
-                json!("test"),
-                None,
+                "$.a[#]",
+                json!({"a": [1, 2]}),
+                Err(MutationError::OutOfRange),
             ),
             (
-                "$.a[#-3]".try_into().unwrap(),
-                json!({"a": []}),
-                json!("test"),
-                None,
+                "$.a[0]",
+                json!({"a": "x"}),
+                Err(MutationError::TypeMismatch),
             ),
+            ("$.a.b", json!({}), Err(MutationError::MissingParent)),
+            ("$", json!({}), Err(MutationError::MissingParent)),
         ];
 
-        for (path, mut value, extra, expected) in tests {
-            let value = path.set(&mut value, extra);
-            assert_eq!(
-                value,
-                expected.as_ref(),
-                "expected {:?} to be {:?}",
-                value,
-                expected
-            );
+        for (path, mut value, expected) in tests {
+            let path: JsonPath = path.try_into().unwrap();
+            let result = path.remove(&mut value);
+            let result = result.map(|outcome| (value, outcome.previous.unwrap()));
+            assert_eq!(result, expected, "expected {} to be {:?}", path, expected);
         }
     }
 
     #[test]
-    fn remove() {
-        let tests: Vec<(JsonPath, serde_json::Value, Option<serde_json::Value>)> = vec![
-            ("$.a".try_into().unwrap(), json!({}), None),
+    fn path_as() {
+        let value = json!({"name": "ann", "age": 31, "tags": ["a", "b"], "none": null});
+        assert_eq!(value.path_as::<String>("$.name"), Ok("ann".to_string()));
+        assert_eq!(value.path_as::<&str>("$.name"), Ok("ann"));
+        assert_eq!(value.path_as::<Vec<String>>("$.tags").unwrap(), ["a", "b"]);
+        assert_eq!(value.path_as::<Option<u8>>("$.none"), Ok(None));
+        assert_eq!(value.path_as_opt::<u8>("$.age"), Ok(Some(31)));
+        assert_eq!(value.path_as_opt::<u8>("$.height"), Ok(None));
+
+        let tests = vec![
+            (value.path_as::<u8>("$.height"), "no value at `$.height`"),
             (
-                "$.a.b[1]".try_into().unwrap(),
-                json!({"a": { "b": [1,2,8] }}),
-                Some(json!({ "a": { "b": [1, 8]}})),
+                value.path_as::<u8>("$.name"),
+                "invalid value at `$.name`: invalid type: string \"ann\", expected u8",
             ),
             (
-                "$.a.b[#-2]".try_into().unwrap(),
-                json!({"a": { "b": [1,2,4] }}),
-                Some(json!({ "a": { "b": [1, 4 ]}})),
+                value.path_as_opt::<u8>("$.tags[#-1]").map(Option::unwrap),
+                "invalid value at `$.tags[#-1]`: invalid type: string \"b\", expected u8",
             ),
             (
-                "$.a".try_into().unwrap(),
-                json!({"a": 10.0}),
-                Some(json!({})),
+                value.path_as::<u8>("$.age["),
+                "invalid path `$.age[`: expected ]",
             ),
-            ("$.a[1]".try_into().unwrap(), json!({"a": []}), None),
-            ("$.a[#-3]".try_into().unwrap(), json!({"a": []}), None),
         ];
+        for (result, expected) in tests {
+            let result = result.map_err(|err| err.to_string());
+            assert_eq!(result, Err(expected.to_string()));
+        }
 
-        for (path, mut value, expected) in tests {
-            let value = path.remove(&mut value);
+        let (name, age, first): (String, u64, &str) =
+            value.extract(("$.name", "$.age", "$.tags[0]")).unwrap();
+        assert_eq!((name.as_str(), age, first), ("ann", 31, "a"));
+        let result = value.extract::<(String, String), _>(("$.name", "$.age"));
+        // With arbitrary_precision serde_json doesn't know the type of a number.
+        let message = if cfg!(feature = "arbitrary_precision") {
+            "invalid type: number, expected a string"
+        } else {
+            "invalid type: integer `31`, expected a string"
+        };
+        assert_eq!(
+            result,
+            Err(ExtractError::Deserialize(
+                "$.age".to_string(),
+                message.to_string()
+            ))
+        );
+    }
+
+    #[test]
+    fn builder() {
+        let mut pushed = JsonPath::root();
+        pushed.push_field("a b").push_index(2);
+        let tests = vec![
+            (JsonPath::root(), "$"),
+            (JsonPath::root().field("a").index(0), "$.a[0]"),
+            (JsonPath::root().index(1).index_from_end(1), "$[1][#-1]"),
+            (JsonPath::root().field("x").index_from_end(0), "$.x[#-0]"),
+            (pushed, "$.\"a b\"[2]"),
+        ];
+        for (path, expected) in tests {
+            let parsed: JsonPath = expected.try_into().unwrap();
+            assert_eq!(path, parsed, "expected {} to be {}", path, expected);
+        }
+    }
+
+    #[test]
+    fn prefixes() {
+        let path = |p: &str| -> JsonPath { p.try_into().unwrap() };
+        let tests = vec![
+            ("$.a.b[0]", "$.a", true, Some("$.b[0]"), "$.a"),
+            ("$.a.b[0]", "$", true, Some("$.a.b[0]"), "$"),
+            ("$.a.b[0]", "$.a.b[0]", true, Some("$"), "$.a.b[0]"),
+            ("$.a.b[0]", "$.a.b[1]", false, None, "$.a.b"),
+            ("$.a", "$.a.b", false, None, "$.a"),
+            ("$.a[#-1]", "$.a[2]", false, None, "$.a"),
+        ];
+        for (a, b, starts_with, stripped, common) in tests {
+            let (a, b) = (path(a), path(b));
             assert_eq!(
-                value,
-                expected.as_ref(),
-                "expected {:?} to be {:?}",
-                value,
-                expected
+                a.starts_with(&b),
+                starts_with,
+                "expected {} starts with {}",
+                a,
+                b
+            );
+            assert_eq!(
+                a.strip_prefix(&b),
+                stripped.map(path),
+                "expected {} without {} to be {:?}",
+                a,
+                b,
+                stripped
+            );
+            assert_eq!(
+                a.common_prefix(&b),
+                path(common),
+                "expected common prefix of {} and {} to be {}",
+                a,
+                b,
+                common
+            );
+            if let Some(stripped) = a.strip_prefix(&b) {
+                assert_eq!(b.join(&stripped), a);
+            }
+        }
+
+        let tests = vec![
+            ("$", None, None),
+            (
+                "$.a",
+                Some("$"),
+                Some(JsonPathElement::Field("a".to_string())),
+            ),
+            (
+                "$.a[#-2]",
+                Some("$.a"),
+                Some(JsonPathElement::Index(JsonPathIndex::NthRight(2))),
+            ),
+        ];
+        for (p, parent, last) in tests {
+            let p = path(p);
+            assert_eq!(p.parent(), parent.map(path), "expected parent of {}", p);
+            assert_eq!(p.last(), last.as_ref(), "expected last of {}", p);
+        }
+    }
+
+    #[test]
+    fn normalize() {
+        let value = json!({"a": [[1, 2], [3, 4, 5]], "b": {}});
+        let tests = vec![
+            ("$.a[#-1]", Some("$.a[1]")),
+            ("$.a[#-1][#-3]", Some("$.a[1][0]")),
+            ("$.a[#-2][#]", Some("$.a[0][2]")),
+            ("$.a[0].x[1]", Some("$.a[0].x[1]")),
+            ("$.b.c", Some("$.b.c")),
+            ("$.a[#-3]", None),
+            ("$.b[#-1]", None),
+            ("$.c.d[#-1]", None),
+        ];
+        for (path, expected) in tests {
+            let parsed: JsonPath = path.try_into().unwrap();
+            let expected: Option<JsonPath> = expected.map(|p| p.try_into().unwrap());
+            let result = parsed.normalize(&value);
+            assert_eq!(result, expected, "expected {} to be {:?}", path, expected);
+            let borrowed = JsonPathRef::parse(path).unwrap();
+            let result = borrowed.normalize(&value).map(JsonPathRef::into_owned);
+            assert_eq!(
+                result, expected,
+                "expected borrowed {} to be {:?}",
+                path, expected
             );
         }
     }
+
+    fn arb_value() -> impl Strategy<Value = serde_json::Value> {
+        let leaf = prop_oneof![
+            Just(json!(null)),
+            any::<bool>().prop_map(|b| json!(b)),
+            any::<i64>().prop_map(|i| json!(i)),
+            "[ab]".prop_map(|s| json!(s)),
+        ];
+        leaf.prop_recursive(3, 16, 4, |inner| {
+            prop_oneof![
+                prop::collection::vec(inner.clone(), 0..4).prop_map(serde_json::Value::from),
+                prop::collection::btree_map("[ab]", inner, 0..3).prop_map(|map| json!(map)),
+            ]
+        })
+    }
+
+    fn arb_path() -> impl Strategy<Value = JsonPath> {
+        let element = prop_oneof![
+            "[ab]".prop_map(JsonPathElement::Field),
+            (0..5usize).prop_map(|i| JsonPathElement::Index(JsonPathIndex::NthLefth(i))),
+            (0..5usize).prop_map(|i| JsonPathElement::Index(JsonPathIndex::NthRight(i))),
+        ];
+        prop::collection::vec(element, 0..4).prop_map(JsonPath::from)
+    }
+
+    proptest! {
+        // Mutations never panic, leave the value unchanged on failure and report what was there.
+        #[test]
+        fn mutations(value in arb_value(), path in arb_path(), v in arb_value()) {
+            let found = path.find(&value).cloned();
+
+            let mut result = value.clone();
+            match path.insert(&mut result, v.clone()) {
+                Ok(outcome) => prop_assert_eq!(outcome.previous, None),
+                Err(_) => prop_assert_eq!(&result, &value),
+            }
+
+            let mut result = value.clone();
+            match path.set(&mut result, v.clone()) {
+                Ok(outcome) => {
+                    prop_assert_eq!(outcome.previous, found.clone());
+                    prop_assert_eq!(path.find(&result), Some(&v));
+                }
+                Err(_) => prop_assert_eq!(&result, &value),
+            }
+
+            let mut result = value.clone();
+            match path.replace(&mut result, v.clone()) {
+                Ok(outcome) => {
+                    prop_assert_eq!(outcome.previous, found.clone());
+                    prop_assert_eq!(path.find(&result), Some(&v));
+                }
+                Err(_) => {
+                    prop_assert_eq!(&result, &value);
+                    prop_assert!(found.is_none());
+                }
+            }
+
+            let mut result = value.clone();
+            match path.remove(&mut result) {
+                Ok(outcome) => prop_assert_eq!(outcome.previous, found),
+                Err(_) => {
+                    prop_assert_eq!(&result, &value);
+                    prop_assert!(found.is_none() || path.is_empty());
+                }
+            }
+        }
+    }
 }
@@ -1,2 +1,29 @@
+pub mod cache;
+pub mod cast;
+pub mod collation;
+pub mod compare;
+pub mod entry;
+pub mod explain;
+pub mod extract;
+pub mod jmespath;
+pub mod jq;
+pub mod json_like;
 pub mod json_path;
+pub mod mysql;
+pub mod number;
+#[cfg(feature = "serde")]
+mod path_serde;
+pub mod path_set;
+pub mod raw;
+mod relocate;
+pub mod search;
+pub mod stream;
+pub mod total;
 pub mod value;
+
+// Paths used by the code jops-macros generates, so crates deriving from it don't need to depend
+// on these crates themselves.
+#[doc(hidden)]
+pub mod __private {
+    pub use serde_json;
+}
@@ -0,0 +1,1095 @@
+use std::{fmt, iter::Peekable, str::Chars, str::FromStr};
+
+use serde_json::{Map, Value};
+
+use crate::{
+    json_path::{JsonPath, JsonPathElement, JsonPathIndex},
+    number::cmp_numbers,
+    search::{self, Matcher, SearchMode},
+};
+
+/// A [MySQL JSON path](https://dev.mysql.com/doc/refman/8.0/en/json.html#json-path-syntax).
+///
+/// Paths resolve to concrete `JsonPath`s against a document, see `MySqlPath::resolve`.
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+pub struct MySqlPath(Vec<MySqlPathLeg>);
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum MySqlPathLeg {
+    Member(String),                      // .name or ."quoted name"
+    MemberWildcard,                      // .*
+    Index(JsonPathIndex),                // [N], [last] is NthRight(1), [last-N] is NthRight(N+1)
+    Range(JsonPathIndex, JsonPathIndex), // [M to N]
+    IndexWildcard,                       // [*]
+    DoubleWildcard,                      // **
+}
+
+const ROOT: char = '$';
+const DOT: char = '.';
+const BEGIN_INDEX: char = '[';
+const CLOSE_INDEX: char = ']';
+const WILDCARD: char = '*';
+const QUOTE: char = '"';
+
+const WILDCARD_ERROR: &str =
+    "path expressions may not contain the * and ** tokens or an array range";
+
+impl MySqlPath {
+    /// Returns true if the path contains `*`, `**` or an array range.
+    pub fn has_wildcard(&self) -> bool {
+        self.0
+            .iter()
+            .any(|leg| !matches!(leg, MySqlPathLeg::Member(_) | MySqlPathLeg::Index(_)))
+    }
+
+    /// Returns the paths of all values inside `value` matched by this path.
+    ///
+    /// Indices are resolved to absolute positions. Following MySQL, `[0]` and `[last]` of a
+    /// value which is not an array refer to the value itself.
+    pub fn resolve(&self, value: &Value) -> Vec<JsonPath> {
+        let mut out = Vec::new();
+        collect(&self.0, value, &mut JsonPath::default(), &mut out);
+        out
+    }
+
+    /// Returns the first path matched by this path.
+    pub fn find(&self, value: &Value) -> Option<JsonPath> {
+        self.resolve(value).into_iter().next()
+    }
+}
+
+// Resolves an index against an array of length len.
+fn absolute(index: JsonPathIndex, len: usize) -> Option<usize> {
+    match index {
+        JsonPathIndex::NthLefth(i) => Some(i),
+        JsonPathIndex::NthRight(i) if i >= 1 && i <= len => Some(len - i),
+        JsonPathIndex::NthRight(_) => None,
+    }
+}
+
+// Resolves a range against an array of length len, clamping both ends into the array.
+fn absolute_range(from: JsonPathIndex, to: JsonPathIndex, len: usize) -> std::ops::Range<usize> {
+    let from = match from {
+        JsonPathIndex::NthLefth(i) => i,
+        JsonPathIndex::NthRight(i) => len.saturating_sub(i),
+    };
+    match absolute(to, len) {
+        Some(to) => from..to.saturating_add(1).min(len),
+        None => 0..0,
+    }
+}
+
+fn collect(legs: &[MySqlPathLeg], value: &Value, path: &mut JsonPath, out: &mut Vec<JsonPath>) {
+    let Some((leg, rest)) = legs.split_first() else {
+        if !out.contains(path) {
+            out.push(path.clone());
+        }
+        return;
+    };
+    match (leg, value) {
+        (MySqlPathLeg::Member(key), Value::Object(map)) => {
+            if let Some(sub) = map.get(key) {
+                descend(rest, sub, path, JsonPathElement::Field(key.clone()), out);
+            }
+        }
+        (MySqlPathLeg::MemberWildcard, Value::Object(map)) => {
+            for (key, sub) in map {
+                descend(rest, sub, path, JsonPathElement::Field(key.clone()), out);
+            }
+        }
+        (MySqlPathLeg::Index(index), Value::Array(array)) => {
+            if let Some(i) = absolute(*index, array.len()).filter(|i| *i < array.len()) {
+                let element = JsonPathElement::Index(JsonPathIndex::NthLefth(i));
+                descend(rest, &array[i], path, element, out);
+            }
+        }
+        (MySqlPathLeg::Index(index), _) if absolute(*index, 1) == Some(0) => {
+            collect(rest, value, path, out);
+        }
+        (MySqlPathLeg::Range(from, to), Value::Array(array)) => {
+            for i in absolute_range(*from, *to, array.len()) {
+                let element = JsonPathElement::Index(JsonPathIndex::NthLefth(i));
+                descend(rest, &array[i], path, element, out);
+            }
+        }
+        (MySqlPathLeg::Range(from, to), _) if absolute_range(*from, *to, 1).contains(&0) => {
+            collect(rest, value, path, out);
+        }
+        (MySqlPathLeg::IndexWildcard, Value::Array(array)) => {
+            for (i, sub) in array.iter().enumerate() {
+                let element = JsonPathElement::Index(JsonPathIndex::NthLefth(i));
+                descend(rest, sub, path, element, out);
+            }
+        }
+        (MySqlPathLeg::DoubleWildcard, _) => {
+            // ** matches zero or more legs
+            collect(rest, value, path, out);
+            match value {
+                Value::Object(map) => {
+                    for (key, sub) in map {
+                        descend(legs, sub, path, JsonPathElement::Field(key.clone()), out);
+                    }
+                }
+                Value::Array(array) => {
+                    for (i, sub) in array.iter().enumerate() {
+                        let element = JsonPathElement::Index(JsonPathIndex::NthLefth(i));
+                        descend(legs, sub, path, element, out);
+                    }
+                }
+                _ => {}
+            }
+        }
+        _ => {}
+    }
+}
+
+fn descend(
+    legs: &[MySqlPathLeg],
+    value: &Value,
+    path: &mut JsonPath,
+    element: JsonPathElement,
+    out: &mut Vec<JsonPath>,
+) {
+    path.push(element);
+    collect(legs, value, path, out);
+    path.pop();
+}
+
+fn skip_whitespace(iter: &mut Peekable<Chars>) {
+    while iter.next_if(|c| c.is_whitespace()).is_some() {}
+}
+
+fn is_identifier(key: &str) -> bool {
+    let mut chars = key.chars();
+    chars
+        .next()
+        .is_some_and(|c| c.is_alphabetic() || c == '_' || c == '$')
+        && chars.all(|c| c.is_alphanumeric() || c == '_' || c == '$')
+}
+
+fn parse_member(iter: &mut Peekable<Chars>) -> Result<String, &'static str> {
+    if iter.peek() == Some(&QUOTE) {
+        // Collect the quoted string and let serde_json handle the escape sequences
+        let mut raw = String::new();
+        raw.extend(iter.next());
+        loop {
+            match iter.next() {
+                Some('\\') => {
+                    raw.push('\\');
+                    raw.extend(iter.next());
+                }
+                Some(QUOTE) => {
+                    raw.push(QUOTE);
+                    break;
+                }
+                Some(c) => raw.push(c),
+                None => return Err("expected \""),
+            }
+        }
+        return serde_json::from_str(&raw).map_err(|_| "invalid quoted member name");
+    }
+
+    let mut member = String::new();
+    if let Some(c) = iter.next_if(|c| c.is_alphabetic() || *c == '_' || *c == '$') {
+        member.push(c);
+    } else {
+        return Err("expected member name");
+    }
+    while let Some(c) = iter.next_if(|c| c.is_alphanumeric() || *c == '_' || *c == '$') {
+        member.push(c);
+    }
+    Ok(member)
+}
+
+fn parse_number(iter: &mut Peekable<Chars>) -> Result<usize, &'static str> {
+    let mut number = String::new();
+    while let Some(c) = iter.next_if(|c| c.is_ascii_digit()) {
+        number.push(c);
+    }
+    number.parse().map_err(|_| "expected array index")
+}
+
+fn parse_keyword(iter: &mut Peekable<Chars>, keyword: &str) -> bool {
+    if iter.peek() != keyword.chars().next().as_ref() {
+        return false;
+    }
+    keyword.chars().all(|c| iter.next_if_eq(&c).is_some())
+}
+
+fn parse_index(iter: &mut Peekable<Chars>) -> Result<JsonPathIndex, &'static str> {
+    skip_whitespace(iter);
+    if !parse_keyword(iter, "last") {
+        return parse_number(iter).map(JsonPathIndex::NthLefth);
+    }
+    skip_whitespace(iter);
+    if iter.next_if_eq(&'-').is_some() {
+        skip_whitespace(iter);
+        let offset = parse_number(iter)?;
+        offset
+            .checked_add(1)
+            .map(JsonPathIndex::NthRight)
+            .ok_or("expected array index")
+    } else {
+        Ok(JsonPathIndex::NthRight(1))
+    }
+}
+
+fn parse_array_location(iter: &mut Peekable<Chars>) -> Result<MySqlPathLeg, &'static str> {
+    skip_whitespace(iter);
+    let leg = if iter.next_if_eq(&WILDCARD).is_some() {
+        MySqlPathLeg::IndexWildcard
+    } else {
+        let from = parse_index(iter)?;
+        skip_whitespace(iter);
+        if parse_keyword(iter, "to") {
+            let to = parse_index(iter)?;
+            match (from, to) {
+                (JsonPathIndex::NthLefth(a), JsonPathIndex::NthLefth(b))
+                | (JsonPathIndex::NthRight(b), JsonPathIndex::NthRight(a))
+                    if a > b =>
+                {
+                    return Err("invalid array range")
+                }
+                _ => MySqlPathLeg::Range(from, to),
+            }
+        } else {
+            MySqlPathLeg::Index(from)
+        }
+    };
+    skip_whitespace(iter);
+    if iter.next_if_eq(&CLOSE_INDEX).is_none() {
+        return Err("expected ]");
+    }
+    Ok(leg)
+}
+
+impl FromStr for MySqlPath {
+    type Err = &'static str;
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        let mut iter = value.chars().peekable();
+        skip_whitespace(&mut iter);
+        if iter.next_if_eq(&ROOT).is_none() {
+            return Err("expected $");
+        }
+
+        let mut legs = Vec::new();
+        loop {
+            skip_whitespace(&mut iter);
+            match iter.next() {
+                Some(DOT) => {
+                    skip_whitespace(&mut iter);
+                    if iter.next_if_eq(&WILDCARD).is_some() {
+                        legs.push(MySqlPathLeg::MemberWildcard);
+                    } else {
+                        legs.push(MySqlPathLeg::Member(parse_member(&mut iter)?));
+                    }
+                }
+                Some(BEGIN_INDEX) => legs.push(parse_array_location(&mut iter)?),
+                Some(WILDCARD) => {
+                    if iter.next_if_eq(&WILDCARD).is_none() {
+                        return Err("expected **");
+                    }
+                    legs.push(MySqlPathLeg::DoubleWildcard);
+                }
+                None => break,
+                _ => return Err("expected ., [ or **"),
+            }
+        }
+        if legs.last() == Some(&MySqlPathLeg::DoubleWildcard) {
+            return Err("path must not end with **");
+        }
+        Ok(MySqlPath(legs))
+    }
+}
+
+impl TryFrom<&str> for MySqlPath {
+    type Error = &'static str;
+
+    #[inline]
+    fn try_from(value: &str) -> Result<Self, Self::Error> {
+        MySqlPath::from_str(value)
+    }
+}
+
+fn fmt_index(index: &JsonPathIndex, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+    match index {
+        JsonPathIndex::NthLefth(i) => write!(f, "{i}"),
+        JsonPathIndex::NthRight(1) => write!(f, "last"),
+        JsonPathIndex::NthRight(i) => write!(f, "last-{}", i.saturating_sub(1)),
+    }
+}
+
+impl fmt::Display for MySqlPath {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{ROOT}")?;
+        for leg in &self.0 {
+            match leg {
+                MySqlPathLeg::Member(key) if is_identifier(key) => write!(f, ".{key}")?,
+                MySqlPathLeg::Member(key) => write!(f, ".{}", Value::String(key.clone()))?,
+                MySqlPathLeg::MemberWildcard => write!(f, ".*")?,
+                MySqlPathLeg::Index(index) => {
+                    write!(f, "[")?;
+                    fmt_index(index, f)?;
+                    write!(f, "]")?;
+                }
+                MySqlPathLeg::Range(from, to) => {
+                    write!(f, "[")?;
+                    fmt_index(from, f)?;
+                    write!(f, " to ")?;
+                    fmt_index(to, f)?;
+                    write!(f, "]")?;
+                }
+                MySqlPathLeg::IndexWildcard => write!(f, "[*]")?,
+                MySqlPathLeg::DoubleWildcard => write!(f, "**")?,
+            }
+        }
+        Ok(())
+    }
+}
+
+impl From<&JsonPath> for MySqlPath {
+    fn from(path: &JsonPath) -> Self {
+        let legs = path
+            .iter()
+            .map(|e| match e {
+                JsonPathElement::Field(key) => MySqlPathLeg::Member(key.clone()),
+                JsonPathElement::Index(index) => MySqlPathLeg::Index(*index),
+            })
+            .collect();
+        MySqlPath(legs)
+    }
+}
+
+// Parses a path which must not contain wildcards or ranges.
+fn parse_single(path: &str) -> Result<MySqlPath, &'static str> {
+    let path = MySqlPath::from_str(path)?;
+    if path.has_wildcard() {
+        Err(WILDCARD_ERROR)
+    } else {
+        Ok(path)
+    }
+}
+
+fn lookup<'a>(doc: &'a Value, path: &MySqlPath) -> Option<&'a Value> {
+    path.find(doc).and_then(|path| path.find(doc))
+}
+
+// Equality of two JSON values, numbers compare by their exact value.
+fn json_eq(a: &Value, b: &Value) -> bool {
+    match (a, b) {
+        (Value::Number(a), Value::Number(b)) => cmp_numbers(a, b).is_eq(),
+        (Value::Array(a), Value::Array(b)) => {
+            a.len() == b.len() && a.iter().zip(b).all(|(a, b)| json_eq(a, b))
+        }
+        (Value::Object(a), Value::Object(b)) => {
+            a.len() == b.len()
+                && a.iter()
+                    .all(|(key, a)| b.get(key).is_some_and(|b| json_eq(a, b)))
+        }
+        (a, b) => a == b,
+    }
+}
+
+/// Appends values to the end of the arrays at the given paths, like `JSON_ARRAY_APPEND`.
+///
+/// Pairs are applied from left to right. A value at a path which is not an array is wrapped
+/// into an array first. Paths which do not exist are ignored.
+pub fn json_array_append<'a>(
+    doc: &mut Value,
+    pairs: impl IntoIterator<Item = (&'a str, Value)>,
+) -> Result<(), &'static str> {
+    let pairs = pairs
+        .into_iter()
+        .map(|(path, v)| parse_single(path).map(|path| (path, v)))
+        .collect::<Result<Vec<_>, _>>()?;
+    for (path, v) in pairs {
+        match path.find(doc).and_then(|path| path.find_mut(doc)) {
+            Some(Value::Array(target)) => target.push(v),
+            Some(target) => {
+                let old = target.take();
+                *target = Value::Array(vec![old, v]);
+            }
+            None => {}
+        }
+    }
+    Ok(())
+}
+
+/// Inserts values into arrays, like `JSON_ARRAY_INSERT`.
+///
+/// Every path must end with an array index. Pairs are applied from left to right. An index
+/// past the end of the array appends the value. Paths which do not point into an array are
+/// ignored.
+pub fn json_array_insert<'a>(
+    doc: &mut Value,
+    pairs: impl IntoIterator<Item = (&'a str, Value)>,
+) -> Result<(), &'static str> {
+    let pairs = pairs
+        .into_iter()
+        .map(|(path, v)| {
+            let mut path = parse_single(path)?;
+            match path.0.pop() {
+                Some(MySqlPathLeg::Index(index)) => Ok((path, index, v)),
+                _ => Err("path is not a path to a cell in an array"),
+            }
+        })
+        .collect::<Result<Vec<_>, _>>()?;
+    for (parent, index, v) in pairs {
+        let Some(mut path) = parent.find(doc) else {
+            continue;
+        };
+        if let Some(Value::Array(target)) = path.find(doc) {
+            let i = match index {
+                JsonPathIndex::NthLefth(i) => i.min(target.len()),
+                JsonPathIndex::NthRight(i) => target.len().saturating_sub(i),
+            };
+            path.push(JsonPathElement::Index(JsonPathIndex::NthLefth(i)));
+            let _ = path.insert(doc, v);
+        }
+    }
+    Ok(())
+}
+
+fn into_array(value: Value) -> Vec<Value> {
+    match value {
+        Value::Array(array) => array,
+        value => vec![value],
+    }
+}
+
+fn merge_preserve(a: Value, b: Value) -> Value {
+    match (a, b) {
+        (Value::Object(mut a), Value::Object(b)) => {
+            for (key, v) in b {
+                let merged = match a.remove(&key) {
+                    Some(old) => merge_preserve(old, v),
+                    None => v,
+                };
+                a.insert(key, merged);
+            }
+            Value::Object(a)
+        }
+        (a, b) => {
+            let mut a = into_array(a);
+            a.extend(into_array(b));
+            Value::Array(a)
+        }
+    }
+}
+
+/// Merges documents like `JSON_MERGE_PRESERVE`, returns None if there is no document.
+///
+/// Arrays are concatenated, objects are merged and values of duplicate keys are merged
+/// recursively. Any other value is wrapped into an array before merging.
+pub fn json_merge_preserve(docs: impl IntoIterator<Item = Value>) -> Option<Value> {
+    docs.into_iter().reduce(merge_preserve)
+}
+
+fn merge_patch(target: Value, patch: Value) -> Value {
+    let Value::Object(patch) = patch else {
+        return patch;
+    };
+    let mut target = match target {
+        Value::Object(target) => target,
+        _ => Map::new(),
+    };
+    for (key, v) in patch {
+        if v.is_null() {
+            target.remove(&key);
+        } else {
+            let old = target.remove(&key).unwrap_or(Value::Null);
+            target.insert(key, merge_patch(old, v));
+        }
+    }
+    Value::Object(target)
+}
+
+/// Merges documents like `JSON_MERGE_PATCH` ([RFC 7396](https://www.rfc-editor.org/rfc/rfc7396)),
+/// returns None if there is no document.
+///
+/// Keys with a null value in a later document are removed, any non-object document replaces
+/// the result so far.
+pub fn json_merge_patch(docs: impl IntoIterator<Item = Value>) -> Option<Value> {
+    docs.into_iter().reduce(merge_patch)
+}
+
+fn contains(target: &Value, candidate: &Value) -> bool {
+    match (target, candidate) {
+        (Value::Array(target), Value::Array(candidate)) => candidate
+            .iter()
+            .all(|c| target.iter().any(|t| contains(t, c))),
+        (Value::Array(target), candidate) => target.iter().any(|t| contains(t, candidate)),
+        (Value::Object(target), Value::Object(candidate)) => candidate
+            .iter()
+            .all(|(key, c)| target.get(key).is_some_and(|t| contains(t, c))),
+        (Value::Object(_), _) | (_, Value::Array(_) | Value::Object(_)) => false,
+        (target, candidate) => json_eq(target, candidate),
+    }
+}
+
+/// Checks whether `candidate` is contained in `target`, like `JSON_CONTAINS`.
+///
+/// Returns None if `path` does not exist in `target`.
+pub fn json_contains(
+    target: &Value,
+    candidate: &Value,
+    path: Option<&str>,
+) -> Result<Option<bool>, &'static str> {
+    let target = match path {
+        Some(path) => match lookup(target, &parse_single(path)?) {
+            Some(target) => target,
+            None => return Ok(None),
+        },
+        None => target,
+    };
+    Ok(Some(contains(target, candidate)))
+}
+
+/// Checks whether `doc` contains one or all of `paths`, like `JSON_CONTAINS_PATH`.
+///
+/// `one_or_all` is either "one" or "all", case insensitive. Paths may contain wildcards.
+pub fn json_contains_path(
+    doc: &Value,
+    one_or_all: &str,
+    paths: &[&str],
+) -> Result<bool, &'static str> {
+    let mode: SearchMode = one_or_all.parse()?;
+    let paths = paths
+        .iter()
+        .map(|path| MySqlPath::from_str(path))
+        .collect::<Result<Vec<_>, _>>()?;
+    let mut found = paths.iter().map(|path| !path.resolve(doc).is_empty());
+    Ok(match mode {
+        SearchMode::One => found.any(|found| found),
+        SearchMode::All => found.all(|found| found),
+    })
+}
+
+/// Returns the maximum depth of a document, like `JSON_DEPTH`.
+///
+/// Scalars, empty arrays and empty objects have a depth of 1.
+pub fn json_depth(doc: &Value) -> usize {
+    let children = match doc {
+        Value::Array(array) => array.iter().map(json_depth).max(),
+        Value::Object(map) => map.values().map(json_depth).max(),
+        _ => None,
+    };
+    children.map_or(1, |depth| depth + 1)
+}
+
+/// Returns the keys of the object at `path` as an array, like `JSON_KEYS`.
+///
+/// Keys are sorted the way MySQL stores them, shorter keys first.
+/// Returns None if the path does not exist or does not point to an object.
+pub fn json_keys(doc: &Value, path: Option<&str>) -> Result<Option<Value>, &'static str> {
+    let target = match path {
+        Some(path) => lookup(doc, &parse_single(path)?),
+        None => Some(doc),
+    };
+    let Some(Value::Object(map)) = target else {
+        return Ok(None);
+    };
+    let mut keys: Vec<&String> = map.keys().collect();
+    keys.sort_by(|a, b| a.len().cmp(&b.len()).then_with(|| a.cmp(b)));
+    Ok(Some(Value::Array(
+        keys.into_iter().cloned().map(Value::String).collect(),
+    )))
+}
+
+/// Checks whether two documents have any elements in common, like `JSON_OVERLAPS`.
+///
+/// Arrays overlap if they share an element, objects overlap if they share a key-value pair.
+/// A non-array compared with an array is treated as an array with one element.
+pub fn json_overlaps(a: &Value, b: &Value) -> bool {
+    match (a, b) {
+        (Value::Object(a), Value::Object(b)) => a
+            .iter()
+            .any(|(key, a)| b.get(key).is_some_and(|b| json_eq(a, b))),
+        (Value::Array(a), Value::Array(b)) => a.iter().any(|a| b.iter().any(|b| json_eq(a, b))),
+        (Value::Array(array), other) | (other, Value::Array(array)) => {
+            array.iter().any(|a| json_eq(a, other))
+        }
+        (a, b) => json_eq(a, b),
+    }
+}
+
+/// Returns the paths to strings matching a LIKE pattern, like `JSON_SEARCH`.
+///
+/// `one_or_all` is either "one" or "all", case insensitive. Only the values at `paths` are
+/// searched, or the whole document if `paths` is empty. Paths may contain wildcards.
+/// Returns None if nothing matches, a single path as String or multiple paths as Array.
+pub fn json_search(
+    doc: &Value,
+    one_or_all: &str,
+    search_str: &str,
+    escape: Option<char>,
+    paths: &[&str],
+) -> Result<Option<Value>, &'static str> {
+    let mode: SearchMode = one_or_all.parse()?;
+    let prefixes = if paths.is_empty() {
+        vec![JsonPath::default()]
+    } else {
+        let paths = paths
+            .iter()
+            .map(|path| MySqlPath::from_str(path))
+            .collect::<Result<Vec<_>, _>>()?;
+        paths.iter().flat_map(|path| path.resolve(doc)).collect()
+    };
+
+    let matcher = Matcher::Like(search_str, escape);
+    let mut found: Vec<JsonPath> = Vec::new();
+    for prefix in &prefixes {
+        for path in search::search(doc, &matcher, mode, Some(prefix)) {
+            if !found.contains(&path) {
+                found.push(path);
+            }
+        }
+        if mode == SearchMode::One && !found.is_empty() {
+            break;
+        }
+    }
+
+    let mut found: Vec<Value> = found
+        .iter()
+        .map(|path| Value::String(MySqlPath::from(path).to_string()))
+        .collect();
+    Ok(match found.len() {
+        0 => None,
+        1 => found.pop(),
+        _ => Some(Value::Array(found)),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::*;
+
+    #[test]
+    fn try_from() {
+        let tests = vec![
+            ("$", Ok(MySqlPath(vec![]))),
+            (
+                "$.a.b_1",
+                Ok(MySqlPath(vec![
+                    MySqlPathLeg::Member("a".to_string()),
+                    MySqlPathLeg::Member("b_1".to_string()),
+                ])),
+            ),
+            (
+                r#"$."a b"."c\"d""#,
+                Ok(MySqlPath(vec![
+                    MySqlPathLeg::Member("a b".to_string()),
+                    MySqlPathLeg::Member("c\"d".to_string()),
+                ])),
+            ),
+            (
+                "$[1][last][ last - 2 ]",
+                Ok(MySqlPath(vec![
+                    MySqlPathLeg::Index(JsonPathIndex::NthLefth(1)),
+                    MySqlPathLeg::Index(JsonPathIndex::NthRight(1)),
+                    MySqlPathLeg::Index(JsonPathIndex::NthRight(3)),
+                ])),
+            ),
+            (
+                "$[1 to 3][last-2 to last]",
+                Ok(MySqlPath(vec![
+                    MySqlPathLeg::Range(JsonPathIndex::NthLefth(1), JsonPathIndex::NthLefth(3)),
+                    MySqlPathLeg::Range(JsonPathIndex::NthRight(3), JsonPathIndex::NthRight(1)),
+                ])),
+            ),
+            (
+                "$.*[*]**.a",
+                Ok(MySqlPath(vec![
+                    MySqlPathLeg::MemberWildcard,
+                    MySqlPathLeg::IndexWildcard,
+                    MySqlPathLeg::DoubleWildcard,
+                    MySqlPathLeg::Member("a".to_string()),
+                ])),
+            ),
+            // Invalid
+            ("a", Err("expected $")),
+            ("$.1", Err("expected member name")),
+            (r#"$."a"#, Err("expected \"")),
+            ("$[1", Err("expected ]")),
+            ("$[-1]", Err("expected array index")),
+            ("$[3 to 1]", Err("invalid array range")),
+            ("$[last to last-1]", Err("invalid array range")),
+            ("$**", Err("path must not end with **")),
+            ("$*.a", Err("expected **")),
+            ("$a", Err("expected ., [ or **")),
+        ];
+        for (path, expected) in tests {
+            assert_eq!(
+                path.try_into(),
+                expected,
+                "expected {} to be {:?}",
+                path,
+                expected
+            );
+        }
+    }
+
+    #[test]
+    fn to_string() {
+        let tests = vec![
+            "$",
+            "$.a[0]",
+            r#"$."a b"[last]"#,
+            "$[last-2 to last].*[*]**.c",
+        ];
+        for path in tests {
+            let parsed: MySqlPath = path.parse().unwrap();
+            assert_eq!(parsed.to_string(), path);
+        }
+    }
+
+    #[test]
+    fn resolve() {
+        let value = json!({"a": [1, {"b": 2}, [3]], "b": {"b": 4}, "c": 5});
+        let tests = vec![
+            ("$.a[1].b", vec!["$.a[1].b"]),
+            ("$.a[last][0]", vec!["$.a[2][0]"]),
+            ("$.c[0]", vec!["$.c"]),
+            ("$.c[1]", vec![]),
+            ("$.a[1 to 5]", vec!["$.a[1]", "$.a[2]"]),
+            (
+                "$.a[0 to 18446744073709551615]",
+                vec!["$.a[0]", "$.a[1]", "$.a[2]"],
+            ),
+            ("$.*", vec!["$.a", "$.b", "$.c"]),
+            ("$.a[*].b", vec!["$.a[1].b"]),
+            ("$**.b", vec!["$.b", "$.a[1].b", "$.b.b"]),
+            ("$.d", vec![]),
+        ];
+        for (path, expected) in tests {
+            let result: Vec<String> = MySqlPath::from_str(path)
+                .unwrap()
+                .resolve(&value)
+                .iter()
+                .map(|p| MySqlPath::from(p).to_string())
+                .collect();
+            assert_eq!(
+                result, expected,
+                "expected {} to resolve to {:?}",
+                path, expected
+            );
+        }
+    }
+
+    #[test]
+    fn array_append() {
+        let tests = vec![
+            (
+                vec![("$[1]", json!(1))],
+                Ok(json!(["a", ["b", "c", 1], "d"])),
+            ),
+            (
+                vec![("$[0]", json!(2))],
+                Ok(json!([["a", 2], ["b", "c"], "d"])),
+            ),
+            (
+                vec![("$[1][0]", json!(3)), ("$[1]", json!(4))],
+                Ok(json!(["a", [["b", 3], "c", 4], "d"])),
+            ),
+            (vec![("$[5]", json!(1))], Ok(json!(["a", ["b", "c"], "d"]))),
+            (vec![("$[*]", json!(1))], Err(WILDCARD_ERROR)),
+        ];
+        for (pairs, expected) in tests {
+            let mut doc = json!(["a", ["b", "c"], "d"]);
+            let result = json_array_append(&mut doc, pairs.clone()).map(|_| doc);
+            assert_eq!(
+                result, expected,
+                "expected {:?} to be {:?}",
+                pairs, expected
+            );
+        }
+    }
+
+    #[test]
+    fn array_insert() {
+        let tests = vec![
+            (
+                vec![("$[1]", json!("x"))],
+                Ok(json!(["a", "x", {"b": [1, 2]}, [3, 4]])),
+            ),
+            (
+                vec![("$[100]", json!("x"))],
+                Ok(json!(["a", {"b": [1, 2]}, [3, 4], "x"])),
+            ),
+            (
+                vec![("$[last]", json!("x"))],
+                Ok(json!(["a", {"b": [1, 2]}, "x", [3, 4]])),
+            ),
+            (
+                vec![("$[1].b[0]", json!("x")), ("$[2][1]", json!("y"))],
+                Ok(json!(["a", {"b": ["x", 1, 2]}, [3, "y", 4]])),
+            ),
+            (
+                vec![("$[1].b.c[0]", json!("x"))],
+                Ok(json!(["a", {"b": [1, 2]}, [3, 4]])),
+            ),
+            (
+                vec![("$[1][0]", json!("x"))],
+                Ok(json!(["a", {"b": [1, 2]}, [3, 4]])),
+            ),
+            (
+                vec![("$[1].b", json!("x"))],
+                Err("path is not a path to a cell in an array"),
+            ),
+        ];
+        for (pairs, expected) in tests {
+            let mut doc = json!(["a", {"b": [1, 2]}, [3, 4]]);
+            let result = json_array_insert(&mut doc, pairs.clone()).map(|_| doc);
+            assert_eq!(
+                result, expected,
+                "expected {:?} to be {:?}",
+                pairs, expected
+            );
+        }
+    }
+
+    #[test]
+    fn merge_preserve() {
+        let tests = vec![
+            (
+                vec![json!([1, 2]), json!([true, false])],
+                Some(json!([1, 2, true, false])),
+            ),
+            (
+                vec![json!({"name": "x"}), json!({"id": 47})],
+                Some(json!({"id": 47, "name": "x"})),
+            ),
+            (vec![json!(1), json!(true)], Some(json!([1, true]))),
+            (
+                vec![json!([1, 2]), json!({"id": 47})],
+                Some(json!([1, 2, {"id": 47}])),
+            ),
+            (
+                vec![
+                    json!({"a": 1, "b": 2}),
+                    json!({"a": 3, "c": 4}),
+                    json!({"a": 5, "d": 6}),
+                ],
+                Some(json!({"a": [1, 3, 5], "b": 2, "c": 4, "d": 6})),
+            ),
+            (vec![], None),
+        ];
+        for (docs, expected) in tests {
+            let result = json_merge_preserve(docs.clone());
+            assert_eq!(result, expected, "expected {:?} to be {:?}", docs, expected);
+        }
+    }
+
+    #[test]
+    fn merge_patch() {
+        let tests = vec![
+            (
+                vec![json!([1, 2]), json!([true, false])],
+                Some(json!([true, false])),
+            ),
+            (
+                vec![json!({"name": "x"}), json!({"id": 47})],
+                Some(json!({"id": 47, "name": "x"})),
+            ),
+            (vec![json!(1), json!(true)], Some(json!(true))),
+            (
+                vec![json!([1, 2]), json!({"id": 47})],
+                Some(json!({"id": 47})),
+            ),
+            (
+                vec![
+                    json!({"a": 1, "b": 2}),
+                    json!({"a": 3, "c": 4}),
+                    json!({"a": 5, "d": 6}),
+                ],
+                Some(json!({"a": 5, "b": 2, "c": 4, "d": 6})),
+            ),
+            (
+                vec![json!({"a": 1, "b": 2}), json!({"b": null})],
+                Some(json!({"a": 1})),
+            ),
+            (
+                vec![
+                    json!({"a": {"x": 1}}),
+                    json!({"a": {"x": null, "y": {"z": null}}}),
+                ],
+                Some(json!({"a": {"y": {}}})),
+            ),
+        ];
+        for (docs, expected) in tests {
+            let result = json_merge_patch(docs.clone());
+            assert_eq!(result, expected, "expected {:?} to be {:?}", docs, expected);
+        }
+    }
+
+    #[test]
+    fn contains() {
+        let target =
+            json!({"a": 1, "b": 2, "c": {"d": 4}, "e": [1, [2, 3]], "f": 9007199254740993u64});
+        let tests = vec![
+            (json!(1), Some("$.a"), Ok(Some(true))),
+            (json!(1.0), Some("$.a"), Ok(Some(true))),
+            (json!("1"), Some("$.a"), Ok(Some(false))),
+            (json!(1), Some("$.b"), Ok(Some(false))),
+            (json!({"d": 4}), Some("$.c"), Ok(Some(true))),
+            (json!({"a": 1, "c": {}}), None, Ok(Some(true))),
+            (json!({"a": 1, "c": {"e": 5}}), None, Ok(Some(false))),
+            (json!([3, 1]), Some("$.e"), Ok(Some(true))),
+            (json!([[3]]), Some("$.e"), Ok(Some(true))),
+            (json!(9007199254740992.0), Some("$.f"), Ok(Some(false))),
+            (json!(9007199254740993u64), Some("$.f"), Ok(Some(true))),
+            (json!(1), Some("$.x"), Ok(None)),
+            (json!(1), Some("$.*"), Err(WILDCARD_ERROR)),
+        ];
+        for (candidate, path, expected) in tests {
+            let result = json_contains(&target, &candidate, path);
+            assert_eq!(
+                result, expected,
+                "expected {} at {:?} to be {:?}",
+                candidate, path, expected
+            );
+        }
+    }
+
+    #[test]
+    fn contains_path() {
+        let doc = json!({"a": 1, "b": 2, "c": {"d": 4}});
+        let tests = vec![
+            ("one", vec!["$.a", "$.e"], Ok(true)),
+            ("all", vec!["$.a", "$.e"], Ok(false)),
+            ("ALL", vec!["$.a", "$.c.d"], Ok(true)),
+            ("one", vec!["$**.d"], Ok(true)),
+            ("one", vec!["$.c[0].d"], Ok(true)),
+            ("any", vec!["$.a"], Err("expected 'one' or 'all'")),
+            ("one", vec!["$.a", "a"], Err("expected $")),
+        ];
+        for (one_or_all, paths, expected) in tests {
+            let result = json_contains_path(&doc, one_or_all, &paths);
+            assert_eq!(
+                result, expected,
+                "expected {} {:?} to be {:?}",
+                one_or_all, paths, expected
+            );
+        }
+    }
+
+    #[test]
+    fn search() {
+        let doc = json!(["abc", [{"k": "10"}, "def"], {"x": "abc"}, {"y": "bcd"}]);
+        let tests = vec![
+            ("one", "abc", None, vec![], Ok(Some(json!("$[0]")))),
+            (
+                "all",
+                "abc",
+                None,
+                vec![],
+                Ok(Some(json!(["$[0]", "$[2].x"]))),
+            ),
+            ("all", "ghi", None, vec![], Ok(None)),
+            ("all", "10", None, vec![], Ok(Some(json!("$[1][0].k")))),
+            (
+                "all",
+                "10",
+                None,
+                vec!["$[*]"],
+                Ok(Some(json!("$[1][0].k"))),
+            ),
+            (
+                "all",
+                "abc",
+                None,
+                vec!["$**.x", "$[2]"],
+                Ok(Some(json!("$[2].x"))),
+            ),
+            (
+                "all",
+                "%a%",
+                None,
+                vec![],
+                Ok(Some(json!(["$[0]", "$[2].x"]))),
+            ),
+            ("all", "%b%", None, vec!["$[3]"], Ok(Some(json!("$[3].y")))),
+            ("all", "1|_", Some('|'), vec![], Ok(None)),
+            ("any", "abc", None, vec![], Err("expected 'one' or 'all'")),
+        ];
+        for (one_or_all, search_str, escape, paths, expected) in tests {
+            let result = json_search(&doc, one_or_all, search_str, escape, &paths);
+            assert_eq!(
+                result, expected,
+                "expected {} {} {:?} to be {:?}",
+                one_or_all, search_str, paths, expected
+            );
+        }
+    }
+
+    #[test]
+    fn depth() {
+        let tests = vec![
+            (json!({}), 1),
+            (json!([]), 1),
+            (json!(true), 1),
+            (json!([10, 20]), 2),
+            (json!([[], {}]), 2),
+            (json!([10, {"a": 20}]), 3),
+        ];
+        for (doc, expected) in tests {
+            assert_eq!(json_depth(&doc), expected, "expected depth of {}", doc);
+        }
+    }
+
+    #[test]
+    fn keys() {
+        let doc = json!({"b": 1, "aa": 2, "a": {"c": 3}, "d": [1]});
+        let tests = vec![
+            (None, Ok(Some(json!(["a", "b", "d", "aa"])))),
+            (Some("$.a"), Ok(Some(json!(["c"])))),
+            (Some("$.d"), Ok(None)),
+            (Some("$.x"), Ok(None)),
+            (Some("$[*]"), Err(WILDCARD_ERROR)),
+        ];
+        for (path, expected) in tests {
+            assert_eq!(
+                json_keys(&doc, path),
+                expected,
+                "expected keys at {:?}",
+                path
+            );
+        }
+    }
+
+    #[test]
+    fn overlaps() {
+        let tests = vec![
+            (json!([1, 3, 5, 7]), json!([2, 5, 7]), true),
+            (json!([1, 3, 5, 7]), json!([2, 6, 8]), false),
+            (
+                json!([[1, 2], [3, 4], 5]),
+                json!([1, [2, 3], [4, 5]]),
+                false,
+            ),
+            (json!({"a": 1, "b": 10}), json!({"c": 1, "b": 10.0}), true),
+            (json!({"a": 1}), json!({"a": 2}), false),
+            (json!(5), json!([1, 5]), true),
+            (json!([4, 5, "6"]), json!(6), false),
+            (json!(1), json!(1.0), true),
+        ];
+        for (a, b, expected) in tests {
+            assert_eq!(
+                json_overlaps(&a, &b),
+                expected,
+                "expected {} overlaps {}",
+                a,
+                b
+            );
+            assert_eq!(
+                json_overlaps(&b, &a),
+                expected,
+                "expected {} overlaps {}",
+                b,
+                a
+            );
+        }
+    }
+}
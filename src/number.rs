@@ -0,0 +1,300 @@
+use std::{
+    cmp::Ordering,
+    hash::{Hash, Hasher},
+};
+
+use serde_json::Number;
+
+// A number whose value is known exactly as an integer or a float. Floats without a fractional
+// part are integers, so 1 and 1.0 are equal, and every remaining float is either below 2^53 in
+// magnitude or too large for an i128.
+#[derive(Clone, Copy)]
+enum Exact {
+    Int(i128),
+    Float(f64),
+}
+
+// Floats of at least 2^127 in magnitude don't fit into an i128.
+const I128_BOUND: f64 = 170141183460469231731687303715884105728.0;
+
+impl Exact {
+    // None if n is only known as text, with serde_json's arbitrary_precision.
+    fn of(n: &Number) -> Option<Self> {
+        if let Some(i) = n.as_i64() {
+            Some(Exact::Int(i128::from(i)))
+        } else if let Some(u) = n.as_u64() {
+            Some(Exact::Int(i128::from(u)))
+        } else if cfg!(feature = "arbitrary_precision") {
+            None
+        } else {
+            // Without arbitrary_precision other numbers are f64, which are never NaN or infinite.
+            n.as_f64().map(Exact::from_f64)
+        }
+    }
+
+    fn from_f64(f: f64) -> Self {
+        if f.fract() == 0.0 && f.abs() < I128_BOUND {
+            Exact::Int(f as i128)
+        } else {
+            Exact::Float(f)
+        }
+    }
+
+    fn cmp(self, other: Self) -> Ordering {
+        match (self, other) {
+            (Exact::Int(a), Exact::Int(b)) => a.cmp(&b),
+            (Exact::Float(a), Exact::Float(b)) => a.total_cmp(&b),
+            // The float has a fractional part or is beyond any integer, so rounding the integer
+            // to the nearest f64 can't change the order or make both equal.
+            (Exact::Int(a), Exact::Float(b)) => (a as f64).total_cmp(&b),
+            (Exact::Float(a), Exact::Int(b)) => a.total_cmp(&(b as f64)),
+        }
+    }
+}
+
+// A decimal number read from JSON number text, 0.d1d2d3... * 10^exponent. The digits have no
+// leading or trailing zeros, zero has no digits and is never negative.
+#[derive(PartialEq, Eq, Hash)]
+struct Decimal {
+    negative: bool,
+    digits: Vec<u8>,
+    exponent: i128,
+}
+
+impl Decimal {
+    fn parse(text: &str) -> Self {
+        let (negative, text) = match text.strip_prefix('-') {
+            Some(rest) => (true, rest),
+            None => (false, text),
+        };
+        let (mantissa, exp) = match text.find(['e', 'E']) {
+            Some(i) => (&text[..i], exponent(&text[i + 1..])),
+            None => (text, 0),
+        };
+        let (int, fraction) = mantissa.split_once('.').unwrap_or((mantissa, ""));
+        let all: Vec<u8> = int
+            .bytes()
+            .chain(fraction.bytes())
+            .map(|b| b - b'0')
+            .collect();
+        let leading = all.iter().take_while(|d| **d == 0).count();
+        let mut digits = all[leading..].to_vec();
+        while digits.last() == Some(&0) {
+            digits.pop();
+        }
+        if digits.is_empty() {
+            return Decimal {
+                negative: false,
+                digits,
+                exponent: 0,
+            };
+        }
+        Decimal {
+            negative,
+            digits,
+            exponent: (int.len() as i128 - leading as i128).saturating_add(exp),
+        }
+    }
+
+    fn cmp(&self, other: &Self) -> Ordering {
+        let sign = |d: &Decimal| match (d.negative, d.digits.is_empty()) {
+            (_, true) => 0,
+            (true, _) => -1,
+            (false, _) => 1,
+        };
+        let magnitude = || {
+            self.exponent
+                .cmp(&other.exponent)
+                .then_with(|| self.digits.cmp(&other.digits))
+        };
+        match sign(self).cmp(&sign(other)) {
+            Ordering::Equal if self.negative => magnitude().reverse(),
+            Ordering::Equal if !self.digits.is_empty() => magnitude(),
+            ordering => ordering,
+        }
+    }
+
+    // The value if it is an integer which fits into an i128.
+    fn to_i128(&self) -> Option<i128> {
+        let exponent = usize::try_from(self.exponent).ok()?;
+        if exponent < self.digits.len() || exponent > 39 {
+            return None;
+        }
+        let mut n: i128 = 0;
+        for i in 0..exponent {
+            let digit = i128::from(self.digits.get(i).copied().unwrap_or(0));
+            n = n.checked_mul(10)?.checked_add(digit)?;
+        }
+        Some(if self.negative { -n } else { n })
+    }
+}
+
+// Reads the exponent of JSON number text, saturating exponents beyond an i128.
+fn exponent(text: &str) -> i128 {
+    let (negative, digits) = match text.strip_prefix('-') {
+        Some(digits) => (true, digits),
+        None => (false, text.strip_prefix('+').unwrap_or(text)),
+    };
+    match digits.parse::<i128>() {
+        Ok(e) if negative => -e,
+        Ok(e) => e,
+        Err(_) if negative => i128::MIN,
+        Err(_) => i128::MAX,
+    }
+}
+
+/// Compares the exact values of two numbers.
+///
+/// Integers and floats are compared without converting either, so `u64::MAX` is less than
+/// `18446744073709551616.0` and `2^53 + 1` is greater than `2^53` as a float. `1` equals `1.0`.
+/// With the `arbitrary_precision` feature numbers of any size and precision are compared by their
+/// decimal text. Floats are then their shortest decimal text, not their exact binary value, so
+/// `-9223372036854775808.0` created from an f64 is `-9.223372036854776e18` and greater than
+/// `i64::MIN`.
+pub fn cmp_numbers(a: &Number, b: &Number) -> Ordering {
+    match (Exact::of(a), Exact::of(b)) {
+        (Some(a), Some(b)) => a.cmp(b),
+        _ => Decimal::parse(&a.to_string()).cmp(&Decimal::parse(&b.to_string())),
+    }
+}
+
+// Hashes n consistently with cmp_numbers, numbers which compare equal hash equally.
+pub(crate) fn hash_number<H: Hasher>(n: &Number, state: &mut H) {
+    let exact = Exact::of(n).ok_or_else(|| Decimal::parse(&n.to_string()));
+    let exact = exact.or_else(|d| d.to_i128().map(Exact::Int).ok_or(d));
+    match exact {
+        Ok(Exact::Int(i)) => i.hash(state),
+        Ok(Exact::Float(f)) => f.to_bits().hash(state),
+        Err(decimal) => decimal.hash(state),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::hash_map::DefaultHasher;
+
+    use super::*;
+
+    use Ordering::{Equal as E, Greater as G, Less as L};
+
+    fn number(text: &str) -> Number {
+        serde_json::from_str(text).unwrap()
+    }
+
+    fn hash(n: &Number) -> u64 {
+        let mut state = DefaultHasher::new();
+        hash_number(n, &mut state);
+        state.finish()
+    }
+
+    #[test]
+    fn test_cmp_numbers() {
+        let tests = vec![
+            ("1", "1.0", E),
+            ("0", "-0.0", E),
+            ("-1", "18446744073709551615", L),
+            ("-9223372036854775808", "18446744073709551615", L),
+            ("18446744073709551615", "18446744073709551616.0", L),
+            ("9223372036854775808", "9223372036854775808.0", E),
+            ("9223372036854775807", "9223372036854775808.0", L),
+            ("9007199254740993", "9007199254740992.0", G),
+            ("-9007199254740993", "-9007199254740992.0", L),
+            ("0.5", "1", L),
+            ("-0.5", "-1", G),
+            ("2.5", "2", G),
+            ("1e300", "18446744073709551615", G),
+            ("-1e300", "-9223372036854775808", L),
+            ("1.5", "1.25", G),
+        ];
+        for (a, b, expected) in tests {
+            let (na, nb) = (number(a), number(b));
+            assert_eq!(
+                cmp_numbers(&na, &nb),
+                expected,
+                "expected {} compared to {} to be {:?}",
+                a,
+                b,
+                expected
+            );
+            assert_eq!(
+                cmp_numbers(&nb, &na),
+                expected.reverse(),
+                "expected {} compared to {} to be {:?}",
+                b,
+                a,
+                expected.reverse()
+            );
+            if expected == E {
+                assert_eq!(
+                    hash(&na),
+                    hash(&nb),
+                    "expected {} and {} to hash equally",
+                    a,
+                    b
+                );
+            }
+        }
+    }
+
+    #[cfg(feature = "arbitrary_precision")]
+    #[test]
+    fn test_arbitrary_precision() {
+        let tests = vec![
+            (
+                "123456789012345678901234567890",
+                "123456789012345678901234567891",
+                L,
+            ),
+            (
+                "123456789012345678901234567890",
+                "1.2345678901234567890123456789e29",
+                E,
+            ),
+            ("0.1000000000000000000001", "0.1", G),
+            ("-0.1000000000000000000001", "-0.1", L),
+            ("1e400", "1e399", G),
+            ("1e400", "10e399", E),
+            ("-1e400", "1", L),
+            ("1.0", "1", E),
+            ("100", "1e2", E),
+            ("0.00", "-0e10", E),
+            ("99.99", "100", L),
+            ("18446744073709551616", "18446744073709551615", G),
+            ("19.99", "1999e-2", E),
+            ("1e99999999999999999999", "2", G),
+            ("1e99999999999999999999", "1e99999999999999999998", G),
+            ("-1e99999999999999999999", "-2", L),
+            ("1e-99999999999999999999", "0.5", L),
+            ("1e-99999999999999999999", "0", G),
+            ("1E+2", "100", E),
+        ];
+        for (a, b, expected) in tests {
+            let (na, nb) = (number(a), number(b));
+            assert_eq!(
+                cmp_numbers(&na, &nb),
+                expected,
+                "expected {} compared to {} to be {:?}",
+                a,
+                b,
+                expected
+            );
+            assert_eq!(
+                cmp_numbers(&nb, &na),
+                expected.reverse(),
+                "expected {} compared to {} to be {:?}",
+                b,
+                a,
+                expected.reverse()
+            );
+            if expected == E {
+                assert_eq!(
+                    hash(&na),
+                    hash(&nb),
+                    "expected {} and {} to hash equally",
+                    a,
+                    b
+                );
+            }
+        }
+    }
+}
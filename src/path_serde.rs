@@ -0,0 +1,251 @@
+use std::fmt;
+
+use serde::{
+    de::{self, SeqAccess, Visitor},
+    Deserialize, Deserializer, Serialize, Serializer,
+};
+
+use crate::json_path::{self, JsonPath, JsonPathElement, JsonPathIndex};
+
+// A JsonPath serializes as its string form and deserializes from a string or an array of
+// segments. Fields are strings, indices are integers and right indices are strings like "#-1".
+// Fields starting with # are escaped with another #, so the field "#-1" is the segment "##-1".
+
+impl Serialize for JsonPath {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.collect_str(self)
+    }
+}
+
+impl Serialize for JsonPathElement {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        match self {
+            JsonPathElement::Field(key) if key.starts_with('#') => {
+                serializer.collect_str(&format_args!("#{key}"))
+            }
+            JsonPathElement::Field(key) => serializer.serialize_str(key),
+            JsonPathElement::Index(index) => index.serialize(serializer),
+        }
+    }
+}
+
+impl Serialize for JsonPathIndex {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        match self {
+            JsonPathIndex::NthLefth(i) => serializer.serialize_u64(*i as u64),
+            JsonPathIndex::NthRight(i) => serializer.collect_str(&format_args!("#-{i}")),
+        }
+    }
+}
+
+// Parses "#" or "#-N", None if s is not a right index.
+fn parse_right(s: &str) -> Option<Result<usize, &'static str>> {
+    let rest = s.strip_prefix('#')?;
+    if rest.is_empty() {
+        return Some(Ok(0));
+    }
+    Some(
+        rest.strip_prefix('-')
+            .and_then(|n| n.parse().ok())
+            .ok_or("#-N with a non-negative N"),
+    )
+}
+
+fn index<E: de::Error>(i: u64) -> Result<usize, E> {
+    usize::try_from(i).map_err(|_| E::invalid_value(de::Unexpected::Unsigned(i), &"an index"))
+}
+
+struct PathVisitor;
+
+impl<'de> Visitor<'de> for PathVisitor {
+    type Value = JsonPath;
+
+    fn expecting(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str("a JsonPath string or an array of path segments")
+    }
+
+    fn visit_str<E: de::Error>(self, v: &str) -> Result<JsonPath, E> {
+        json_path::parse_at(v).map_err(|(pos, err)| {
+            E::custom(format_args!(
+                "invalid JsonPath {v:?} at position {pos}: {err}"
+            ))
+        })
+    }
+
+    fn visit_seq<A: SeqAccess<'de>>(self, mut seq: A) -> Result<JsonPath, A::Error> {
+        let mut out = Vec::with_capacity(seq.size_hint().unwrap_or(0));
+        loop {
+            match seq.next_element::<JsonPathElement>() {
+                Ok(Some(e)) => out.push(e),
+                Ok(None) => return Ok(JsonPath::from(out)),
+                Err(err) => {
+                    let i = out.len();
+                    return Err(de::Error::custom(format_args!(
+                        "invalid segment {i}: {err}"
+                    )));
+                }
+            }
+        }
+    }
+}
+
+impl<'de> Deserialize<'de> for JsonPath {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        deserializer.deserialize_any(PathVisitor)
+    }
+}
+
+struct ElementVisitor;
+
+impl<'de> Visitor<'de> for ElementVisitor {
+    type Value = JsonPathElement;
+
+    fn expecting(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str("a key, an index or a right index like \"#-1\"")
+    }
+
+    fn visit_str<E: de::Error>(self, v: &str) -> Result<JsonPathElement, E> {
+        if v.starts_with("##") {
+            return Ok(JsonPathElement::Field(v[1..].to_string()));
+        }
+        match parse_right(v) {
+            Some(Ok(i)) => Ok(JsonPathElement::Index(JsonPathIndex::NthRight(i))),
+            Some(Err(err)) => Err(E::invalid_value(de::Unexpected::Str(v), &err)),
+            None => Ok(JsonPathElement::Field(v.to_string())),
+        }
+    }
+
+    fn visit_u64<E: de::Error>(self, v: u64) -> Result<JsonPathElement, E> {
+        index(v).map(|i| JsonPathElement::Index(JsonPathIndex::NthLefth(i)))
+    }
+
+    fn visit_i64<E: de::Error>(self, v: i64) -> Result<JsonPathElement, E> {
+        match u64::try_from(v) {
+            Ok(v) => self.visit_u64(v),
+            Err(_) => Err(E::invalid_value(
+                de::Unexpected::Signed(v),
+                &"a non-negative index",
+            )),
+        }
+    }
+}
+
+impl<'de> Deserialize<'de> for JsonPathElement {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        deserializer.deserialize_any(ElementVisitor)
+    }
+}
+
+impl<'de> Deserialize<'de> for JsonPathIndex {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        match JsonPathElement::deserialize(deserializer)? {
+            JsonPathElement::Index(index) => Ok(index),
+            JsonPathElement::Field(key) => Err(de::Error::invalid_value(
+                de::Unexpected::Str(&key),
+                &"an index or a right index like \"#-1\"",
+            )),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::*;
+
+    #[test]
+    fn test_serialize() {
+        let tests = vec![
+            ("$", json!("$")),
+            ("$.a[0][#-1]", json!("$.a[0][#-1]")),
+            ("$.\"a b\"", json!("$.\"a b\"")),
+        ];
+        for (path, expected) in tests {
+            let parsed: JsonPath = path.try_into().unwrap();
+            let result = serde_json::to_value(&parsed).unwrap();
+            assert_eq!(result, expected, "expected {} to be {}", path, expected);
+            let back: JsonPath = serde_json::from_value(result).unwrap();
+            assert_eq!(back, parsed, "expected {} to deserialize", expected);
+        }
+
+        let segments: Vec<JsonPathElement> = "$.a[0][#-1]".parse::<JsonPath>().unwrap().to_vec();
+        assert_eq!(
+            serde_json::to_value(segments).unwrap(),
+            json!(["a", 0, "#-1"])
+        );
+        let segments = vec![
+            JsonPathElement::Field("#".to_string()),
+            JsonPathElement::Field("#-1".to_string()),
+            JsonPathElement::Field("##x".to_string()),
+            JsonPathElement::Field("a#".to_string()),
+            JsonPathElement::Index(JsonPathIndex::NthRight(1)),
+            JsonPathElement::Index(JsonPathIndex::NthLefth(2)),
+        ];
+        let value = serde_json::to_value(&segments).unwrap();
+        assert_eq!(value, json!(["##", "##-1", "###x", "a#", "#-1", 2]));
+        let back: Vec<JsonPathElement> = serde_json::from_value(value).unwrap();
+        assert_eq!(back, segments);
+        let path = JsonPath::from(segments);
+        let back: JsonPath = serde_json::from_value(serde_json::to_value(&path).unwrap()).unwrap();
+        assert_eq!(back, path);
+    }
+
+    #[test]
+    fn test_deserialize() {
+        let tests = vec![
+            (json!(["a", 0, "#-1"]), Ok("$.a[0][#-1]")),
+            (json!(["a b", "#"]), Ok("$.\"a b\"[#-0]")),
+            (json!([]), Ok("$")),
+            (json!("$.a[#-2]"), Ok("$.a[#-2]")),
+            (
+                json!("$.a[1"),
+                Err("invalid JsonPath \"$.a[1\" at position 5: expected ]"),
+            ),
+            (
+                json!("$.a?b"),
+                Err("invalid JsonPath \"$.a?b\" at position 3: expected . or ["),
+            ),
+            (
+                json!("a"),
+                Err("invalid JsonPath \"a\" at position 0: expected $ or numeric"),
+            ),
+            (
+                json!(["a", -1]),
+                Err(concat!(
+                    "invalid segment 1: invalid value: integer `-1`, ",
+                    "expected a non-negative index"
+                )),
+            ),
+            (
+                json!(["#-x"]),
+                Err(concat!(
+                    "invalid segment 0: invalid value: string \"#-x\", ",
+                    "expected #-N with a non-negative N"
+                )),
+            ),
+            (
+                json!(["a", 1.5]),
+                Err(concat!(
+                    "invalid segment 1: invalid type: floating point `1.5`, ",
+                    "expected a key, an index or a right index like \"#-1\""
+                )),
+            ),
+            (
+                json!(1),
+                Err(concat!(
+                    "invalid type: integer `1`, ",
+                    "expected a JsonPath string or an array of path segments"
+                )),
+            ),
+        ];
+        for (value, expected) in tests {
+            let result =
+                serde_json::from_value::<JsonPath>(value.clone()).map_err(|e| e.to_string());
+            let expected = expected
+                .map(|p| p.parse::<JsonPath>().unwrap())
+                .map_err(str::to_string);
+            assert_eq!(result, expected, "expected {} to be {:?}", value, expected);
+        }
+    }
+}
@@ -0,0 +1,248 @@
+use crate::{
+    json_like::JsonLike,
+    json_path::{
+        self, JsonPath, JsonPathElement, JsonPathLike, JsonPathRef, MutationError, MutationOutcome,
+    },
+};
+
+// The outcome of setting one path of a PathSet, None if no value was given for it.
+type SetOutcome<V> = Option<Result<MutationOutcome<V>, MutationError>>;
+
+#[derive(Debug, Default, Clone)]
+struct Node {
+    ids: Vec<usize>, // ids of the paths ending here
+    children: Vec<(JsonPathElement, Node)>,
+}
+
+/// Many JsonPaths sharing their prefixes, applied to a value in a single traversal.
+///
+/// Paths are identified by the position in which they were added.
+#[derive(Debug, Default, Clone)]
+pub struct PathSet {
+    root: Node,
+    len: usize,
+}
+
+impl PathSet {
+    pub fn new() -> Self {
+        PathSet::default()
+    }
+
+    /// Adds a path and returns its id.
+    pub fn push<P: JsonPathLike + ?Sized>(&mut self, path: &P) -> usize {
+        let mut node = &mut self.root;
+        for e in json_path::elements(path) {
+            let i = match node
+                .children
+                .iter()
+                .position(|(child, _)| child.borrowed() == e)
+            {
+                Some(i) => i,
+                None => {
+                    node.children.push((e.into_owned(), Node::default()));
+                    node.children.len() - 1
+                }
+            };
+            node = &mut node.children[i].1;
+        }
+        let id = self.len;
+        node.ids.push(id);
+        self.len += 1;
+        id
+    }
+
+    /// Returns the number of paths.
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    /// Finds the values of all paths, the result is indexed by path id.
+    pub fn find<'a, V: JsonLike>(&self, value: &'a V) -> Vec<Option<&'a V>> {
+        let mut out = vec![None; self.len];
+        find(&self.root, value, &mut out);
+        out
+    }
+
+    /// Sets the values of many paths like `JsonPath::set`, each value is given with its path id.
+    ///
+    /// Shorter paths are set before longer paths inside them. Returns for each path id the outcome
+    /// `JsonPath::set` would have, or None if no value was given for the path.
+    pub fn set<V: JsonLike>(
+        &self,
+        value: &mut V,
+        values: impl IntoIterator<Item = (usize, V)>,
+    ) -> Vec<SetOutcome<V>> {
+        let mut pending: Vec<Option<V>> = (0..self.len).map(|_| None).collect();
+        for (id, v) in values {
+            if let Some(slot) = pending.get_mut(id) {
+                *slot = Some(v);
+            }
+        }
+        let mut out: Vec<SetOutcome<V>> = (0..self.len).map(|_| None).collect();
+        set(&self.root, value, &mut pending, &mut out);
+        // Values left over belong to the root or to paths whose parent is missing.
+        for (slot, v) in out.iter_mut().zip(pending) {
+            if v.is_some() {
+                *slot = Some(Err(MutationError::MissingParent));
+            }
+        }
+        out
+    }
+}
+
+impl<'a> FromIterator<&'a JsonPath> for PathSet {
+    fn from_iter<T: IntoIterator<Item = &'a JsonPath>>(iter: T) -> Self {
+        let mut set = PathSet::new();
+        for path in iter {
+            set.push(path);
+        }
+        set
+    }
+}
+
+impl<'a, 'p> FromIterator<&'a JsonPathRef<'p>> for PathSet {
+    fn from_iter<T: IntoIterator<Item = &'a JsonPathRef<'p>>>(iter: T) -> Self {
+        let mut set = PathSet::new();
+        for path in iter {
+            set.push(path);
+        }
+        set
+    }
+}
+
+fn find<'a, V: JsonLike>(node: &Node, value: &'a V, out: &mut [Option<&'a V>]) {
+    for id in &node.ids {
+        out[*id] = Some(value);
+    }
+    for (e, child) in &node.children {
+        if let Some(sub) = json_path::step(value, &e.borrowed()) {
+            find(child, sub, out);
+        }
+    }
+}
+
+fn set<V: JsonLike>(
+    node: &Node,
+    value: &mut V,
+    pending: &mut [Option<V>],
+    out: &mut [SetOutcome<V>],
+) {
+    for (e, child) in &node.children {
+        for id in &child.ids {
+            if let Some(v) = pending[*id].take() {
+                let outcome = json_path::set(value, &e.borrowed(), v);
+                out[*id] = Some(outcome.map(|previous| MutationOutcome { previous }));
+            }
+        }
+        if !child.children.is_empty() {
+            if let Some(sub) = json_path::step_mut(value, &e.borrowed()) {
+                set(child, sub, pending, out);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::*;
+
+    fn paths(paths: &[&str]) -> Vec<JsonPath> {
+        paths.iter().map(|p| p.parse().unwrap()).collect()
+    }
+
+    #[test]
+    fn test_find() {
+        let value = json!({"a": {"b": [1, 2, {"c": 3}], "d": "x"}, "e": null});
+        let paths = paths(&[
+            "$.a.b[0]",
+            "$.a.b[#-1].c",
+            "$.a.d",
+            "$.a.b[0]",
+            "$.e",
+            "$.f",
+            "$",
+            "$.a.d.x",
+        ]);
+        let set: PathSet = paths.iter().collect();
+        assert_eq!(set.len(), paths.len());
+        let result = set.find(&value);
+        for (id, path) in paths.iter().enumerate() {
+            assert_eq!(
+                result[id],
+                path.find(&value),
+                "expected {} to be found like JsonPath::find",
+                path
+            );
+        }
+        let borrowed: Vec<JsonPathRef> = paths.iter().map(JsonPath::borrowed).collect();
+        let set: PathSet = borrowed.iter().collect();
+        assert_eq!(set.find(&value), result);
+    }
+
+    #[test]
+    fn test_set() {
+        let ok = |previous| Some(Ok(MutationOutcome { previous }));
+        let err = |e| Some(Err(e));
+        let paths = paths(&["$.a.b", "$.a", "$.a.c", "$.x.y", "$.l[1]", "$.l[#-1]", "$"]);
+        let set: PathSet = paths.iter().collect();
+        let tests = vec![
+            (
+                vec![(0, json!(1)), (2, json!(2))],
+                json!({"a": {"b": 1, "c": 2}, "l": [0, 1]}),
+                vec![ok(None), None, ok(None), None, None, None, None],
+            ),
+            // $.a is replaced before $.a.b is set inside it
+            (
+                vec![(0, json!(1)), (1, json!({"z": 0}))],
+                json!({"a": {"z": 0, "b": 1}, "l": [0, 1]}),
+                vec![ok(None), ok(Some(json!({}))), None, None, None, None, None],
+            ),
+            (
+                vec![(3, json!(1)), (4, json!(2)), (5, json!(3)), (6, json!(4))],
+                json!({"a": {}, "l": [0, 3]}),
+                vec![
+                    None,
+                    None,
+                    None,
+                    err(MutationError::MissingParent),
+                    ok(Some(json!(1))),
+                    ok(Some(json!(2))),
+                    err(MutationError::MissingParent),
+                ],
+            ),
+            (
+                vec![(0, json!(1)), (1, json!(5))],
+                json!({"a": 5, "l": [0, 1]}),
+                vec![
+                    err(MutationError::TypeMismatch),
+                    ok(Some(json!({}))),
+                    None,
+                    None,
+                    None,
+                    None,
+                    None,
+                ],
+            ),
+        ];
+        for (values, expected, expected_set) in tests {
+            let mut value = json!({"a": {}, "l": [0, 1]});
+            let result = set.set(&mut value, values.clone());
+            assert_eq!(
+                value, expected,
+                "expected {:?} to result in {}",
+                values, expected
+            );
+            assert_eq!(
+                result, expected_set,
+                "expected {:?} to set {:?}",
+                values, expected_set
+            );
+        }
+    }
+}
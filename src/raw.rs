@@ -0,0 +1,299 @@
+use std::{collections::VecDeque, ops::Range};
+
+use serde_json::value::RawValue;
+
+use crate::json_path::{self, JsonPathElementRef, JsonPathIndex, JsonPathLike};
+
+const UNEXPECTED_END: &str = "unexpected end of JSON";
+const INVALID_JSON: &str = "invalid JSON";
+
+/// Finds the value at `path` inside the JSON text without parsing it into a `Value`.
+///
+/// Only the found value is validated, skipped values are only checked for balanced brackets and
+/// strings. Returns the first match if an object contains a key more than once.
+pub fn find_in_str<'a, P: JsonPathLike + ?Sized>(
+    path: &P,
+    json: &'a str,
+) -> Result<Option<&'a RawValue>, &'static str> {
+    match find_span_in_str(path, json)? {
+        Some(span) => serde_json::from_str(&json[span])
+            .map(Some)
+            .map_err(|_| INVALID_JSON),
+        None => Ok(None),
+    }
+}
+
+/// Returns the byte range of the value at `path` inside the JSON text, see `find_in_str`.
+pub fn find_span_in_str<P: JsonPathLike + ?Sized>(
+    path: &P,
+    json: &str,
+) -> Result<Option<Range<usize>>, &'static str> {
+    let mut scanner = Scanner {
+        bytes: json.as_bytes(),
+        pos: 0,
+    };
+    scanner.skip_whitespace();
+    for e in json_path::elements(path) {
+        let found = match e {
+            JsonPathElementRef::Field(key) => scanner.member(&key)?,
+            JsonPathElementRef::Index(JsonPathIndex::NthLefth(i)) => scanner.element(i)?,
+            JsonPathElementRef::Index(JsonPathIndex::NthRight(i)) => scanner.element_right(i)?,
+        };
+        if !found {
+            return Ok(None);
+        }
+    }
+    let start = scanner.pos;
+    scanner.skip_value()?;
+    Ok(Some(start..scanner.pos))
+}
+
+struct Scanner<'a> {
+    bytes: &'a [u8],
+    pos: usize,
+}
+
+impl<'a> Scanner<'a> {
+    fn peek(&self) -> Result<u8, &'static str> {
+        self.bytes.get(self.pos).copied().ok_or(UNEXPECTED_END)
+    }
+
+    fn skip_whitespace(&mut self) {
+        while let Some(b' ' | b'\t' | b'\n' | b'\r') = self.bytes.get(self.pos) {
+            self.pos += 1;
+        }
+    }
+
+    fn expect(&mut self, b: u8) -> Result<(), &'static str> {
+        if self.peek()? == b {
+            self.pos += 1;
+            Ok(())
+        } else {
+            Err(INVALID_JSON)
+        }
+    }
+
+    // Skips a string starting at the quote, returns its contents and whether they contain escapes.
+    fn skip_string(&mut self) -> Result<(&'a [u8], bool), &'static str> {
+        self.expect(b'"')?;
+        let start = self.pos;
+        let mut escaped = false;
+        loop {
+            match self.peek()? {
+                b'"' => {
+                    self.pos += 1;
+                    return Ok((&self.bytes[start..self.pos - 1], escaped));
+                }
+                b'\\' => {
+                    escaped = true;
+                    self.pos += 2;
+                }
+                _ => self.pos += 1,
+            }
+        }
+    }
+
+    // Skips a value, containers are skipped by counting brackets outside of strings.
+    fn skip_value(&mut self) -> Result<(), &'static str> {
+        let mut depth = 0usize;
+        loop {
+            match self.peek()? {
+                b'"' => {
+                    self.skip_string()?;
+                }
+                b'{' | b'[' => {
+                    depth += 1;
+                    self.pos += 1;
+                }
+                b'}' | b']' if depth > 0 => {
+                    depth -= 1;
+                    self.pos += 1;
+                }
+                b'}' | b']' | b',' | b':' if depth == 0 => return Err(INVALID_JSON),
+                b',' | b':' | b' ' | b'\t' | b'\n' | b'\r' => self.pos += 1,
+                _ => {
+                    // Number, true, false or null
+                    let start = self.pos;
+                    while let Some(b'0'..=b'9' | b'a'..=b'z' | b'A'..=b'Z' | b'+' | b'-' | b'.') =
+                        self.bytes.get(self.pos)
+                    {
+                        self.pos += 1;
+                    }
+                    if self.pos == start {
+                        return Err(INVALID_JSON);
+                    }
+                }
+            }
+            if depth == 0 {
+                return Ok(());
+            }
+        }
+    }
+
+    // Skips the separator after a member or element, returns false at the end of the container.
+    fn next_item(&mut self, close: u8) -> Result<bool, &'static str> {
+        self.skip_whitespace();
+        match self.peek()? {
+            b',' => {
+                self.pos += 1;
+                self.skip_whitespace();
+                Ok(true)
+            }
+            b if b == close => {
+                self.pos += 1;
+                Ok(false)
+            }
+            _ => Err(INVALID_JSON),
+        }
+    }
+
+    // Opens a container, returns false if the value is not one or if it is empty.
+    fn open(&mut self, open: u8, close: u8) -> Result<Option<bool>, &'static str> {
+        if self.peek()? != open {
+            return Ok(None);
+        }
+        self.pos += 1;
+        self.skip_whitespace();
+        if self.peek()? == close {
+            self.pos += 1;
+            return Ok(Some(false));
+        }
+        Ok(Some(true))
+    }
+
+    // Moves to the value of key inside an object.
+    fn member(&mut self, key: &str) -> Result<bool, &'static str> {
+        if self.open(b'{', b'}')? != Some(true) {
+            return Ok(false);
+        }
+        loop {
+            let (raw, escaped) = self.skip_string()?;
+            let matches = if escaped {
+                let quoted = &self.bytes[self.pos - raw.len() - 2..self.pos];
+                let quoted = std::str::from_utf8(quoted).map_err(|_| INVALID_JSON)?;
+                serde_json::from_str::<String>(quoted).map_err(|_| INVALID_JSON)? == key
+            } else {
+                raw == key.as_bytes()
+            };
+            self.skip_whitespace();
+            self.expect(b':')?;
+            self.skip_whitespace();
+            if matches {
+                return Ok(true);
+            }
+            self.skip_value()?;
+            if !self.next_item(b'}')? {
+                return Ok(false);
+            }
+        }
+    }
+
+    // Moves to the i-th element of an array.
+    fn element(&mut self, i: usize) -> Result<bool, &'static str> {
+        if self.open(b'[', b']')? != Some(true) {
+            return Ok(false);
+        }
+        for _ in 0..i {
+            self.skip_value()?;
+            if !self.next_item(b']')? {
+                return Ok(false);
+            }
+        }
+        Ok(true)
+    }
+
+    // Moves to the i-th element from the end of an array, remembering the last i elements.
+    fn element_right(&mut self, i: usize) -> Result<bool, &'static str> {
+        if i == 0 || self.open(b'[', b']')? != Some(true) {
+            return Ok(false);
+        }
+        let mut starts = VecDeque::new();
+        loop {
+            if starts.len() == i {
+                starts.pop_front();
+            }
+            starts.push_back(self.pos);
+            self.skip_value()?;
+            if !self.next_item(b']')? {
+                break;
+            }
+        }
+        if starts.len() < i {
+            return Ok(false);
+        }
+        self.pos = starts[0];
+        Ok(true)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::json_path::{JsonPath, JsonPathRef};
+
+    #[test]
+    fn test_find_in_str() {
+        let json = r#" {
+            "a": {"x": [1, "]", {"y": null}]},
+            "b": [10, 20, 30],
+            "c\"d": "quoted",
+            "e": [],
+            "f": "first",
+            "f": "second",
+            "g": true
+        } "#;
+        let tests = vec![
+            ("$", Ok(Some(json.trim()))),
+            ("$.a.x[1]", Ok(Some(r#""]""#))),
+            ("$.a.x[2]", Ok(Some(r#"{"y": null}"#))),
+            ("$.a.x[3]", Ok(None)),
+            ("$.b[#-1]", Ok(Some("30"))),
+            ("$.b[#-3]", Ok(Some("10"))),
+            ("$.b[#-4]", Ok(None)),
+            ("$.b[#]", Ok(None)),
+            ("$.\"c\\\"d\"", Ok(Some(r#""quoted""#))),
+            ("$.e[0]", Ok(None)),
+            ("$.e[#-1]", Ok(None)),
+            ("$.f", Ok(Some(r#""first""#))),
+            ("$.g", Ok(Some("true"))),
+            ("$.g.h", Ok(None)),
+            ("$.h", Ok(None)),
+            ("$[0]", Ok(None)),
+        ];
+        for (path, expected) in tests {
+            let parsed: JsonPath = path.try_into().unwrap();
+            let result = find_in_str(&parsed, json).map(|raw| raw.map(RawValue::get));
+            assert_eq!(result, expected, "expected {} to be {:?}", path, expected);
+            let borrowed = JsonPathRef::parse(path).unwrap();
+            let result = find_in_str(&borrowed, json).map(|raw| raw.map(RawValue::get));
+            assert_eq!(
+                result, expected,
+                "expected borrowed {} to be {:?}",
+                path, expected
+            );
+        }
+    }
+
+    #[test]
+    fn test_invalid() {
+        let tests = vec![
+            ("$.a", r#"{"a": "#, Err(UNEXPECTED_END)),
+            ("$.b", r#"{"a": 1 "b": 2}"#, Err(INVALID_JSON)),
+            ("$.a", r#"{"a": tru}"#, Err(INVALID_JSON)),
+            ("$[2]", "[1, 2", Err(UNEXPECTED_END)),
+            // The text after the found value is not read
+            ("$[1]", "[1, 2", Ok(Some("2"))),
+            // Skipped values are not validated
+            ("$.b", r#"{"a": [nul], "b": 2}"#, Ok(Some("2"))),
+        ];
+        for (path, json, expected) in tests {
+            let parsed: JsonPath = path.try_into().unwrap();
+            let result = find_in_str(&parsed, json).map(|raw| raw.map(RawValue::get));
+            assert_eq!(
+                result, expected,
+                "expected {} in {} to be {:?}",
+                path, json, expected
+            );
+        }
+    }
+}
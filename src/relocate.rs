@@ -0,0 +1,330 @@
+use crate::{
+    json_like::JsonLike,
+    json_path::{
+        self, JsonPathElementRef, JsonPathIndex, JsonPathLike, MutationError, MutationOutcome,
+    },
+};
+
+// Moving, copying and swapping values between paths of the same value.
+//
+// Both paths are resolved against the value before it is changed, `#-N` indices become absolute
+// indices. Every failure is detected while resolving, so a failed operation changes nothing.
+
+// Resolves the first len elements of path against value, the last of them must exist unless
+// vacant is true. A vacant element is a missing key of an object or a position up to the length
+// of an array.
+fn resolve<'p, V: JsonLike, P: JsonPathLike + ?Sized>(
+    path: &'p P,
+    len: usize,
+    value: &V,
+    vacant: bool,
+) -> Result<Vec<JsonPathElementRef<'p>>, MutationError> {
+    let Some(n) = len.checked_sub(1) else {
+        return Ok(Vec::new());
+    };
+    let mut out = Vec::with_capacity(len);
+    let mut value = value;
+    for e in json_path::elements(path).take(n) {
+        let e = match (e, value.as_array()) {
+            (JsonPathElementRef::Index(index), Some(array)) => {
+                let i = json_path::position(index, array.len())
+                    .map_err(|_| MutationError::MissingParent)?;
+                JsonPathElementRef::Index(JsonPathIndex::NthLefth(i))
+            }
+            (e, _) => e,
+        };
+        value = json_path::step(value, &e).ok_or(MutationError::MissingParent)?;
+        out.push(e);
+    }
+    let last = path.element(n).ok_or(MutationError::MissingParent)?;
+    let e = match last {
+        JsonPathElementRef::Field(ref key) => {
+            if !value.is_object() {
+                return Err(MutationError::TypeMismatch);
+            }
+            if !vacant && value.get_key(key).is_none() {
+                return Err(MutationError::MissingKey);
+            }
+            last
+        }
+        JsonPathElementRef::Index(index) => {
+            let len = value.as_array().ok_or(MutationError::TypeMismatch)?.len();
+            let i = json_path::position(index, len)?;
+            if !vacant && i == len {
+                return Err(MutationError::OutOfRange);
+            }
+            JsonPathElementRef::Index(JsonPathIndex::NthLefth(i))
+        }
+    };
+    out.push(e);
+    Ok(out)
+}
+
+// Returns whether dest points inside the value at the resolved path from.
+fn inside<V: JsonLike, P: JsonPathLike + ?Sized>(
+    from: &[JsonPathElementRef],
+    dest: &P,
+    value: &V,
+) -> bool {
+    if dest.depth() <= from.len() {
+        return false;
+    }
+    resolve(dest, from.len(), value, false).is_ok_and(|prefix| prefix == from)
+}
+
+// Adds v at the resolved path like a JSON Patch add, keys are set and array elements inserted.
+fn add<V: JsonLike>(
+    value: &mut V,
+    path: &[JsonPathElementRef],
+    v: V,
+) -> Result<MutationOutcome<V>, MutationError> {
+    let (last, rest) = path.split_last().ok_or(MutationError::MissingParent)?;
+    let target = json_path::find_mut(value, rest.iter().map(JsonPathElementRef::borrowed))
+        .ok_or(MutationError::MissingParent)?;
+    let previous = match last {
+        JsonPathElementRef::Field(_) => json_path::set(target, last, v)?,
+        JsonPathElementRef::Index(_) => json_path::insert(target, last, v)?,
+    };
+    Ok(MutationOutcome { previous })
+}
+
+pub(crate) fn move_to<V, F, D>(
+    from: &F,
+    dest: &D,
+    value: &mut V,
+) -> Result<MutationOutcome<V>, MutationError>
+where
+    V: JsonLike,
+    F: JsonPathLike + ?Sized,
+    D: JsonPathLike + ?Sized,
+{
+    // The root has no parent to remove it from or to add a value to, fail before removing.
+    if from.depth() == 0 || dest.depth() == 0 {
+        return Err(MutationError::MissingParent);
+    }
+    let from = resolve(from, from.depth(), value, false)?;
+    if inside(&from, dest, value) {
+        return Err(MutationError::InsideSource);
+    }
+    let mut dest = resolve(dest, dest.depth(), value, true)?;
+    if dest == from {
+        return Ok(MutationOutcome { previous: None });
+    }
+    // Removing from an array moves the following elements one position to the left.
+    let n = from.len() - 1;
+    if dest.len() > n && dest[..n] == from[..n] {
+        if let (
+            JsonPathElementRef::Index(JsonPathIndex::NthLefth(i)),
+            JsonPathElementRef::Index(JsonPathIndex::NthLefth(d)),
+        ) = (&from[n], &mut dest[n])
+        {
+            if *d > *i {
+                *d -= 1;
+            }
+        }
+    }
+    let (last, rest) = from.split_last().ok_or(MutationError::MissingParent)?;
+    let target = json_path::find_mut(value, rest.iter().map(JsonPathElementRef::borrowed))
+        .ok_or(MutationError::MissingParent)?;
+    let v = json_path::remove(target, last)?.ok_or(MutationError::MissingKey)?;
+    add(value, &dest, v)
+}
+
+pub(crate) fn copy_to<V, F, D>(
+    from: &F,
+    dest: &D,
+    value: &mut V,
+) -> Result<MutationOutcome<V>, MutationError>
+where
+    V: JsonLike + Clone,
+    F: JsonPathLike + ?Sized,
+    D: JsonPathLike + ?Sized,
+{
+    let from = resolve(from, from.depth(), value, false)?;
+    let dest = resolve(dest, dest.depth(), value, true)?;
+    let v = json_path::find(value, from.iter().map(JsonPathElementRef::borrowed))
+        .ok_or(MutationError::MissingParent)?
+        .clone();
+    add(value, &dest, v)
+}
+
+pub(crate) fn swap<V, A, B>(a: &A, b: &B, value: &mut V) -> Result<(), MutationError>
+where
+    V: JsonLike + Clone,
+    A: JsonPathLike + ?Sized,
+    B: JsonPathLike + ?Sized,
+{
+    let ra = resolve(a, a.depth(), value, false)?;
+    if inside(&ra, b, value) {
+        return Err(MutationError::InsideSource);
+    }
+    let rb = resolve(b, b.depth(), value, false)?;
+    if inside(&rb, a, value) {
+        return Err(MutationError::InsideSource);
+    }
+    if ra == rb {
+        return Ok(());
+    }
+    let missing = MutationError::MissingParent;
+    let vb = json_path::find(value, rb.iter().map(JsonPathElementRef::borrowed))
+        .ok_or(missing)?
+        .clone();
+    let target = json_path::find_mut(value, ra.iter().map(JsonPathElementRef::borrowed));
+    let va = std::mem::replace(target.ok_or(missing)?, vb);
+    let target = json_path::find_mut(value, rb.iter().map(JsonPathElementRef::borrowed));
+    *target.ok_or(missing)? = va;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::*;
+    use crate::json_path::{JsonPath, JsonPathRef};
+
+    fn path(path: &str) -> JsonPath {
+        path.try_into().unwrap()
+    }
+
+    #[test]
+    fn test_move_to() {
+        let value = json!({"a": {"b": [1, 2, 3]}, "c": [4, 5], "d": "x"});
+        let tests = vec![
+            (
+                "$.d",
+                "$.e",
+                Ok(json!({"a": {"b": [1, 2, 3]}, "c": [4, 5], "e": "x"})),
+            ),
+            ("$.d", "$.a", Ok(json!({"a": "x", "c": [4, 5]}))),
+            (
+                "$.a.b[0]",
+                "$.a.b[2]",
+                Ok(json!({"a": {"b": [2, 1, 3]}, "c": [4, 5], "d": "x"})),
+            ),
+            (
+                "$.a.b[0]",
+                "$.a.b[#]",
+                Ok(json!({"a": {"b": [2, 3, 1]}, "c": [4, 5], "d": "x"})),
+            ),
+            (
+                "$.a.b[#-1]",
+                "$.a.b[0]",
+                Ok(json!({"a": {"b": [3, 1, 2]}, "c": [4, 5], "d": "x"})),
+            ),
+            (
+                "$.a.b[1]",
+                "$.a.b[2]",
+                Ok(json!({"a": {"b": [1, 2, 3]}, "c": [4, 5], "d": "x"})),
+            ),
+            (
+                "$.a.b",
+                "$.c[1]",
+                Ok(json!({"a": {}, "c": [4, [1, 2, 3], 5], "d": "x"})),
+            ),
+            ("$.c[0]", "$.c[1].x", Err(MutationError::TypeMismatch)),
+            ("$.d", "$.d", Ok(value.clone())),
+            ("$.a", "$.a.b.c", Err(MutationError::InsideSource)),
+            ("$.a", "$.a.e", Err(MutationError::InsideSource)),
+            (
+                "$.a.b[#-1]",
+                "$.a.b[2][0]",
+                Err(MutationError::InsideSource),
+            ),
+            ("$.f", "$.e", Err(MutationError::MissingKey)),
+            ("$.c[2]", "$.e", Err(MutationError::OutOfRange)),
+            ("$.d", "$.c[3]", Err(MutationError::OutOfRange)),
+            ("$.d", "$.c.e", Err(MutationError::TypeMismatch)),
+            ("$", "$.e", Err(MutationError::MissingParent)),
+            ("$.d", "$", Err(MutationError::MissingParent)),
+        ];
+        for (from, dest, expected) in tests {
+            let mut result = value.clone();
+            let outcome = path(from).move_to(&path(dest), &mut result);
+            let result = outcome.map(|_| result.clone()).inspect_err(|_| {
+                assert_eq!(result, value, "expected a failed move to change nothing");
+            });
+            assert_eq!(
+                result, expected,
+                "expected move of {} to {} to be {:?}",
+                from, dest, expected
+            );
+            let mut borrowed = value.clone();
+            let outcome = JsonPathRef::parse(from)
+                .unwrap()
+                .move_to(&JsonPathRef::parse(dest).unwrap(), &mut borrowed);
+            assert_eq!(
+                outcome.map(|_| borrowed),
+                result,
+                "expected borrowed move of {} to {} to be {:?}",
+                from,
+                dest,
+                result
+            );
+        }
+    }
+
+    #[test]
+    fn test_copy_to() {
+        let value = json!({"a": {"b": [1, 2]}, "c": "x"});
+        let tests = vec![
+            (
+                "$.a",
+                "$.a.b[1]",
+                Ok(json!({"a": {"b": [1, {"b": [1, 2]}, 2]}, "c": "x"})),
+            ),
+            ("$.a.b[#-1]", "$.c", Ok(json!({"a": {"b": [1, 2]}, "c": 2}))),
+            (
+                "$",
+                "$.d",
+                Ok(json!({"a": {"b": [1, 2]}, "c": "x", "d": {"a": {"b": [1, 2]}, "c": "x"}})),
+            ),
+            ("$.d", "$.e", Err(MutationError::MissingKey)),
+            ("$.c", "$.a.b[3]", Err(MutationError::OutOfRange)),
+            ("$.c", "$", Err(MutationError::MissingParent)),
+        ];
+        for (from, dest, expected) in tests {
+            let mut result = value.clone();
+            let outcome = path(from).copy_to(&path(dest), &mut result);
+            let result = outcome.map(|_| result.clone()).inspect_err(|_| {
+                assert_eq!(result, value, "expected a failed copy to change nothing");
+            });
+            assert_eq!(
+                result, expected,
+                "expected copy of {} to {} to be {:?}",
+                from, dest, expected
+            );
+        }
+    }
+
+    #[test]
+    fn test_swap() {
+        let value = json!({"a": {"b": [1, 2, 3]}, "c": "x"});
+        let tests = vec![
+            (
+                "$.a.b[0]",
+                "$.a.b[#-1]",
+                Ok(json!({"a": {"b": [3, 2, 1]}, "c": "x"})),
+            ),
+            ("$.a", "$.c", Ok(json!({"a": "x", "c": {"b": [1, 2, 3]}}))),
+            ("$.c", "$.c", Ok(value.clone())),
+            ("$.a", "$.a.b[0]", Err(MutationError::InsideSource)),
+            ("$.a.b[#-1]", "$.a.b[2].x", Err(MutationError::InsideSource)),
+            ("$.a.b", "$", Err(MutationError::InsideSource)),
+            ("$.a", "$.d", Err(MutationError::MissingKey)),
+            ("$.a.b[3]", "$.c", Err(MutationError::OutOfRange)),
+        ];
+        for (a, b, expected) in tests {
+            let mut result = value.clone();
+            let outcome = JsonPath::swap(&path(a), &path(b), &mut result);
+            let result = outcome.map(|_| result.clone()).inspect_err(|_| {
+                assert_eq!(result, value, "expected a failed swap to change nothing");
+            });
+            assert_eq!(
+                result, expected,
+                "expected swap of {} and {} to be {:?}",
+                a, b, expected
+            );
+        }
+    }
+}
@@ -0,0 +1,387 @@
+use std::{cmp::Ordering, str::FromStr};
+
+use serde_json::Value;
+
+use crate::{
+    compare::{Comparator, Sqlite},
+    json_path::{JsonPath, JsonPathElement, JsonPathIndex, JsonPathLike},
+    number::cmp_numbers,
+};
+
+/// Decides which leaf values a search matches.
+///
+/// Leaves are all values which are neither an Array nor an Object. `Equal` doesn't cast between
+/// types, `Compare(Ordering::Equal, _)` does if the comparator does.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Matcher<'a> {
+    Equal(&'a Value),             // leaf equals value, strings are collated
+    Like(&'a str, Option<char>),  // String leaf matches a LIKE pattern with an escape character
+    Compare(Ordering, &'a Value), // comparing leaf with value returns the ordering
+}
+
+impl<'a> Matcher<'a> {
+    /// Matches `leaf`, comparing with `partial_cmp`.
+    pub fn matches(&self, leaf: &Value) -> bool {
+        self.matches_with(leaf, &Sqlite)
+    }
+
+    /// Matches `leaf`, comparing with `comparator`.
+    pub fn matches_with<C: Comparator>(&self, leaf: &Value, comparator: &C) -> bool {
+        match self {
+            // Values of different types are unequal, so 1 doesn't find true or "1".
+            Matcher::Equal(value) => match (leaf, value) {
+                (Value::Number(a), Value::Number(b)) => cmp_numbers(a, b) == Ordering::Equal,
+                (Value::String(_), Value::String(_)) => {
+                    comparator.partial_cmp(leaf, *value) == Some(Ordering::Equal)
+                }
+                _ => leaf == *value,
+            },
+            Matcher::Like(pattern, escape) => leaf
+                .as_str()
+                .is_some_and(|text| like(text, pattern, *escape)),
+            Matcher::Compare(ordering, value) => {
+                comparator.partial_cmp(leaf, *value) == Some(*ordering)
+            }
+        }
+    }
+}
+
+/// Whether a search stops at the first match or returns all matches.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub enum SearchMode {
+    One,
+    #[default]
+    All,
+}
+
+impl FromStr for SearchMode {
+    type Err = &'static str;
+
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        match value.to_ascii_lowercase().as_str() {
+            "one" => Ok(SearchMode::One),
+            "all" => Ok(SearchMode::All),
+            _ => Err("expected 'one' or 'all'"),
+        }
+    }
+}
+
+enum LikeToken {
+    Any,        // % matches any sequence of characters
+    One,        // _ matches exactly one character
+    Char(char), // any other or an escaped character matches itself
+}
+
+/// Checks whether `text` matches a SQL LIKE `pattern`, case sensitive.
+///
+/// `%` matches any sequence of characters and `_` matches exactly one character.
+/// The escape character, `\` if None, makes the following character match literally.
+pub fn like(text: &str, pattern: &str, escape: Option<char>) -> bool {
+    let escape = escape.unwrap_or('\\');
+    let mut tokens = Vec::new();
+    let mut iter = pattern.chars();
+    while let Some(c) = iter.next() {
+        tokens.push(match c {
+            '%' => LikeToken::Any,
+            '_' => LikeToken::One,
+            c if c == escape => LikeToken::Char(iter.next().unwrap_or(escape)),
+            c => LikeToken::Char(c),
+        });
+    }
+
+    let text: Vec<char> = text.chars().collect();
+    let (mut t, mut p) = (0, 0);
+    // Position after the last % and the text position it currently matches up to
+    let mut backtrack: Option<(usize, usize)> = None;
+    while t < text.len() {
+        match tokens.get(p) {
+            Some(LikeToken::Any) => {
+                p += 1;
+                backtrack = Some((p, t));
+                continue;
+            }
+            Some(LikeToken::One) => {
+                t += 1;
+                p += 1;
+                continue;
+            }
+            Some(LikeToken::Char(c)) if *c == text[t] => {
+                t += 1;
+                p += 1;
+                continue;
+            }
+            _ => {}
+        }
+        match backtrack {
+            Some((bp, bt)) => {
+                p = bp;
+                t = bt + 1;
+                backtrack = Some((bp, bt + 1));
+            }
+            None => return false,
+        }
+    }
+    tokens[p..].iter().all(|t| matches!(t, LikeToken::Any))
+}
+
+/// Returns the paths of all leaves inside `value` which match.
+///
+/// Paths are returned in document order. If `prefix` is set, only leaves inside the value at
+/// `prefix` are searched.
+pub fn search(
+    value: &Value,
+    matcher: &Matcher,
+    mode: SearchMode,
+    prefix: Option<&dyn JsonPathLike>,
+) -> Vec<JsonPath> {
+    search_with(value, matcher, mode, prefix, &Sqlite)
+}
+
+/// Like `search`, `Matcher::Compare` compares with `comparator` instead of `partial_cmp` and
+/// `Matcher::Equal` compares strings with its collation.
+pub fn search_with<C: Comparator>(
+    value: &Value,
+    matcher: &Matcher,
+    mode: SearchMode,
+    prefix: Option<&dyn JsonPathLike>,
+    comparator: &C,
+) -> Vec<JsonPath> {
+    let mut path = prefix.map(|prefix| prefix.to_path()).unwrap_or_default();
+    let mut out = Vec::new();
+    if let Some(start) = path.find(value) {
+        walk(start, matcher, comparator, mode, &mut path, &mut out);
+    }
+    out
+}
+
+// Returns true if the search is done.
+fn walk<C: Comparator>(
+    value: &Value,
+    matcher: &Matcher,
+    comparator: &C,
+    mode: SearchMode,
+    path: &mut JsonPath,
+    out: &mut Vec<JsonPath>,
+) -> bool {
+    let children: Box<dyn Iterator<Item = (JsonPathElement, &Value)>> = match value {
+        Value::Object(map) => Box::new(
+            map.iter()
+                .map(|(key, sub)| (JsonPathElement::Field(key.clone()), sub)),
+        ),
+        Value::Array(array) => Box::new(
+            array
+                .iter()
+                .enumerate()
+                .map(|(i, sub)| (JsonPathElement::Index(JsonPathIndex::NthLefth(i)), sub)),
+        ),
+        leaf => {
+            if matcher.matches_with(leaf, comparator) {
+                out.push(path.clone());
+                return mode == SearchMode::One;
+            }
+            return false;
+        }
+    };
+    for (element, sub) in children {
+        path.push(element);
+        let done = walk(sub, matcher, comparator, mode, path, out);
+        path.pop();
+        if done {
+            return true;
+        }
+    }
+    false
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::*;
+    use crate::{
+        collation::NoCase,
+        compare::{Collated, JavaScript, MySql, Postgres},
+        json_path::JsonPathRef,
+    };
+
+    #[test]
+    fn test_like() {
+        let tests = vec![
+            ("abc", "abc", None, true),
+            ("abc", "ABC", None, false),
+            ("abc", "a%", None, true),
+            ("abc", "%c", None, true),
+            ("abc", "%b%", None, true),
+            ("abc", "a_c", None, true),
+            ("abc", "a_", None, false),
+            ("", "%", None, true),
+            ("", "_", None, false),
+            ("aXbXc", "a%X%c", None, true),
+            ("order-123", "order-%3", None, true),
+            ("100%", "100\\%", None, true),
+            ("1000", "100\\%", None, false),
+            ("a_b", "a|_b", Some('|'), true),
+            ("axb", "a|_b", Some('|'), false),
+        ];
+        for (text, pattern, escape, expected) in tests {
+            assert_eq!(
+                like(text, pattern, escape),
+                expected,
+                "expected {} LIKE {} to be {}",
+                text,
+                pattern,
+                expected
+            );
+        }
+    }
+
+    #[test]
+    fn test_search() {
+        let value = json!({
+            "a": "order-123",
+            "b": [1, "order-123", {"c": "order-124"}],
+            "d": {"e": 5, "f": true}
+        });
+        let order = json!("order-123");
+        let one = json!(1);
+        let prefix: JsonPath = "$.b".try_into().unwrap();
+        let prefix_ref = JsonPathRef::parse("$.b[2]").unwrap();
+        let tests: Vec<(_, _, Option<&dyn JsonPathLike>, _)> = vec![
+            (
+                Matcher::Equal(&order),
+                SearchMode::All,
+                None,
+                vec!["$.a", "$.b[1]"],
+            ),
+            (Matcher::Equal(&order), SearchMode::One, None, vec!["$.a"]),
+            (
+                Matcher::Like("order-%", None),
+                SearchMode::All,
+                None,
+                vec!["$.a", "$.b[1]", "$.b[2].c"],
+            ),
+            (
+                Matcher::Like("order-%", None),
+                SearchMode::All,
+                Some(&prefix),
+                vec!["$.b[1]", "$.b[2].c"],
+            ),
+            // Strings which aren't numbers are greater than numbers
+            (
+                Matcher::Compare(Ordering::Greater, &one),
+                SearchMode::All,
+                None,
+                vec!["$.a", "$.b[1]", "$.b[2].c", "$.d.e"],
+            ),
+            (
+                Matcher::Compare(Ordering::Equal, &one),
+                SearchMode::All,
+                None,
+                vec!["$.b[0]", "$.d.f"],
+            ),
+            (
+                Matcher::Like("order-%", None),
+                SearchMode::All,
+                Some(&prefix_ref),
+                vec!["$.b[2].c"],
+            ),
+            (Matcher::Like("x%", None), SearchMode::All, None, vec![]),
+            (Matcher::Equal(&one), SearchMode::All, None, vec!["$.b[0]"]),
+        ];
+        for (matcher, mode, prefix, expected) in tests {
+            let result: Vec<String> = search(&value, &matcher, mode, prefix)
+                .iter()
+                .map(|path| path.to_string())
+                .collect();
+            assert_eq!(
+                result, expected,
+                "expected {:?} to find {:?}",
+                matcher, expected
+            );
+        }
+    }
+
+    #[test]
+    fn test_search_with() {
+        let value = json!({"a": [1, "2", null, [3]], "b": false});
+        let zero = json!(0);
+        let matcher = Matcher::Compare(Ordering::Greater, &zero);
+        let words = json!(["ABC", "abc", "abd"]);
+        let abc = json!("abc");
+        let no_case = Collated(Sqlite, NoCase);
+        let ones = json!([1, true, "1", " 1.0 ", [1], 1.0]);
+        let one = json!(1);
+        let tests = vec![
+            (
+                search_with(&value, &matcher, SearchMode::All, None, &Sqlite),
+                vec!["$.a[0]", "$.a[1]", "$.a[3][0]"],
+            ),
+            (
+                search_with(&value, &matcher, SearchMode::All, None, &Postgres),
+                vec!["$.a[0]", "$.a[3][0]", "$.b"],
+            ),
+            (
+                search_with(&value, &matcher, SearchMode::All, None, &MySql),
+                vec!["$.a[0]", "$.a[1]", "$.a[3][0]", "$.b"],
+            ),
+            (
+                search_with(&value, &matcher, SearchMode::All, None, &JavaScript),
+                vec!["$.a[0]", "$.a[1]", "$.a[3][0]"],
+            ),
+            (
+                search_with(
+                    &words,
+                    &Matcher::Equal(&abc),
+                    SearchMode::All,
+                    None,
+                    &Sqlite,
+                ),
+                vec!["$[1]"],
+            ),
+            (
+                search_with(
+                    &words,
+                    &Matcher::Equal(&abc),
+                    SearchMode::All,
+                    None,
+                    &no_case,
+                ),
+                vec!["$[0]", "$[1]"],
+            ),
+            // Equal doesn't cast for any comparator, 1 finds 1.0 but not true, "1" or " 1.0 "
+            (
+                search_with(&ones, &Matcher::Equal(&one), SearchMode::All, None, &Sqlite),
+                vec!["$[0]", "$[4][0]", "$[5]"],
+            ),
+            (
+                search_with(&ones, &Matcher::Equal(&one), SearchMode::All, None, &MySql),
+                vec!["$[0]", "$[4][0]", "$[5]"],
+            ),
+            // Compare casts like the comparator
+            (
+                search_with(
+                    &ones,
+                    &Matcher::Compare(Ordering::Equal, &one),
+                    SearchMode::All,
+                    None,
+                    &JavaScript,
+                ),
+                vec!["$[0]", "$[1]", "$[2]", "$[3]", "$[4][0]", "$[5]"],
+            ),
+            (
+                search_with(
+                    &json!({"a": 2.0, "b": "2"}),
+                    &Matcher::Equal(&json!(2)),
+                    SearchMode::All,
+                    None,
+                    &Sqlite,
+                ),
+                vec!["$.a"],
+            ),
+        ];
+        for (result, expected) in tests {
+            let result: Vec<String> = result.iter().map(|path| path.to_string()).collect();
+            assert_eq!(result, expected, "expected search to find {:?}", expected);
+        }
+    }
+}
@@ -0,0 +1,630 @@
+use std::{
+    collections::VecDeque,
+    error, fmt,
+    io::{self, BufRead, BufReader, Read},
+    str::FromStr,
+};
+
+use serde_json::Value;
+
+use crate::json_path::{Elements, JsonPath, JsonPathElement, JsonPathIndex};
+
+/// A path which may contain wildcard array positions, written as `[*]`.
+///
+/// `#-N` indices are not supported since they require the length of the array.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PathPattern(Vec<PatternElement>);
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum PatternElement {
+    Field(String), // key of an object
+    Index(usize),  // N-th element from zero
+    AnyIndex,      // every element of an array
+}
+
+impl PatternElement {
+    fn matches(&self, e: &JsonPathElement) -> bool {
+        match (self, e) {
+            (PatternElement::Field(a), JsonPathElement::Field(b)) => a == b,
+            (PatternElement::Index(a), JsonPathElement::Index(JsonPathIndex::NthLefth(b))) => {
+                a == b
+            }
+            (PatternElement::AnyIndex, JsonPathElement::Index(_)) => true,
+            _ => false,
+        }
+    }
+}
+
+impl PathPattern {
+    // Returns whether the pattern starts with path.
+    fn starts_with(&self, path: &[JsonPathElement]) -> bool {
+        self.0.len() >= path.len() && self.0.iter().zip(path).all(|(p, e)| p.matches(e))
+    }
+}
+
+impl FromStr for PathPattern {
+    type Err = &'static str;
+
+    // The grammar of JsonPath with `[*]` as an additional element.
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        let mut elements = Elements::new(value);
+        let mut out = Vec::new();
+        loop {
+            if elements.eat_wildcard() {
+                out.push(PatternElement::AnyIndex);
+                continue;
+            }
+            let Some(e) = elements.next() else {
+                return Ok(PathPattern(out));
+            };
+            out.push(pattern_element(&e?.into_owned())?);
+        }
+    }
+}
+
+impl TryFrom<&str> for PathPattern {
+    type Error = &'static str;
+
+    #[inline]
+    fn try_from(value: &str) -> Result<Self, Self::Error> {
+        PathPattern::from_str(value)
+    }
+}
+
+impl TryFrom<&JsonPath> for PathPattern {
+    type Error = &'static str;
+
+    fn try_from(path: &JsonPath) -> Result<Self, Self::Error> {
+        path.iter()
+            .map(pattern_element)
+            .collect::<Result<_, _>>()
+            .map(PathPattern)
+    }
+}
+
+fn pattern_element(e: &JsonPathElement) -> Result<PatternElement, &'static str> {
+    match e {
+        JsonPathElement::Field(key) => Ok(PatternElement::Field(key.clone())),
+        JsonPathElement::Index(JsonPathIndex::NthLefth(i)) => Ok(PatternElement::Index(*i)),
+        JsonPathElement::Index(JsonPathIndex::NthRight(_)) => Err("#-N indices can't be streamed"),
+    }
+}
+
+/// A value found by a JsonStream.
+#[derive(Debug, Clone, PartialEq)]
+pub struct StreamMatch {
+    pub pattern: usize, // position of the matching pattern
+    pub path: JsonPath,
+    pub value: Value,
+}
+
+#[derive(Debug)]
+pub enum StreamError {
+    Io(io::Error),
+    Syntax(&'static str),
+}
+
+impl fmt::Display for StreamError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            StreamError::Io(err) => err.fmt(f),
+            StreamError::Syntax(err) => f.write_str(err),
+        }
+    }
+}
+
+impl error::Error for StreamError {
+    fn source(&self) -> Option<&(dyn error::Error + 'static)> {
+        match self {
+            StreamError::Io(err) => Some(err),
+            StreamError::Syntax(_) => None,
+        }
+    }
+}
+
+impl From<io::Error> for StreamError {
+    fn from(err: io::Error) -> Self {
+        StreamError::Io(err)
+    }
+}
+
+const UNEXPECTED_END: StreamError = StreamError::Syntax("unexpected end of JSON");
+const INVALID_JSON: StreamError = StreamError::Syntax("invalid JSON");
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum State {
+    Value,        // a value starts at the current path
+    FirstMember,  // after {
+    NextMember,   // after , in an object
+    FirstElement, // after [
+    NextElement,  // after , in an array
+    AfterValue,   // a value ended at the current path
+    Done,
+}
+
+/// Reads JSON text and yields the values matching any of the patterns, in document order.
+///
+/// Only matched values and the current path are held in memory, everything else is skipped
+/// while reading. A value matching several patterns is yielded once per pattern.
+pub struct JsonStream<R> {
+    reader: BufReader<R>,
+    patterns: Vec<PathPattern>,
+    path: JsonPath,
+    containers: Vec<bool>, // true for objects, false for arrays
+    state: State,
+    pending: VecDeque<StreamMatch>,
+}
+
+impl<R: Read> JsonStream<R> {
+    pub fn new(reader: R, patterns: Vec<PathPattern>) -> Self {
+        JsonStream {
+            reader: BufReader::new(reader),
+            patterns,
+            path: JsonPath::default(),
+            containers: Vec::new(),
+            state: State::Value,
+            pending: VecDeque::new(),
+        }
+    }
+
+    fn peek(&mut self) -> Result<u8, StreamError> {
+        self.reader
+            .fill_buf()?
+            .first()
+            .copied()
+            .ok_or(UNEXPECTED_END)
+    }
+
+    fn bump(&mut self, capture: &mut Option<Vec<u8>>, b: u8) {
+        self.reader.consume(1);
+        if let Some(capture) = capture {
+            capture.push(b);
+        }
+    }
+
+    fn skip_whitespace(&mut self) -> Result<(), StreamError> {
+        loop {
+            match self.reader.fill_buf()?.first() {
+                Some(b' ' | b'\t' | b'\n' | b'\r') => self.reader.consume(1),
+                _ => return Ok(()),
+            }
+        }
+    }
+
+    fn expect(&mut self, expected: u8) -> Result<(), StreamError> {
+        if self.peek()? != expected {
+            return Err(INVALID_JSON);
+        }
+        self.reader.consume(1);
+        Ok(())
+    }
+
+    fn string(&mut self, capture: &mut Option<Vec<u8>>) -> Result<(), StreamError> {
+        let quote = self.peek()?;
+        if quote != b'"' {
+            return Err(INVALID_JSON);
+        }
+        self.bump(capture, quote);
+        loop {
+            let b = self.peek()?;
+            self.bump(capture, b);
+            match b {
+                b'"' => return Ok(()),
+                b'\\' => {
+                    let b = self.peek()?;
+                    self.bump(capture, b);
+                }
+                _ => {}
+            }
+        }
+    }
+
+    // Reads a value, containers are read by counting brackets outside of strings.
+    fn value(&mut self, capture: &mut Option<Vec<u8>>) -> Result<(), StreamError> {
+        let mut depth = 0usize;
+        loop {
+            match self.peek()? {
+                b'"' => self.string(capture)?,
+                b @ (b'{' | b'[') => {
+                    depth += 1;
+                    self.bump(capture, b);
+                }
+                b @ (b'}' | b']') if depth > 0 => {
+                    depth -= 1;
+                    self.bump(capture, b);
+                }
+                b'}' | b']' | b',' | b':' if depth == 0 => return Err(INVALID_JSON),
+                b @ (b',' | b':' | b' ' | b'\t' | b'\n' | b'\r') => self.bump(capture, b),
+                _ => {
+                    // Number, true, false or null
+                    let mut empty = true;
+                    while let Some(&b) = self.reader.fill_buf()?.first() {
+                        if !(b.is_ascii_alphanumeric() || matches!(b, b'+' | b'-' | b'.')) {
+                            break;
+                        }
+                        self.bump(capture, b);
+                        empty = false;
+                    }
+                    if empty {
+                        return Err(INVALID_JSON);
+                    }
+                }
+            }
+            if depth == 0 {
+                return Ok(());
+            }
+        }
+    }
+
+    // Handles the value starting at the current path.
+    fn start_value(&mut self) -> Result<(), StreamError> {
+        self.skip_whitespace()?;
+        let matching: Vec<usize> = self
+            .patterns
+            .iter()
+            .enumerate()
+            .filter(|(_, p)| p.starts_with(&self.path))
+            .map(|(i, _)| i)
+            .collect();
+        let depth = self.path.len();
+        if matching.iter().any(|&i| self.patterns[i].0.len() == depth) {
+            let mut capture = Some(Vec::new());
+            self.value(&mut capture)?;
+            let value: Value =
+                serde_json::from_slice(&capture.unwrap_or_default()).map_err(|_| INVALID_JSON)?;
+            // Patterns ending deeper are matched inside the value
+            for i in matching {
+                let rest = &self.patterns[i].0[depth..];
+                let mut path = self.path.clone();
+                expand(i, rest, &value, &mut path, &mut self.pending);
+            }
+            self.state = State::AfterValue;
+        } else if !matching.is_empty() {
+            match self.peek()? {
+                b'{' => {
+                    self.reader.consume(1);
+                    self.containers.push(true);
+                    self.state = State::FirstMember;
+                }
+                b'[' => {
+                    self.reader.consume(1);
+                    self.containers.push(false);
+                    self.state = State::FirstElement;
+                }
+                _ => {
+                    self.value(&mut None)?;
+                    self.state = State::AfterValue;
+                }
+            }
+        } else {
+            self.value(&mut None)?;
+            self.state = State::AfterValue;
+        }
+        Ok(())
+    }
+
+    fn member(&mut self) -> Result<(), StreamError> {
+        self.skip_whitespace()?;
+        if self.state == State::FirstMember && self.peek()? == b'}' {
+            self.reader.consume(1);
+            self.containers.pop();
+            self.state = State::AfterValue;
+            return Ok(());
+        }
+        let mut key = Some(Vec::new());
+        self.string(&mut key)?;
+        let key: String =
+            serde_json::from_slice(&key.unwrap_or_default()).map_err(|_| INVALID_JSON)?;
+        self.skip_whitespace()?;
+        self.expect(b':')?;
+        self.path.push(JsonPathElement::Field(key));
+        self.state = State::Value;
+        Ok(())
+    }
+
+    fn element(&mut self) -> Result<(), StreamError> {
+        self.skip_whitespace()?;
+        if self.state == State::FirstElement {
+            if self.peek()? == b']' {
+                self.reader.consume(1);
+                self.containers.pop();
+                self.state = State::AfterValue;
+                return Ok(());
+            }
+            self.path
+                .push(JsonPathElement::Index(JsonPathIndex::NthLefth(0)));
+        } else if let Some(JsonPathElement::Index(JsonPathIndex::NthLefth(i))) =
+            self.path.last_mut()
+        {
+            *i += 1;
+        }
+        self.state = State::Value;
+        Ok(())
+    }
+
+    // Reads the separator after a value, leaving the containers which end.
+    fn after_value(&mut self) -> Result<(), StreamError> {
+        let Some(&object) = self.containers.last() else {
+            self.state = State::Done;
+            return Ok(());
+        };
+        self.skip_whitespace()?;
+        match (self.peek()?, object) {
+            (b',', true) => {
+                self.reader.consume(1);
+                self.path.pop();
+                self.state = State::NextMember;
+            }
+            (b',', false) => {
+                self.reader.consume(1);
+                self.state = State::NextElement;
+            }
+            (b'}', true) | (b']', false) => {
+                self.reader.consume(1);
+                self.path.pop();
+                self.containers.pop();
+            }
+            _ => return Err(INVALID_JSON),
+        }
+        Ok(())
+    }
+
+    fn step(&mut self) -> Result<(), StreamError> {
+        match self.state {
+            State::Value => self.start_value(),
+            State::FirstMember | State::NextMember => self.member(),
+            State::FirstElement | State::NextElement => self.element(),
+            State::AfterValue => self.after_value(),
+            State::Done => Ok(()),
+        }
+    }
+}
+
+// Adds the values inside value matching the rest of a pattern.
+fn expand(
+    pattern: usize,
+    rest: &[PatternElement],
+    value: &Value,
+    path: &mut JsonPath,
+    out: &mut VecDeque<StreamMatch>,
+) {
+    let Some((first, rest)) = rest.split_first() else {
+        out.push_back(StreamMatch {
+            pattern,
+            path: path.clone(),
+            value: value.clone(),
+        });
+        return;
+    };
+    let mut descend = |e: JsonPathElement, sub: &Value, path: &mut JsonPath| {
+        path.push(e);
+        expand(pattern, rest, sub, path, out);
+        path.pop();
+    };
+    match (first, value) {
+        (PatternElement::Field(key), Value::Object(map)) => {
+            if let Some(sub) = map.get(key) {
+                descend(JsonPathElement::Field(key.clone()), sub, path);
+            }
+        }
+        (PatternElement::Index(i), Value::Array(array)) => {
+            if let Some(sub) = array.get(*i) {
+                descend(
+                    JsonPathElement::Index(JsonPathIndex::NthLefth(*i)),
+                    sub,
+                    path,
+                );
+            }
+        }
+        (PatternElement::AnyIndex, Value::Array(array)) => {
+            for (i, sub) in array.iter().enumerate() {
+                descend(
+                    JsonPathElement::Index(JsonPathIndex::NthLefth(i)),
+                    sub,
+                    path,
+                );
+            }
+        }
+        _ => {}
+    }
+}
+
+impl<R: Read> Iterator for JsonStream<R> {
+    type Item = Result<StreamMatch, StreamError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            if let Some(found) = self.pending.pop_front() {
+                return Some(Ok(found));
+            }
+            if self.state == State::Done {
+                return None;
+            }
+            if let Err(err) = self.step() {
+                self.state = State::Done;
+                return Some(Err(err));
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::*;
+
+    fn patterns(patterns: &[&str]) -> Vec<PathPattern> {
+        patterns.iter().map(|p| p.parse().unwrap()).collect()
+    }
+
+    #[test]
+    fn try_from() {
+        let tests = vec![
+            ("$", Ok(PathPattern(vec![]))),
+            (
+                "$.records[*].id",
+                Ok(PathPattern(vec![
+                    PatternElement::Field("records".to_string()),
+                    PatternElement::AnyIndex,
+                    PatternElement::Field("id".to_string()),
+                ])),
+            ),
+            (
+                "$[2].\"a b\"",
+                Ok(PathPattern(vec![
+                    PatternElement::Index(2),
+                    PatternElement::Field("a b".to_string()),
+                ])),
+            ),
+            ("$[#-1]", Err("#-N indices can't be streamed")),
+            ("$[*", Err("expected ]")),
+            ("$a", Err("expected . or [")),
+            ("a", Err("expected $ or numeric")),
+            ("$[]", Err("expected array index")),
+            ("$[99999999999999999999999]", Err("expected array index")),
+            (
+                "$.a[*][*]",
+                Ok(PathPattern(vec![
+                    PatternElement::Field("a".to_string()),
+                    PatternElement::AnyIndex,
+                    PatternElement::AnyIndex,
+                ])),
+            ),
+            ("$[#]", Err("#-N indices can't be streamed")),
+        ];
+        for (pattern, expected) in tests {
+            assert_eq!(
+                pattern.try_into(),
+                expected,
+                "expected {} to be {:?}",
+                pattern,
+                expected
+            );
+        }
+    }
+
+    #[test]
+    fn stream() {
+        let json = r#"{
+            "meta": {"count": 3, "skip": [1, {"records": []}]},
+            "records": [
+                {"id": 1, "tags": ["a", "b"]},
+                {"id": 2, "tags": []},
+                {"id": 3, "name": "x\"y", "tags": ["c"]}
+            ],
+            "after": null
+        }"#;
+        let tests = vec![
+            (
+                vec!["$.records[*].id"],
+                vec![
+                    (0, "$.records[0].id", json!(1)),
+                    (0, "$.records[1].id", json!(2)),
+                    (0, "$.records[2].id", json!(3)),
+                ],
+            ),
+            (
+                vec!["$.records[*].tags[*]", "$.meta.count"],
+                vec![
+                    (1, "$.meta.count", json!(3)),
+                    (0, "$.records[0].tags[0]", json!("a")),
+                    (0, "$.records[0].tags[1]", json!("b")),
+                    (0, "$.records[2].tags[0]", json!("c")),
+                ],
+            ),
+            (
+                vec!["$.records[2]", "$.records[*].name"],
+                vec![
+                    (
+                        0,
+                        "$.records[2]",
+                        json!({"id": 3, "name": "x\"y", "tags": ["c"]}),
+                    ),
+                    (1, "$.records[2].name", json!("x\"y")),
+                ],
+            ),
+            (
+                vec!["$.after", "$.missing"],
+                vec![(0, "$.after", json!(null))],
+            ),
+            (vec!["$.records[5]"], vec![]),
+        ];
+        for (patterns_, expected) in tests {
+            let stream = JsonStream::new(json.as_bytes(), patterns(&patterns_));
+            let result: Vec<(usize, String, Value)> = stream
+                .map(|m| m.unwrap())
+                .map(|m| (m.pattern, m.path.to_string(), m.value))
+                .collect();
+            let expected: Vec<(usize, String, Value)> = expected
+                .into_iter()
+                .map(|(i, p, v)| (i, p.to_string(), v))
+                .collect();
+            assert_eq!(
+                result, expected,
+                "expected {:?} to find {:?}",
+                patterns_, expected
+            );
+        }
+    }
+
+    #[test]
+    fn invalid() {
+        let tests = vec![
+            (r#"{"records": [1, 2"#, 2, "unexpected end of JSON"),
+            (r#"{"records": [1 2]}"#, 1, "invalid JSON"),
+            (r#"{"records" [1]}"#, 0, "invalid JSON"),
+            (r#"{"records": [tru]}"#, 0, "invalid JSON"),
+        ];
+        for (json, ok, expected) in tests {
+            let mut stream = JsonStream::new(json.as_bytes(), patterns(&["$.records[*]"]));
+            for _ in 0..ok {
+                assert!(stream.next().unwrap().is_ok());
+            }
+            let err = stream.next().unwrap().unwrap_err();
+            assert_eq!(err.to_string(), expected, "expected {} to fail", json);
+            assert!(stream.next().is_none());
+        }
+    }
+
+    // A reader producing a large array element by element, never held in memory as a whole.
+    struct Records {
+        remaining: usize,
+        buf: Vec<u8>,
+    }
+
+    impl Read for Records {
+        fn read(&mut self, out: &mut [u8]) -> io::Result<usize> {
+            if self.buf.is_empty() {
+                self.buf = match self.remaining {
+                    0 => return Ok(0),
+                    1 => b"{\"id\": 1}]}".to_vec(),
+                    n => {
+                        format!("{{\"id\": {}, \"pad\": \"{}\"}},", n, "x".repeat(64)).into_bytes()
+                    }
+                };
+                self.remaining -= 1;
+            }
+            let n = out.len().min(self.buf.len());
+            out[..n].copy_from_slice(&self.buf[..n]);
+            self.buf.drain(..n);
+            Ok(n)
+        }
+    }
+
+    #[test]
+    fn large() {
+        let reader = io::Cursor::new(b"{\"records\": [".to_vec()).chain(Records {
+            remaining: 100_000,
+            buf: Vec::new(),
+        });
+        let stream = JsonStream::new(reader, patterns(&["$.records[*].id"]));
+        let mut count = 0;
+        for m in stream {
+            let m = m.unwrap();
+            assert_eq!(m.value, json!(100_000 - count));
+            count += 1;
+        }
+        assert_eq!(count, 100_000);
+    }
+}
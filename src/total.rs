@@ -0,0 +1,322 @@
+use std::{
+    borrow::Cow,
+    cmp::Ordering,
+    hash::{Hash, Hasher},
+    ops::Deref,
+};
+
+use serde_json::{Map, Value};
+
+use crate::number::{cmp_numbers, hash_number};
+
+// Position of each type in the order.
+fn rank(value: &Value) -> u8 {
+    match value {
+        Value::Null => 0,
+        Value::Bool(_) => 1,
+        Value::Number(_) => 2,
+        Value::String(_) => 3,
+        Value::Array(_) => 4,
+        Value::Object(_) => 5,
+    }
+}
+
+fn sorted(map: &Map<String, Value>) -> Vec<(&String, &Value)> {
+    let mut pairs: Vec<_> = map.iter().collect();
+    pairs.sort_unstable_by(|a, b| a.0.cmp(b.0));
+    pairs
+}
+
+/// A total order of `serde_json::Value`s.
+///
+/// Types are ordered Null < Bool < Number < String < Array < Object and never cast into each
+/// other, unlike in `partial_cmp`. Numbers compare by their exact value with
+/// `number::cmp_numbers`, so `1` equals `1.0`. Arrays are compared element by element and Objects
+/// like Arrays of their key/value pairs sorted by key, the same as in `partial_cmp`.
+pub fn total_cmp(a: &Value, b: &Value) -> Ordering {
+    match (a, b) {
+        (Value::Null, Value::Null) => Ordering::Equal,
+        (Value::Bool(a), Value::Bool(b)) => a.cmp(b),
+        (Value::Number(a), Value::Number(b)) => cmp_numbers(a, b),
+        (Value::String(a), Value::String(b)) => a.cmp(b),
+        (Value::Array(a), Value::Array(b)) => a
+            .iter()
+            .zip(b)
+            .map(|(a, b)| total_cmp(a, b))
+            .find(|ordering| ordering.is_ne())
+            .unwrap_or_else(|| a.len().cmp(&b.len())),
+        (Value::Object(a), Value::Object(b)) => {
+            let (a, b) = (sorted(a), sorted(b));
+            a.iter()
+                .zip(&b)
+                .map(|((ka, va), (kb, vb))| ka.cmp(kb).then_with(|| total_cmp(va, vb)))
+                .find(|ordering| ordering.is_ne())
+                .unwrap_or_else(|| a.len().cmp(&b.len()))
+        }
+        (a, b) => rank(a).cmp(&rank(b)),
+    }
+}
+
+/// Hashes `value` consistently with `total_cmp`, values which compare equal hash equally.
+pub fn total_hash<H: Hasher>(value: &Value, state: &mut H) {
+    rank(value).hash(state);
+    match value {
+        Value::Null => {}
+        Value::Bool(b) => b.hash(state),
+        Value::Number(n) => hash_number(n, state),
+        Value::String(s) => s.hash(state),
+        Value::Array(array) => {
+            array.len().hash(state);
+            for v in array {
+                total_hash(v, state);
+            }
+        }
+        Value::Object(map) => {
+            map.len().hash(state);
+            for (k, v) in sorted(map) {
+                k.hash(state);
+                total_hash(v, state);
+            }
+        }
+    }
+}
+
+/// Wraps a borrowed or owned `serde_json::Value` and provides `Ord`, `Eq` and `Hash`
+/// implementations following `total_cmp`.
+///
+/// Use it for keys of a `BTreeMap` or `HashMap`, to sort or to deduplicate values.
+/// `TotalJsonValue<'static>` owns its value.
+#[derive(Debug, Clone)]
+pub struct TotalJsonValue<'a>(Cow<'a, Value>);
+
+impl<'a> TotalJsonValue<'a> {
+    pub fn new(value: &'a Value) -> Self {
+        TotalJsonValue(Cow::Borrowed(value))
+    }
+
+    pub fn owned(value: Value) -> TotalJsonValue<'static> {
+        TotalJsonValue(Cow::Owned(value))
+    }
+
+    /// Clones the value if it is borrowed.
+    pub fn into_owned(self) -> TotalJsonValue<'static> {
+        TotalJsonValue(Cow::Owned(self.0.into_owned()))
+    }
+
+    /// Returns the value, cloned if it is borrowed.
+    pub fn into_value(self) -> Value {
+        self.0.into_owned()
+    }
+}
+
+impl<'a> Ord for TotalJsonValue<'a> {
+    #[inline]
+    fn cmp(&self, other: &Self) -> Ordering {
+        total_cmp(&self.0, &other.0)
+    }
+}
+
+impl<'a> PartialOrd for TotalJsonValue<'a> {
+    #[inline]
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl<'a> PartialEq for TotalJsonValue<'a> {
+    #[inline]
+    fn eq(&self, other: &Self) -> bool {
+        self.cmp(other).is_eq()
+    }
+}
+
+impl<'a> Eq for TotalJsonValue<'a> {}
+
+impl<'a> Hash for TotalJsonValue<'a> {
+    #[inline]
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        total_hash(&self.0, state)
+    }
+}
+
+impl<'a> From<&'a Value> for TotalJsonValue<'a> {
+    #[inline]
+    fn from(value: &'a Value) -> Self {
+        TotalJsonValue::new(value)
+    }
+}
+
+impl From<Value> for TotalJsonValue<'static> {
+    #[inline]
+    fn from(value: Value) -> Self {
+        TotalJsonValue::owned(value)
+    }
+}
+
+impl<'a> Deref for TotalJsonValue<'a> {
+    type Target = Value;
+
+    #[inline]
+    fn deref(&self) -> &Self::Target {
+        &self.0
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{
+        collections::{hash_map::DefaultHasher, BTreeMap, HashSet},
+        hash::BuildHasher,
+    };
+
+    use serde_json::json;
+
+    use super::*;
+
+    fn hash(value: &Value) -> u64 {
+        let mut state = DefaultHasher::new();
+        total_hash(value, &mut state);
+        state.finish()
+    }
+
+    #[test]
+    fn test_total_cmp() {
+        // Every value is less than the following ones.
+        let ascending = vec![
+            json!(null),
+            json!(false),
+            json!(true),
+            json!(-1e300),
+            json!(i64::MIN),
+            json!(-1.5),
+            json!(-1),
+            json!(0),
+            json!(0.5),
+            json!(2),
+            json!(9007199254740993u64),
+            json!(i64::MAX),
+            json!(9223372036854775808u64),
+            json!(u64::MAX),
+            json!(18446744073709551616.0),
+            json!(1e300),
+            json!(""),
+            json!("1"),
+            json!("a"),
+            json!([]),
+            json!([null]),
+            json!([false, 2]),
+            json!([0]),
+            json!([0, null]),
+            json!({}),
+            json!({"a": null}),
+            json!({"a": 1}),
+            json!({"a": 1, "b": 0}),
+            json!({"b": 0}),
+        ];
+        for (i, a) in ascending.iter().enumerate() {
+            for (j, b) in ascending.iter().enumerate() {
+                let result = total_cmp(a, b);
+                let expected = i.cmp(&j);
+                assert_eq!(
+                    result, expected,
+                    "expected {}.total_cmp({}) to be {:?}",
+                    a, b, expected
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn test_total_eq() {
+        let mut tests = vec![
+            (json!(1), json!(1.0)),
+            (json!(-0.0), json!(0)),
+            (json!(i64::MIN), json!(-9223372036854775808.0)),
+            (json!(9007199254740992u64), json!(9007199254740992.0)),
+            (json!([1, {"a": 2.0}]), json!([1.0, {"a": 2}])),
+            (json!({"a": 1, "b": [2]}), json!({"b": [2.0], "a": 1})),
+        ];
+        // With arbitrary_precision a float is its shortest decimal text, -9.223372036854776e18
+        // is greater than i64::MIN.
+        if cfg!(feature = "arbitrary_precision") {
+            let (a, b) = tests.remove(2);
+            assert_eq!(total_cmp(&a, &b), Ordering::Greater);
+        }
+        for (a, b) in tests {
+            assert_eq!(
+                total_cmp(&a, &b),
+                Ordering::Equal,
+                "expected {} == {}",
+                a,
+                b
+            );
+            assert_eq!(
+                hash(&a),
+                hash(&b),
+                "expected {} and {} to hash equally",
+                a,
+                b
+            );
+        }
+
+        let tests = vec![
+            (json!(true), json!(1)),
+            (json!("1"), json!(1)),
+            (json!(null), json!(false)),
+            (json!(9007199254740993u64), json!(9007199254740992.0)),
+            (json!([1, 2]), json!([2, 1])),
+            (json!({"a": 1}), json!({"b": 1})),
+        ];
+        for (a, b) in tests {
+            assert_ne!(
+                total_cmp(&a, &b),
+                Ordering::Equal,
+                "expected {} != {}",
+                a,
+                b
+            );
+            assert_ne!(
+                hash(&a),
+                hash(&b),
+                "expected {} and {} to hash differently",
+                a,
+                b
+            );
+        }
+    }
+
+    #[test]
+    fn test_collections() {
+        let values = [json!({"b": 2, "a": 1}), json!(1.0), json!(null), json!("x")];
+        let mut map = BTreeMap::new();
+        for (i, value) in values.iter().enumerate() {
+            map.insert(TotalJsonValue::new(value), i);
+        }
+        map.insert(TotalJsonValue::owned(json!(1)), 4);
+        map.insert(TotalJsonValue::owned(json!({"a": 1, "b": 2})), 5);
+        let result: Vec<(Value, usize)> =
+            map.into_iter().map(|(k, v)| (k.into_value(), v)).collect();
+        assert_eq!(
+            result,
+            vec![
+                (json!(null), 2),
+                (json!(1.0), 4),
+                (json!("x"), 3),
+                (json!({"a": 1, "b": 2}), 5),
+            ]
+        );
+
+        let more = [json!(1), json!({"a": 1, "b": 2})];
+        let set: HashSet<TotalJsonValue> = values
+            .iter()
+            .chain(&more)
+            .map(TotalJsonValue::from)
+            .collect();
+        assert_eq!(set.len(), 4);
+        let state = std::collections::hash_map::RandomState::new();
+        assert_eq!(
+            state.hash_one(TotalJsonValue::new(&json!([1, 2.0]))),
+            state.hash_one(TotalJsonValue::owned(json!([1.0, 2]))),
+        );
+    }
+}
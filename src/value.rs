@@ -1,109 +1,185 @@
-use std::{cmp::Ordering, mem::size_of_val, ops::Deref};
+use std::{cmp::Ordering, ops::Deref};
 
-use serde_json::Value;
+use serde_json::{Number, Value};
 
-/// Compares two `serde_json::Value`s.
+use crate::{
+    cast,
+    collation::{Binary, Collation},
+    compare::{Comparator, Sqlite},
+    json_like::JsonLike,
+    number::cmp_numbers,
+};
+
+// The kind of a JsonLike value as seen by partial_cmp.
+enum Kind<'a, V> {
+    Null,
+    Bool(bool),
+    Number(Number),
+    String(&'a str),
+    Array(&'a [V]),
+    Object,
+    Other, // a value without a JSON equivalent, like a TOML datetime
+}
+
+fn kind<V: JsonLike>(value: &V) -> Kind<'_, V> {
+    if value.is_null() {
+        Kind::Null
+    } else if let Some(b) = value.as_bool() {
+        Kind::Bool(b)
+    } else if let Some(n) = value.as_number() {
+        Kind::Number(n)
+    } else if let Some(s) = value.as_str() {
+        Kind::String(s)
+    } else if let Some(array) = value.as_array() {
+        Kind::Array(array)
+    } else if value.is_object() {
+        Kind::Object
+    } else {
+        Kind::Other
+    }
+}
+
+// Compares element by element, a prefix is less than the longer array.
+fn cmp_array<V: JsonLike, L: Collation + ?Sized>(a: &[V], b: &[V], c: &L) -> Option<Ordering> {
+    for (a, b) in a.iter().zip(b) {
+        match partial_cmp_collated(a, b, c)? {
+            Ordering::Equal => {}
+            ordering => return Some(ordering),
+        }
+    }
+    Some(a.len().cmp(&b.len()))
+}
+
+// Compares the key/value pairs sorted by key like arrays of pairs, keys before values.
+fn cmp_object<V: JsonLike, L: Collation + ?Sized>(a: &V, b: &V, c: &L) -> Option<Ordering> {
+    let mut a_keys = a.keys();
+    let mut b_keys = b.keys();
+    a_keys.sort_unstable();
+    b_keys.sort_unstable();
+    for (ka, kb) in a_keys.iter().zip(&b_keys) {
+        match ka.cmp(kb) {
+            Ordering::Equal => {}
+            ordering => return Some(ordering),
+        }
+        match partial_cmp_collated(a.get_key(ka)?, b.get_key(kb)?, c)? {
+            Ordering::Equal => {}
+            ordering => return Some(ordering),
+        }
+    }
+    Some(a_keys.len().cmp(&b_keys.len()))
+}
+
+/// Compares two JSON-like values, such as `serde_json::Value`s.
 ///
 /// Follows SQL JSON Operators.
 /// Comparing any Value with `Value::Null` returns None.
-/// `Value::Bool` is casted to a f64, when comparing with `Value::Number`.
+/// Numbers are compared by their exact value, see `number::cmp_numbers`.
+/// `Value::Bool` is casted to 1 or 0, when comparing with `Value::Number`.
 /// `Value::Bool` is always less than a String, Array, or Object.
 /// `Value::Number` is always less than a String, Array, or Object.
 /// `Value::String` is always less than an Array, or Object.
-/// Comparing a `Value::String` with a `Value::Number` trys to parse the String as a f64 for
-/// comparison.
-/// Arrays and Objects get compared by memory.
-pub fn partial_cmp(a: &Value, b: &Value) -> Option<Ordering> {
+/// Comparing a `Value::String` with a `Value::Number` compares the String as a number if it is a
+/// numeric literal, see `cast::affinity`, otherwise the Number is less.
+/// `Value::Array` is always less than an Object.
+/// Arrays are compared element by element, an Array is greater than its prefixes.
+/// Objects are compared like Arrays of their key/value pairs sorted by key, the key of a pair is
+/// compared before its value.
+/// Comparing Arrays or Objects returns None if that depends on comparing a Null.
+/// Values without a JSON equivalent, like a TOML datetime, are greater than any other value and
+/// can only be compared for equality.
+pub fn partial_cmp<V: JsonLike>(a: &V, b: &V) -> Option<Ordering> {
+    partial_cmp_collated(a, b, &Binary)
+}
+
+/// Compares like `partial_cmp`, Strings are compared with `collation` instead of by bytes.
+pub fn partial_cmp_collated<V: JsonLike, L: Collation + ?Sized>(
+    a: &V,
+    b: &V,
+    collation: &L,
+) -> Option<Ordering> {
     if a == b {
         return Some(Ordering::Equal);
     }
-    match (a, b) {
+    match (kind(a), kind(b)) {
         // Equal types
         // Anything with Null can't be compared
-        (Value::Null | _, Value::Null) | (Value::Null, _) => None,
-        (Value::Bool(a), Value::Bool(b)) => Some(a.cmp(b)),
-        (Value::Number(a), Value::Number(b)) => {
-            // Try to be as precise as possible
-            if let (Some(a), Some(ref b)) = (a.as_i64(), b.as_i64()) {
-                a.partial_cmp(b)
-            } else if let (Some(a), Some(ref b)) = (a.as_u64(), b.as_u64()) {
-                a.partial_cmp(b)
-            } else if let (Some(a), Some(ref b)) = (a.as_f64(), b.as_f64()) {
-                a.partial_cmp(b)
-            } else {
-                None
-            }
-        }
-        (Value::String(a), Value::String(b)) => a.partial_cmp(b),
+        (Kind::Null | _, Kind::Null) | (Kind::Null, _) => None,
+        (Kind::Bool(a), Kind::Bool(b)) => Some(a.cmp(&b)),
+        (Kind::Number(a), Kind::Number(b)) => Some(cmp_numbers(&a, &b)),
+        (Kind::String(a), Kind::String(b)) => Some(collation.compare(a, b)),
 
-        // Unequal types with casting
-        (Value::Number(a), Value::Bool(b)) => {
-            a.as_f64().and_then(|ref a| a.partial_cmp(&f64::from(*b)))
-        }
-        (Value::Bool(a), Value::Number(b)) => {
-            b.as_f64().and_then(|ref b| f64::from(*a).partial_cmp(b))
-        }
+        // Unequal types with casting, the same as `cast::to_numeric` and `cast::affinity`
+        (Kind::Number(a), Kind::Bool(_)) => Some(cmp_numbers(&a, &cast::to_numeric(b)?)),
+        (Kind::Bool(_), Kind::Number(b)) => Some(cmp_numbers(&cast::to_numeric(a)?, &b)),
         // Bool is always less than a String, Array, Object
-        (Value::Bool(_), _) => Some(Ordering::Less),
-        (_, Value::Bool(_)) => Some(Ordering::Greater),
-        // Try to convert String to f64
-        (Value::Number(a), Value::String(b)) => {
-            let b: Result<f64, _> = b.parse();
-            if let (Some(a), Ok(ref b)) = (a.as_f64(), b) {
-                a.partial_cmp(b)
-            } else {
-                Some(Ordering::Less)
-            }
-        }
-        (Value::String(a), Value::Number(b)) => {
-            let a: Result<f64, _> = a.parse();
-            if let (Some(ref b), Ok(a)) = (b.as_f64(), a) {
-                a.partial_cmp(b)
-            } else {
-                Some(Ordering::Less)
-            }
-        }
+        (Kind::Bool(_), _) => Some(Ordering::Less),
+        (_, Kind::Bool(_)) => Some(Ordering::Greater),
+        // Strings which are numeric literals compare as numbers
+        (Kind::Number(a), Kind::String(b)) => match cast::affinity(b) {
+            Some(ref b) => Some(cmp_numbers(&a, b)),
+            None => Some(Ordering::Less),
+        },
+        (Kind::String(a), Kind::Number(ref b)) => match cast::affinity(a) {
+            Some(ref a) => Some(cmp_numbers(a, b)),
+            None => Some(Ordering::Greater),
+        },
 
         // Integer or Real values are less than String, Array, Object
-        (Value::Number(_), _) => Some(Ordering::Less),
-        (_, Value::Number(_)) => Some(Ordering::Greater),
+        (Kind::Number(_), _) => Some(Ordering::Less),
+        (_, Kind::Number(_)) => Some(Ordering::Greater),
         // String values are less than Array, Object
-        (Value::String(_), _) => Some(Ordering::Less),
-        (_, Value::String(_)) => Some(Ordering::Greater),
-
-        // Compare Arrays and Objects by memory size
-        (Value::Array(a), Value::Array(b)) => size_of_val(a).partial_cmp(&size_of_val(b)),
-        (Value::Array(a), Value::Object(b)) => size_of_val(a).partial_cmp(&size_of_val(b)),
-        (Value::Object(a), Value::Array(b)) => size_of_val(a).partial_cmp(&size_of_val(b)),
-        (Value::Object(a), Value::Object(b)) => size_of_val(a).partial_cmp(&size_of_val(b)),
+        (Kind::String(_), _) => Some(Ordering::Less),
+        (_, Kind::String(_)) => Some(Ordering::Greater),
+
+        // Arrays are less than Objects
+        (Kind::Array(a), Kind::Array(b)) => cmp_array(a, b, collation),
+        (Kind::Array(_), _) => Some(Ordering::Less),
+        (_, Kind::Array(_)) => Some(Ordering::Greater),
+        (Kind::Object, Kind::Object) => cmp_object(a, b, collation),
+        (Kind::Object, _) => Some(Ordering::Less),
+        (_, Kind::Object) => Some(Ordering::Greater),
+        // Unequal other values can't be compared
+        (Kind::Other, Kind::Other) => None,
     }
 }
 
 /// Wraps a reference to a `serde_json::Value` and provides `PartialOrd` implementation.
+///
+/// Values are compared with the comparator `C`, by default `partial_cmp`.
 #[derive(Debug, Clone, PartialEq, Eq)]
-pub struct JsonValue<'a>(&'a Value);
+pub struct JsonValue<'a, C = Sqlite>(&'a Value, C);
 
 impl<'a> JsonValue<'a> {
     pub fn new(value: &'a Value) -> Self {
-        JsonValue(value)
+        JsonValue(value, Sqlite)
+    }
+}
+
+impl<'a, C: Comparator> JsonValue<'a, C> {
+    pub fn with_comparator(value: &'a Value, comparator: C) -> Self {
+        JsonValue(value, comparator)
+    }
+
+    pub fn comparator(&self) -> &C {
+        &self.1
     }
 }
 
-impl<'a> PartialOrd for JsonValue<'a> {
+impl<'a, C: Comparator + PartialEq> PartialOrd for JsonValue<'a, C> {
     #[inline]
     fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
-        partial_cmp(self.0, other.0)
+        self.1.partial_cmp(self.0, other.0)
     }
 }
 
-impl<'a> From<&'a Value> for JsonValue<'a> {
+impl<'a, C: Default> From<&'a Value> for JsonValue<'a, C> {
     #[inline]
     fn from(value: &'a Value) -> Self {
-        JsonValue(value)
+        JsonValue(value, C::default())
     }
 }
 
-impl<'a> Deref for JsonValue<'a> {
+impl<'a, C> Deref for JsonValue<'a, C> {
     type Target = serde_json::Value;
 
     #[inline]
@@ -117,6 +193,7 @@ mod tests {
     use serde_json::json;
 
     use super::*;
+    use crate::compare::{JavaScript, MySql, Postgres};
 
     #[test]
     fn test_partial_cmp() {
@@ -153,6 +230,86 @@ mod tests {
             ),
             (json!([]), Value::Bool(false), Some(Ordering::Greater)),
             (json!([0, 1]), Value::Bool(false), Some(Ordering::Greater)),
+            // Numbers of mixed representations
+            (json!(-1), json!(u64::MAX), Some(Ordering::Less)),
+            (json!(i64::MIN), json!(u64::MAX), Some(Ordering::Less)),
+            (
+                json!(9007199254740993u64),
+                json!(9007199254740992.0),
+                Some(Ordering::Greater),
+            ),
+            (
+                json!(u64::MAX),
+                json!(18446744073709551616.0),
+                Some(Ordering::Less),
+            ),
+            (json!(2), json!(2.0), Some(Ordering::Equal)),
+            // Number and String
+            (json!(10), json!("9"), Some(Ordering::Greater)),
+            (json!(3), json!(" 3.0 "), Some(Ordering::Equal)),
+            (json!("-1e2"), json!(-99), Some(Ordering::Less)),
+            (json!(12), json!("12abc"), Some(Ordering::Less)),
+            (json!("12abc"), json!(12), Some(Ordering::Greater)),
+            // Literals which overflow a Real aren't numbers
+            (json!(5), json!("1e999"), Some(Ordering::Less)),
+            (json!(5), json!("-1e999"), Some(Ordering::Less)),
+            (json!("-1e999"), json!(5), Some(Ordering::Greater)),
+            (json!(1e300), json!("inf"), Some(Ordering::Less)),
+            (json!(0), json!(""), Some(Ordering::Less)),
+            // Array
+            (json!([]), json!([]), Some(Ordering::Equal)),
+            (json!([]), json!([0]), Some(Ordering::Less)),
+            (json!([1, 2]), json!([1]), Some(Ordering::Greater)),
+            (json!([1, 2]), json!([1, 3]), Some(Ordering::Less)),
+            (json!([2]), json!([1, 3]), Some(Ordering::Greater)),
+            (json!(["b"]), json!(["a", "z"]), Some(Ordering::Greater)),
+            (json!([1, "a"]), json!([true, "b"]), Some(Ordering::Less)),
+            (json!([[1, 2], 0]), json!([[1, 3]]), Some(Ordering::Less)),
+            (json!([{"a": 1}]), json!([[9]]), Some(Ordering::Greater)),
+            (json!([null, 1]), json!([null, 2]), Some(Ordering::Less)),
+            (json!([1, null]), json!([2, 0]), Some(Ordering::Less)),
+            (json!([null]), json!([1]), None),
+            (json!([0, 1]), json!("[0, 1]"), Some(Ordering::Greater)),
+            (json!([]), json!(1e300), Some(Ordering::Greater)),
+            (json!([]), json!({}), Some(Ordering::Less)),
+            // Object
+            (json!({}), json!({}), Some(Ordering::Equal)),
+            (json!({}), json!({"a": 1}), Some(Ordering::Less)),
+            (json!({"a": 1}), json!({"a": 2}), Some(Ordering::Less)),
+            (
+                json!({"a": 1, "b": 2}),
+                json!({"b": 2, "a": 1}),
+                Some(Ordering::Equal),
+            ),
+            (
+                json!({"a": 1, "b": 2}),
+                json!({"a": 1}),
+                Some(Ordering::Greater),
+            ),
+            (json!({"a": 9}), json!({"b": 0}), Some(Ordering::Less)),
+            (
+                json!({"b": 0}),
+                json!({"a": 9, "c": 0}),
+                Some(Ordering::Greater),
+            ),
+            (
+                json!({"a": 1, "c": 0}),
+                json!({"a": 1, "b": 9}),
+                Some(Ordering::Greater),
+            ),
+            (
+                json!({"a": {"x": [1]}}),
+                json!({"a": {"x": [2]}}),
+                Some(Ordering::Less),
+            ),
+            (json!({"a": null}), json!({"a": 1}), None),
+            (
+                json!({"a": null, "b": 1}),
+                json!({"a": null, "b": 2}),
+                Some(Ordering::Less),
+            ),
+            (json!({"a": 1}), json!("{}"), Some(Ordering::Greater)),
+            (json!({}), json!([[]]), Some(Ordering::Greater)),
         ];
         for (ref a, ref b, expected) in tests {
             let a: JsonValue = a.into();
@@ -165,4 +322,97 @@ mod tests {
             );
         }
     }
+
+    #[test]
+    fn test_json_value_comparator() {
+        let (null, one, text) = (json!(null), json!(1), json!("a"));
+        let a: JsonValue<Postgres> = (&null).into();
+        let b: JsonValue<Postgres> = (&one).into();
+        assert!(a < b);
+        let a = JsonValue::with_comparator(&one, MySql);
+        let b = JsonValue::with_comparator(&text, MySql);
+        assert!(a < b);
+        let a = JsonValue::with_comparator(&null, JavaScript);
+        let b = JsonValue::with_comparator(&text, JavaScript);
+        assert_eq!(PartialOrd::partial_cmp(&a, &b), None);
+        assert_eq!(
+            PartialOrd::partial_cmp(&JsonValue::new(&null), &JsonValue::new(&one)),
+            None
+        );
+    }
+
+    #[test]
+    fn test_sort() {
+        let mut values = vec![
+            json!({"b": 1}),
+            json!([1, [2]]),
+            json!({"a": 2}),
+            json!("x"),
+            json!([1]),
+            json!({"a": 1, "b": 1}),
+            json!([0, 5]),
+            json!(3),
+            json!({"a": 1}),
+            json!([1, [1, 9]]),
+            json!(true),
+        ];
+        values.sort_by(|a, b| partial_cmp(a, b).unwrap());
+        assert_eq!(
+            values,
+            vec![
+                json!(true),
+                json!(3),
+                json!("x"),
+                json!([0, 5]),
+                json!([1]),
+                json!([1, [1, 9]]),
+                json!([1, [2]]),
+                json!({"a": 1}),
+                json!({"a": 1, "b": 1}),
+                json!({"a": 2}),
+                json!({"b": 1}),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_cast_consistency() {
+        let values = vec![
+            json!(true),
+            json!(false),
+            json!(0),
+            json!(1),
+            json!(-2.5),
+            json!(u64::MAX),
+            json!("1"),
+            json!(" 1.0 "),
+            json!("-2.5"),
+            json!("1e3"),
+            json!("12abc"),
+            json!("1e999"),
+            json!(""),
+        ];
+        // A number compares like a Bool cast to NUMERIC and like a numeric String cast to REAL.
+        for a in values.iter().filter(|v| v.is_number()) {
+            for b in &values {
+                let cast = match b {
+                    Value::String(s) if cast::affinity(s).is_none() => {
+                        assert_eq!(partial_cmp(a, b), Some(Ordering::Less));
+                        assert_eq!(partial_cmp(b, a), Some(Ordering::Greater));
+                        continue;
+                    }
+                    _ => cast::to_real(b).unwrap(),
+                };
+                let expected = a.as_f64().unwrap().partial_cmp(&cast);
+                assert_eq!(
+                    partial_cmp(a, b),
+                    expected,
+                    "expected {}.partial_cmp({}) to agree with CAST({} AS REAL)",
+                    a,
+                    b,
+                    b
+                );
+            }
+        }
+    }
 }
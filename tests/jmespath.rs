@@ -0,0 +1,64 @@
+use std::{fs, path::Path, str::FromStr};
+
+use jops::{jmespath::JmesPath, total::total_cmp};
+use serde_json::Value;
+
+// Runs every case of the JSON files in tests/jmespath, which use the format of the JMESPath
+// compliance suite at https://github.com/jmespath/jmespath.test. Results are compared with
+// `total_cmp`, so 1 equals 1.0. A case expecting a syntax error must fail to parse, other error
+// kinds must parse and fail to evaluate with an error starting with the kind, like
+// "invalid type" for invalid-type.
+#[test]
+fn compliance() {
+    let dir = Path::new(env!("CARGO_MANIFEST_DIR")).join("tests/jmespath");
+    let mut files: Vec<_> = fs::read_dir(&dir)
+        .unwrap()
+        .map(|entry| entry.unwrap().path())
+        .filter(|path| path.extension().is_some_and(|ext| ext == "json"))
+        .collect();
+    files.sort();
+    assert!(!files.is_empty(), "no test files in {}", dir.display());
+    let (mut cases, mut failures) = (0, Vec::new());
+    for path in files {
+        let name = path.file_name().unwrap().to_string_lossy().into_owned();
+        let suites: Vec<Value> = serde_json::from_str(&fs::read_to_string(&path).unwrap())
+            .unwrap_or_else(|err| panic!("invalid test file {}: {}", name, err));
+        for suite in &suites {
+            let given = &suite["given"];
+            for case in suite["cases"].as_array().unwrap() {
+                let expression = case["expression"].as_str().unwrap();
+                if case.get("bench").is_some() {
+                    continue;
+                }
+                cases += 1;
+                let compiled = JmesPath::from_str(expression);
+                let result = compiled.as_ref().map_err(|err| *err);
+                let result = result.and_then(|e| e.search(given));
+                let passed = match (case.get("result"), case.get("error")) {
+                    (Some(expected), _) => result
+                        .as_ref()
+                        .is_ok_and(|result| total_cmp(result, expected).is_eq()),
+                    (None, Some(error)) if error == "syntax" => compiled.is_err(),
+                    (None, Some(error)) => {
+                        let kind = error.as_str().unwrap().replace('-', " ");
+                        compiled.is_ok() && result.as_ref().is_err_and(|err| err.starts_with(&kind))
+                    }
+                    (None, None) => panic!("case without result or error in {}", name),
+                };
+                if !passed {
+                    failures.push(format!(
+                        "{}: {} on {}: expected {}, got {:?}",
+                        name, expression, given, case, result
+                    ));
+                }
+            }
+        }
+    }
+    assert!(cases > 0, "no test cases in {}", dir.display());
+    assert!(
+        failures.is_empty(),
+        "{} cases failed:\n{}",
+        failures.len(),
+        failures.join("\n")
+    );
+}